@@ -1,32 +1,74 @@
-use crate::llm::{Message, Role, LlmClient};
+use crate::llm::{Message, Role, LlmClient, LlmError, ToolCall, ToolResult};
 use super::protocol::{AgentUpdate, PlannedTool};
-use crate::mcp::MCPServerRegistry;
+use crate::config::{AgentPolicy, Persona};
+use crate::mcp::{MCPServerRegistry, TransportError};
 use anyhow::Result;
+use std::future::Future;
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use tokio::time::{timeout, Duration};
+use tokio::time::{timeout, Duration, Instant};
+use tokio_util::sync::CancellationToken;
 
+type AgentSender = tokio::sync::mpsc::UnboundedSender<AgentUpdate>;
+
+/// How one tool call is timed out and retried
+struct ToolCallLimits {
+    timeout: Duration,
+    retries: u32,
+    /// A timed out call may still have run, so it is only sent again when the server opts in
+    retry_timeouts: bool,
+}
+
+/// Whether a failed tool call may succeed when sent again: only when the server could not be reached.
+/// Errors the server reports (invalid params, unknown tool) come back the same way every time.
+fn is_retryable_tool_error(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<TransportError>().is_some()
+}
 
 pub struct AgenticLoop {
     pub mcp_registry: Arc<RwLock<MCPServerRegistry>>,
     pub llm_client: Arc<dyn LlmClient>,
     pub tool_logger: super::tool_logger::ToolLogger,
+    pub policy: AgentPolicy,
+    /// Fired when the user stops the generation
+    pub cancel: CancellationToken,
+    /// Persona of the conversation; its tool set narrows the enabled tools
+    pub persona: Option<Persona>,
 }
 
 impl AgenticLoop {
-    pub fn new(mcp_registry: Arc<RwLock<MCPServerRegistry>>, llm_client: Arc<dyn LlmClient>) -> Self {
+    pub fn new(mcp_registry: Arc<RwLock<MCPServerRegistry>>, llm_client: Arc<dyn LlmClient>, policy: AgentPolicy, cancel: CancellationToken) -> Self {
         Self {
             mcp_registry,
             llm_client,
             tool_logger: super::tool_logger::ToolLogger::new("agentic_tool_calls.log".to_string()),
+            policy,
+            cancel,
+            persona: None,
+        }
+    }
+    
+    /// Whether the conversation's persona may call `tool_name`
+    fn persona_allows(&self, registry: &MCPServerRegistry, tool_name: &str) -> bool {
+        match &self.persona {
+            Some(persona) => {
+                let server_name = registry.get_route_for_tool(tool_name).map(|route| route.server_name.as_str()).unwrap_or_default();
+                persona.allows_tool(tool_name, server_name)
+            }
+            None => true,
         }
     }
     
-    pub async fn process_message(&mut self, mut messages: Vec<Message>, agent_tx: Option<tokio::sync::mpsc::UnboundedSender<AgentUpdate>>, _message_id: Option<uuid::Uuid>) -> Result<String> {
+    pub async fn process_message(&mut self, mut messages: Vec<Message>, agent_tx: Option<AgentSender>, _message_id: Option<uuid::Uuid>) -> Result<String> {
         
         let mut iteration = 0;
+        let mut tool_calls_made: u32 = 0;
+        let deadline = Instant::now() + Duration::from_secs(self.policy.max_duration_secs);
         
         loop {
+            if self.cancel.is_cancelled() {
+                return Err(LlmError::Cancelled.into());
+            }
             iteration += 1;
             self.tool_logger.log_iteration_start(iteration)?;
             let _ = self.tool_logger.log_begin_turn(iteration);
@@ -40,22 +82,41 @@ impl AgenticLoop {
                 });
             }
             
+            // Once a limit is hit the model gets one last call, without tools, to wrap up
+            let limit = if iteration > self.policy.max_iterations {
+                Some(format!("the limit of {} iterations", self.policy.max_iterations))
+            } else if tool_calls_made >= self.policy.max_tool_calls {
+                Some(format!("the limit of {} tool calls", self.policy.max_tool_calls))
+            } else if Instant::now() >= deadline {
+                Some(format!("the time budget of {} seconds", self.policy.max_duration_secs))
+            } else {
+                None
+            };
+            if let Some(limit) = limit {
+                return self.finish_at_limit(messages, &limit, turn_id, iteration, agent_tx.as_ref()).await;
+            }
+            
             // Get enabled tools from MCP registry
             let available_tools = {
                 let registry = self.mcp_registry.read().await;
-                let tools = registry.get_enabled_tools();
+                let mut tools = registry.get_enabled_tools();
+                tools.retain(|tool| self.persona_allows(&registry, &tool.name));
                 log::debug!("🔧 Enabled tools count: {}", tools.len());
                 tools
             };
             
             // Call LLM with current messages and available tools
-            let response = match self.llm_client.send_message_with_tools(
+            let response = match crate::llm::cancellable(&self.cancel, self.llm_client.send_message_with_tools(
                 messages.clone(), 
                 available_tools, 
                 None, 
                 None
-            ).await {
+            )).await {
                 Ok(response) => response,
+                Err(LlmError::Cancelled) => {
+                    log::info!("⏹️ Generation cancelled during model call");
+                    return Err(LlmError::Cancelled.into());
+                }
                 Err(e) => {
                     log::error!("❌ LLM call failed: {}", e);
                     // Send model error via AgentUpdate
@@ -71,6 +132,12 @@ impl AgenticLoop {
             
             
             
+            if let (Some(tx), Some(reasoning)) = (agent_tx.as_ref(), response.reasoning.as_ref()) {
+                if !reasoning.text.is_empty() {
+                    let _ = tx.send(AgentUpdate::ReasoningDelta { turn_id, text_chunk: reasoning.text.clone() });
+                }
+            }
+            
             // Send assistant content and planned tools via AgentUpdate
             if !response.tool_calls.is_empty() {
                 if let Some(tx) = agent_tx.as_ref() {
@@ -103,7 +170,7 @@ impl AgenticLoop {
                 return Ok(response.content);
             }
             
-            // Execute tool calls and get results (with timeout & simple retries)
+            // Execute tool calls and get results (with timeout & retries per policy)
             let mut tool_results = Vec::new();
             let mut started_ids: std::collections::HashSet<String> = std::collections::HashSet::new();
             for (_tool_idx, tool_call) in response.tool_calls.iter().enumerate() {
@@ -118,36 +185,19 @@ impl AgenticLoop {
                     }
                 }
                 
-                // Execute tool with timeout and up to 2 retries
-                let mut attempt: u8 = 0;
-                let max_retries: u8 = 2;
-                let per_call_timeout = Duration::from_secs(20);
-                let result = loop {
-                    attempt += 1;
-                    let call_future = async {
-                        let mut registry = self.mcp_registry.write().await;
-                        registry.call_tool(tool_call.clone()).await
-                    };
-                    match timeout(per_call_timeout, call_future).await {
-                        Ok(Ok(result)) => break result,
-                        Ok(Err(e)) => {
-                            // Report error and decide retryability
-                            if let Some(tx) = agent_tx.as_ref() {
-                                let _ = tx.send(AgentUpdate::ToolError { turn_id, tool_call_id: tool_call.id.clone(), name: tool_call.name.clone(), error: e.to_string(), retryable: attempt <= max_retries });
-                            }
-                            if attempt > max_retries { break crate::llm::ToolResult { content: format!("Error: {}", e), is_error: true }; }
-                            continue;
-                        }
-                        Err(_) => {
-                            // Timeout
-                            let err_msg = format!("Timeout after {:?}", per_call_timeout);
-                            if let Some(tx) = agent_tx.as_ref() {
-                                let _ = tx.send(AgentUpdate::ToolError { turn_id, tool_call_id: tool_call.id.clone(), name: tool_call.name.clone(), error: err_msg, retryable: attempt <= max_retries });
-                            }
-                            if attempt > max_retries { break crate::llm::ToolResult { content: "Timeout".to_string(), is_error: true }; }
-                            continue;
-                        }
-                    }
+                if self.cancel.is_cancelled() {
+                    log::info!("⏹️ Generation cancelled before tool {}", tool_call.name);
+                    return Err(LlmError::Cancelled.into());
+                }
+                
+                // Every requested call needs a result, so calls over the limits are answered as skipped
+                let result = if tool_calls_made >= self.policy.max_tool_calls {
+                    ToolResult { content: format!("Skipped: the limit of {} tool calls was reached", self.policy.max_tool_calls), is_error: true }
+                } else if Instant::now() >= deadline {
+                    ToolResult { content: "Skipped: the time budget for this request ran out".to_string(), is_error: true }
+                } else {
+                    tool_calls_made += 1;
+                    self.execute_tool(tool_call, turn_id, deadline, agent_tx.as_ref()).await
                 };
                 
                 // Log tool result
@@ -168,8 +218,11 @@ impl AgenticLoop {
                 tool_results.push(result_message);
             }
             
-            // Add assistant message with tool calls to message history
-            messages.push(Message::new_with_tool_calls(Role::Assistant, response.content, response.tool_calls.clone()));
+            // Add assistant message with tool calls to message history, keeping its reasoning
+            // so backends that need it (Anthropic's signed thinking) can send it back
+            let mut assistant_message = Message::new_with_tool_calls(Role::Assistant, response.content, response.tool_calls.clone());
+            assistant_message.reasoning = response.reasoning;
+            messages.push(assistant_message);
             
             // Add tool results to message history
             messages.extend(tool_results);
@@ -181,5 +234,317 @@ impl AgenticLoop {
             let _ = self.tool_logger.log_end_turn(iteration);
         }
     }
+    
+    /// Run one tool call, retrying transport failures (and timeouts, if the server allows) with exponential backoff
+    async fn execute_tool(&self, tool_call: &ToolCall, turn_id: uuid::Uuid, deadline: Instant, agent_tx: Option<&AgentSender>) -> ToolResult {
+        let report_error = |error: &str, retryable: bool| {
+            if let Some(tx) = agent_tx {
+                let _ = tx.send(AgentUpdate::ToolError { turn_id, tool_call_id: tool_call.id.clone(), name: tool_call.name.clone(), error: error.to_string(), retryable });
+            }
+        };
+        
+        // Routing problems (unknown tool, disabled server) will not go away on retry
+        let limits = {
+            let registry = self.mcp_registry.read().await;
+            let route = match registry.get_route_for_tool(&tool_call.name) {
+                Ok(route) => route.clone(),
+                Err(e) => {
+                    report_error(&e.to_string(), false);
+                    return ToolResult { content: e.to_string(), is_error: true };
+                }
+            };
+            if !registry.is_server_enabled(&route.server_name) {
+                let error = format!("Server {} is disabled", route.server_name);
+                report_error(&error, false);
+                return ToolResult { content: error, is_error: true };
+            }
+            if !self.persona_allows(&registry, &tool_call.name) {
+                let error = format!("Tool {} is not available to this assistant", tool_call.name);
+                report_error(&error, false);
+                return ToolResult { content: error, is_error: true };
+            }
+            let server_config = registry.server_configs.get(&route.server_name);
+            ToolCallLimits {
+                timeout: Duration::from_secs(server_config.and_then(|c| c.tool_timeout_secs).unwrap_or(self.policy.tool_timeout_secs)),
+                retries: server_config.and_then(|c| c.tool_retries).unwrap_or(self.policy.tool_retries),
+                retry_timeouts: server_config.is_some_and(|c| c.retry_timeouts),
+            }
+        };
+        
+        self.call_with_retries(tool_call, &limits, deadline, report_error, || async {
+            let mut registry = self.mcp_registry.write().await;
+            registry.call_tool(tool_call.clone(), &self.cancel).await
+        }).await
+    }
+    
+    /// Run `call` until it succeeds, fails for good, or runs out of retries or time
+    async fn call_with_retries<F, Fut>(
+        &self,
+        tool_call: &ToolCall,
+        limits: &ToolCallLimits,
+        deadline: Instant,
+        report_error: impl Fn(&str, bool),
+        call: F,
+    ) -> ToolResult
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<ToolResult>>,
+    {
+        let mut attempt: u32 = 0;
+        let mut backoff = Duration::from_millis(self.policy.retry_backoff_ms);
+        loop {
+            attempt += 1;
+            // A single call never outlives the loop's time budget
+            let per_call_timeout = limits.timeout.min(deadline.saturating_duration_since(Instant::now()));
+            let (error, may_retry) = match timeout(per_call_timeout, call()).await {
+                Ok(Ok(result)) => return result,
+                Ok(Err(e)) => (e.to_string(), is_retryable_tool_error(&e)),
+                Err(_) => (format!("Timeout after {:?}", per_call_timeout), limits.retry_timeouts),
+            };
+            if self.cancel.is_cancelled() {
+                report_error("Cancelled", false);
+                return ToolResult { content: "Cancelled".to_string(), is_error: true };
+            }
+            
+            let retryable = may_retry && attempt <= limits.retries && Instant::now() + backoff < deadline;
+            report_error(&error, retryable);
+            if !retryable {
+                return ToolResult { content: error, is_error: true };
+            }
+            log::debug!("Retrying tool {} in {:?} (attempt {})", tool_call.name, backoff, attempt + 1);
+            tokio::select! {
+                _ = tokio::time::sleep(backoff) => {}
+                _ = self.cancel.cancelled() => {
+                    return ToolResult { content: "Cancelled".to_string(), is_error: true };
+                }
+            }
+            backoff *= 2;
+        }
+    }
+    
+    /// Ask the model for a final answer without tools after a policy limit stopped the loop
+    async fn finish_at_limit(&mut self, messages: Vec<Message>, limit: &str, turn_id: uuid::Uuid, iteration: u32, agent_tx: Option<&AgentSender>) -> Result<String> {
+        log::warn!("⏹️ Agent loop stopped after reaching {}", limit);
+        // Backends reject tool_use/tool_result blocks in a request without tool definitions
+        let mut messages = without_tool_blocks(messages);
+        push_user_text(
+            &mut messages,
+            format!(
+                "You have reached {} for this request, so no more tools can be called. \
+                 Answer now with what you have: summarize what was done, what is still open, \
+                 and mention that the limit was reached.",
+                limit
+            ),
+        );
+        
+        let response = match crate::llm::cancellable(&self.cancel, self.llm_client.send_message_with_tools(messages, Vec::new(), None, None)).await {
+            Ok(response) => response,
+            Err(LlmError::Cancelled) => return Err(LlmError::Cancelled.into()),
+            Err(e) => {
+                log::error!("❌ LLM call failed: {}", e);
+                if let Some(tx) = agent_tx {
+                    let _ = tx.send(AgentUpdate::ModelError {
+                        turn_id,
+                        error: format!("Model communication failed: {}", e)
+                    });
+                }
+                return Err(anyhow::anyhow!("LLM call failed: {}", e));
+            }
+        };
+        
+        self.tool_logger.log_final_response(&response.content, iteration)?;
+        if let Some(tx) = agent_tx {
+            if let Some(reasoning) = response.reasoning.as_ref().filter(|reasoning| !reasoning.text.is_empty()) {
+                let _ = tx.send(AgentUpdate::ReasoningDelta { turn_id, text_chunk: reasoning.text.clone() });
+            }
+            let _ = tx.send(AgentUpdate::AssistantComplete { turn_id, full_text: response.content.clone() });
+            let _ = tx.send(AgentUpdate::EndTurn { turn_id });
+            let _ = tx.send(AgentUpdate::EndConversation { final_text: response.content.clone() });
+        }
+        let _ = self.tool_logger.log_end_turn(iteration);
+        Ok(response.content)
+    }
+}
+
+/// The history with tool calls and their results rewritten as plain text
+fn without_tool_blocks(messages: Vec<Message>) -> Vec<Message> {
+    let mut flattened: Vec<Message> = Vec::with_capacity(messages.len());
+    for message in messages {
+        if message.role == Role::Tool {
+            let text = format!(
+                "Result of tool call {}:\n{}",
+                message.tool_call_id.as_deref().unwrap_or_default(),
+                message.content
+            );
+            push_user_text(&mut flattened, text);
+        } else if let Some(tool_calls) = &message.tool_calls {
+            let mut text = message.content.clone();
+            for call in tool_calls {
+                if !text.is_empty() {
+                    text.push('\n');
+                }
+                text.push_str(&format!("[Called tool {} ({}) with {}]", call.name, call.id, call.parameters));
+            }
+            // Signed reasoning belongs to the tool_use blocks, so it is dropped with them
+            let mut plain = Message::new(message.role, text);
+            plain.timestamp = message.timestamp;
+            flattened.push(plain);
+        } else {
+            flattened.push(message);
+        }
+    }
+    flattened
+}
+
+/// Add user text, joining it to a trailing user message so roles keep alternating
+fn push_user_text(messages: &mut Vec<Message>, text: String) {
+    match messages.last_mut() {
+        Some(last) if last.role == Role::User && last.attachments.is_none() => {
+            last.content.push_str("\n\n");
+            last.content.push_str(&text);
+        }
+        _ => messages.push(Message::new(Role::User, text)),
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{ChatResponse, StreamChunk, ToolDefinition};
+    use async_trait::async_trait;
+    use futures::Stream;
+    use std::pin::Pin;
+    use std::sync::Mutex;
+
+    /// Asks for a tool, then answers; like Anthropic it rejects tool blocks sent without tool definitions
+    struct ToolHappyClient {
+        requests: Mutex<Vec<Vec<Message>>>,
+    }
+
+    #[async_trait]
+    impl LlmClient for ToolHappyClient {
+        async fn send_message_stream(
+            &self,
+            _messages: Vec<Message>,
+            _temperature: Option<f32>,
+            _max_tokens: Option<u32>,
+        ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamChunk, LlmError>> + Send>>, LlmError> {
+            Err(LlmError::Config("not streamed".to_string()))
+        }
+
+        async fn send_message_with_tools(
+            &self,
+            messages: Vec<Message>,
+            available_tools: Vec<ToolDefinition>,
+            _temperature: Option<f32>,
+            _max_tokens: Option<u32>,
+        ) -> Result<ChatResponse, LlmError> {
+            let has_tool_blocks = messages.iter().any(|msg| msg.role == Role::Tool || msg.tool_calls.is_some());
+            let mut requests = self.requests.lock().unwrap();
+            requests.push(messages);
+            if available_tools.is_empty() && has_tool_blocks {
+                return Err(LlmError::Api("tool_use blocks require tools".to_string()));
+            }
+            if requests.len() > 1 {
+                return Ok(ChatResponse { content: "Summary".to_string(), tool_calls: Vec::new(), reasoning: None });
+            }
+            Ok(ChatResponse {
+                content: String::new(),
+                tool_calls: vec![ToolCall { id: "call_1".to_string(), name: "lookup".to_string(), parameters: serde_json::json!({}) }],
+                reasoning: None,
+            })
+        }
+
+        async fn list_models(&self) -> Result<Vec<String>, LlmError> {
+            Ok(Vec::new())
+        }
+    }
+
+    fn test_agent(policy: AgentPolicy) -> AgenticLoop {
+        let client = Arc::new(ToolHappyClient { requests: Mutex::new(Vec::new()) });
+        AgenticLoop::new(Arc::new(RwLock::new(MCPServerRegistry::new())), client, policy, CancellationToken::new())
+    }
+
+    /// Run `outcomes` in order through `call_with_retries`, returning the result and the number of attempts
+    async fn run_retries(limits: ToolCallLimits, outcomes: Vec<Option<Result<ToolResult>>>) -> (ToolResult, usize) {
+        let agent = test_agent(AgentPolicy { retry_backoff_ms: 1, ..AgentPolicy::default() });
+        let tool_call = ToolCall { id: "call_1".to_string(), name: "files__write".to_string(), parameters: serde_json::json!({}) };
+        let outcomes = Mutex::new(outcomes.into_iter());
+        let attempts = Mutex::new(0);
+        let deadline = Instant::now() + Duration::from_secs(60);
+        let result = agent.call_with_retries(&tool_call, &limits, deadline, |_, _| {}, || {
+            *attempts.lock().unwrap() += 1;
+            let outcome = outcomes.lock().unwrap().next().flatten();
+            async move {
+                match outcome {
+                    Some(outcome) => outcome,
+                    // No outcome: the call hangs until it times out
+                    None => std::future::pending().await,
+                }
+            }
+        }).await;
+        let attempts = *attempts.lock().unwrap();
+        (result, attempts)
+    }
+
+    fn limits(retry_timeouts: bool) -> ToolCallLimits {
+        ToolCallLimits { timeout: Duration::from_millis(20), retries: 2, retry_timeouts }
+    }
+
+    fn success() -> Option<Result<ToolResult>> {
+        Some(Ok(ToolResult { content: "done".to_string(), is_error: false }))
+    }
+
+    #[tokio::test]
+    async fn test_transport_errors_are_retried() {
+        let lost = || Some(Err(TransportError("MCP server closed the connection before responding".to_string()).into()));
+        let (result, attempts) = run_retries(limits(false), vec![lost(), lost(), success()]).await;
+        assert!(!result.is_error);
+        assert_eq!(attempts, 3);
+
+        // Retries run out
+        let (result, attempts) = run_retries(limits(false), vec![lost(), lost(), lost(), success()]).await;
+        assert!(result.is_error);
+        assert_eq!(attempts, 3);
+    }
+
+    #[tokio::test]
+    async fn test_other_tool_errors_are_not_retried() {
+        let (result, attempts) = run_retries(limits(false), vec![Some(Err(anyhow::anyhow!("Tool files__write not found"))), success()]).await;
+        assert!(result.is_error);
+        assert_eq!(attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_timeouts_are_retried_only_when_allowed() {
+        let (result, attempts) = run_retries(limits(false), vec![None, success()]).await;
+        assert!(result.content.starts_with("Timeout"));
+        assert_eq!(attempts, 1);
+
+        let (result, attempts) = run_retries(limits(true), vec![None, success()]).await;
+        assert!(!result.is_error);
+        assert_eq!(attempts, 2);
+    }
+
+    #[tokio::test]
+    async fn test_iteration_limit_asks_for_summary() {
+        let client = Arc::new(ToolHappyClient { requests: Mutex::new(Vec::new()) });
+        let policy = AgentPolicy { max_iterations: 1, ..AgentPolicy::default() };
+        let mut agent = AgenticLoop::new(Arc::new(RwLock::new(MCPServerRegistry::new())), client.clone(), policy, CancellationToken::new());
+        let log_file = std::env::temp_dir().join("test_iteration_limit.log");
+        agent.tool_logger = super::super::tool_logger::ToolLogger::new(log_file.to_string_lossy().into_owned());
+
+        // The tool is unknown to the empty registry, so its call is answered with an error result
+        let answer = agent.process_message(vec![Message::new(Role::User, "Look it up".to_string())], None, None).await.unwrap();
+        assert_eq!(answer, "Summary");
+
+        let requests = client.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        let last = requests.last().unwrap();
+        assert!(last.iter().all(|msg| msg.role != Role::Tool && msg.tool_calls.is_none()));
+        assert!(last.iter().any(|msg| msg.content.contains("[Called tool lookup (call_1)")));
+        assert!(last.last().unwrap().content.contains("limit of 1 iterations"));
+
+        let _ = std::fs::remove_file(&log_file);
+    }
+}
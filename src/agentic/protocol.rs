@@ -24,6 +24,11 @@ pub enum AgentUpdate {
         turn_id: Uuid,
         full_text: String,
     },
+    /// Thinking or reasoning summary text, shown apart from the answer
+    ReasoningDelta {
+        turn_id: Uuid,
+        text_chunk: String,
+    },
     ToolPlanned {
         turn_id: Uuid,
         plan_items: Vec<PlannedTool>,
@@ -3,15 +3,29 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
 
-#[derive(Debug, Deserialize, Clone, Serialize)]
+pub mod watcher;
+
+#[derive(Debug, Deserialize, Clone, Serialize, PartialEq)]
 pub struct LlmProfile {
     #[serde(default = "default_backend")]
-    pub backend: String,  // "openai", "anthropic", "deepseek", "ollama", "gemini"
+    pub backend: String,  // "openai", "openai-responses", "anthropic", "deepseek", "azure", "ollama", "gemini"
     pub api_key: String,
     pub model: String,
     pub endpoint: String,
     pub temperature: Option<f32>,
     pub max_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinking_budget: Option<u32>, // Extended thinking tokens (Anthropic, Gemini); None leaves it off
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback: Option<String>, // Profile to fail over to once retries are exhausted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent: Option<AgentPolicy>, // Overrides the global [agent] policy for this profile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ollama: Option<OllamaSettings>, // Only read by the "ollama" backend
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub azure: Option<AzureSettings>, // Only read by the "azure" backend
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub responses: Option<ResponsesSettings>, // Only read by the "openai-responses" backend
 }
 
 fn default_backend() -> String {
@@ -27,17 +41,269 @@ impl Default for LlmProfile {
             endpoint: "https://api.openai.com/v1".to_string(),
             temperature: Some(0.7),
             max_tokens: Some(1000),
+            thinking_budget: None,
+            fallback: None,
+            agent: None,
+            ollama: None,
+            azure: None,
+            responses: None,
+        }
+    }
+}
+
+/// Which HTTP API an Ollama profile talks to
+#[derive(Debug, Deserialize, Clone, Copy, Serialize, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OllamaApi {
+    /// OpenAI-compatible `/v1/chat/completions`
+    #[default]
+    OpenAI,
+    /// Ollama's own `/api/chat`
+    Native,
+}
+
+/// Settings for the "ollama" backend; everything except `api` needs the native API
+#[derive(Debug, Deserialize, Clone, Serialize, PartialEq, Default)]
+#[serde(default)]
+pub struct OllamaSettings {
+    pub api: OllamaApi,
+    /// Context window size (`options.num_ctx`)
+    pub num_ctx: Option<u32>,
+    /// How long the model stays loaded after a request, e.g. "10m" or "-1"
+    pub keep_alive: Option<String>,
+    /// "json" to force JSON output
+    pub format: Option<String>,
+}
+
+/// How requests to an Azure OpenAI resource are authenticated
+#[derive(Debug, Deserialize, Clone, Copy, Serialize, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum AzureAuth {
+    /// Resource key sent in the `api-key` header
+    #[default]
+    ApiKey,
+    /// Microsoft Entra ID access token sent as a `Bearer` token
+    Entra,
+}
+
+/// Settings for the "azure" backend
+#[derive(Debug, Deserialize, Clone, Serialize, PartialEq)]
+#[serde(default)]
+pub struct AzureSettings {
+    /// Deployment name; defaults to the profile's `model`
+    pub deployment: Option<String>,
+    /// Value of the `api-version` query parameter
+    pub api_version: String,
+    pub auth: AzureAuth,
+    /// Command printing an Entra ID token, used instead of `api_key` when set
+    pub token_command: Option<String>,
+}
+
+impl Default for AzureSettings {
+    fn default() -> Self {
+        Self {
+            deployment: None,
+            api_version: "2024-10-21".to_string(),
+            auth: AzureAuth::ApiKey,
+            token_command: None,
+        }
+    }
+}
+
+/// Settings for the "openai-responses" backend
+#[derive(Debug, Deserialize, Clone, Serialize, PartialEq)]
+#[serde(default)]
+pub struct ResponsesSettings {
+    /// `reasoning.effort` for reasoning models: "minimal", "low", "medium" or "high"
+    pub reasoning_effort: Option<String>,
+    /// `reasoning.summary`: "auto", "concise" or "detailed"
+    pub reasoning_summary: Option<String>,
+    /// Continue tool turns with `previous_response_id` instead of resending the history
+    pub chain: bool,
+}
+
+impl Default for ResponsesSettings {
+    fn default() -> Self {
+        Self {
+            reasoning_effort: None,
+            reasoning_summary: Some("auto".to_string()),
+            chain: true,
+        }
+    }
+}
+
+/// Retries for failed LLM requests: rate limits, server errors and lost connections
+#[derive(Debug, Deserialize, Clone, Serialize, PartialEq)]
+#[serde(default)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    /// First retry delay, doubled for each further retry
+    pub initial_backoff_ms: u64,
+    /// Upper bound for a delay; a longer `retry-after` fails the request instead
+    pub max_backoff_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff_ms: 1000,
+            max_backoff_ms: 30_000,
+        }
+    }
+}
+
+/// Limits that keep the agent loop from running away with tool calls
+#[derive(Debug, Deserialize, Clone, Serialize, PartialEq)]
+#[serde(default)]
+pub struct AgentPolicy {
+    /// Model calls that may request tools before the loop is wrapped up
+    pub max_iterations: u32,
+    /// Tool calls allowed across the whole loop
+    pub max_tool_calls: u32,
+    /// Wall-clock budget for the whole loop
+    pub max_duration_secs: u64,
+    /// Timeout for a single tool call attempt
+    pub tool_timeout_secs: u64,
+    /// Extra attempts for tool calls whose server could not be reached
+    pub tool_retries: u32,
+    /// Delay before the first retry, doubled for each further retry
+    pub retry_backoff_ms: u64,
+}
+
+impl Default for AgentPolicy {
+    fn default() -> Self {
+        Self {
+            max_iterations: 10,
+            max_tool_calls: 50,
+            max_duration_secs: 600,
+            tool_timeout_secs: 20,
+            tool_retries: 2,
+            retry_backoff_ms: 500,
+        }
+    }
+}
+
+/// A named assistant combining a system prompt, a model and a tool set
+#[derive(Debug, Deserialize, Clone, Serialize, PartialEq, Default)]
+#[serde(default)]
+pub struct Persona {
+    /// Replaces system_prompt.md in the persona's conversations
+    pub system_prompt: Option<String>,
+    /// Profile its conversations start with; None follows the default profile
+    pub profile: Option<String>,
+    /// Qualified tool names (`server__tool`) or MCP server names the persona may use;
+    /// None allows every enabled tool
+    pub tools: Option<Vec<String>>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+}
+
+impl Persona {
+    /// Whether the qualified `tool_name`, served by `server_name`, is in the persona's tool set
+    pub fn allows_tool(&self, tool_name: &str, server_name: &str) -> bool {
+        self.tools
+            .as_ref()
+            .map(|tools| tools.iter().any(|allowed| allowed == tool_name || allowed == server_name))
+            .unwrap_or(true)
+    }
+}
+
+/// Color scheme of the app window
+#[derive(Debug, Deserialize, Clone, Copy, Serialize, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemePreference {
+    /// Follow the desktop's light or dark setting
+    #[default]
+    System,
+    Dark,
+    Light,
+}
+
+impl ThemePreference {
+    pub const ALL: [ThemePreference; 3] = [ThemePreference::System, ThemePreference::Dark, ThemePreference::Light];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ThemePreference::System => "System",
+            ThemePreference::Dark => "Dark",
+            ThemePreference::Light => "Light",
+        }
+    }
+}
+
+/// Preferences for the app itself, as opposed to the models and tools it talks to
+#[derive(Debug, Deserialize, Clone, Serialize, PartialEq)]
+#[serde(default)]
+pub struct AppPreferences {
+    pub theme: ThemePreference,
+    /// Write new conversations to the history database
+    pub save_history: bool,
+    /// Desktop notifications for runs that finish while the window is in the background
+    pub notifications: bool,
+    /// Keep the newest message in view while a reply streams in
+    pub auto_scroll: bool,
+}
+
+impl Default for AppPreferences {
+    fn default() -> Self {
+        Self {
+            theme: ThemePreference::System,
+            save_history: true,
+            notifications: true,
+            auto_scroll: true,
         }
     }
 }
 
 // New Claude Desktop-style configuration
-#[derive(Debug, Deserialize, Clone, Serialize)]
+#[derive(Debug, Deserialize, Clone, Serialize, PartialEq)]
 pub struct MCPServerConfig {
     pub command: String,
     pub args: Vec<String>,
     #[serde(default)]
     pub env: HashMap<String, String>, // Per-server environment variables
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub disabled: bool, // Connect, but keep the server's tools away from the model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_timeout_secs: Option<u64>, // Overrides the agent policy for this server's tools
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_retries: Option<u32>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub retry_timeouts: bool, // Retry timed out calls too; only safe for tools without side effects
+}
+
+impl MCPServerConfig {
+    /// A stdio server running `command` with no arguments or environment
+    pub fn new(command: String) -> Self {
+        Self {
+            command,
+            args: Vec::new(),
+            env: HashMap::new(),
+            disabled: false,
+            tool_timeout_secs: None,
+            tool_retries: None,
+            retry_timeouts: false,
+        }
+    }
+
+    /// Same process as `other`: they only differ in whether the tools are offered
+    pub fn same_process(&self, other: &MCPServerConfig) -> bool {
+        MCPServerConfig { disabled: other.disabled, ..self.clone() } == *other
+    }
+
+    /// Expand `${env:VAR_NAME}` in the command, arguments and environment values
+    pub fn expand_env_vars(&mut self) {
+        self.command = MCPConfig::expand_env_var_string(&self.command);
+        self.args = self.args
+            .iter()
+            .map(|arg| MCPConfig::expand_env_var_string(arg))
+            .collect();
+        self.env = self.env
+            .iter()
+            .map(|(k, v)| (k.clone(), MCPConfig::expand_env_var_string(v)))
+            .collect();
+    }
 }
 
 #[derive(Debug, Deserialize, Clone, Serialize)]
@@ -64,6 +330,17 @@ pub struct AppConfig {
     pub prompts: crate::prompts::PromptConfig,
     #[serde(default)]
     pub mcp: MCPConfig,
+    #[serde(default)]
+    pub agent: AgentPolicy,
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub personas: HashMap<String, Persona>,
+    #[serde(default)]
+    pub preferences: AppPreferences,
+    /// Shortcut overrides by command id, e.g. `new_conversation = "Ctrl+Shift+N"`; empty unbinds
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub keybindings: HashMap<String, String>,
 }
 
 impl Default for AppConfig {
@@ -79,6 +356,11 @@ impl Default for AppConfig {
             profiles,
             prompts: crate::prompts::PromptConfig::default(),
             mcp: MCPConfig::default(),
+            agent: AgentPolicy::default(),
+            retry: RetryPolicy::default(),
+            personas: HashMap::new(),
+            preferences: AppPreferences::default(),
+            keybindings: HashMap::new(),
         }
     }
 }
@@ -90,11 +372,7 @@ impl AppConfig {
             .join("cosmic_llm")
     }
 
-    fn config_file_path() -> PathBuf {
-        Self::config_dir().join("config.toml")
-    }
-
-    fn config_toml_path() -> PathBuf {
+    pub fn config_file_path() -> PathBuf {
         Self::config_dir().join("config.toml")
     }
 
@@ -113,19 +391,62 @@ impl AppConfig {
         config.try_deserialize()
     }
 
+    /// Reload config.toml for hot reload, rejecting files that fail to parse or validate
+    pub fn reload() -> Result<Self, String> {
+        let config = Self::load().map_err(|e| e.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Check the settings that would otherwise silently fall back to defaults
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.profiles.contains_key(&self.default) {
+            return Err(format!("default profile '{}' is not defined in [profiles]", self.default));
+        }
+        for (name, profile) in &self.profiles {
+            if profile.model.trim().is_empty() {
+                return Err(format!("profile '{}' has no model", name));
+            }
+            if let Some(fallback) = &profile.fallback {
+                if fallback == name || !self.profiles.contains_key(fallback) {
+                    return Err(format!("profile '{}' falls back to '{}', which is not another defined profile", name, fallback));
+                }
+            }
+        }
+        for (name, persona) in &self.personas {
+            if let Some(profile) = &persona.profile {
+                if !self.profiles.contains_key(profile) {
+                    return Err(format!("persona '{}' uses profile '{}', which is not defined in [profiles]", name, profile));
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub fn get_default_profile(&self) -> Option<&LlmProfile> {
         self.profiles.get(&self.default)
     }
 
+    /// Agent policy for `profile`, falling back to the global one
+    pub fn agent_policy(&self, profile: &str) -> AgentPolicy {
+        self.get_profile(profile)
+            .and_then(|profile| profile.agent.clone())
+            .unwrap_or_else(|| self.agent.clone())
+    }
+
     pub fn get_profile(&self, name: &str) -> Option<&LlmProfile> {
         self.profiles.get(name)
     }
 
+    pub fn get_persona(&self, name: &str) -> Option<&Persona> {
+        self.personas.get(name)
+    }
+
     pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
         use std::fs;
         use toml;
         
-        let config_path = Self::config_toml_path();
+        let config_path = Self::config_file_path();
         
         // Create config directory if it doesn't exist
         if let Some(parent) = config_path.parent() {
@@ -141,6 +462,16 @@ impl AppConfig {
 impl MCPConfig {
     /// Load MCP configuration from separate mcp_config.json file (Claude Desktop format)
     pub fn load_from_json() -> Result<Self, Box<dyn std::error::Error>> {
+        let mut config = Self::load_raw_json()?;
+        
+        // Expand environment variables in all fields
+        config.expand_env_vars();
+        
+        Ok(config)
+    }
+    
+    /// Load mcp_config.json as written, keeping `${env:...}` references so it can be saved back
+    pub fn load_raw_json() -> Result<Self, Box<dyn std::error::Error>> {
         let mcp_config_path = Self::mcp_config_path();
         
         if !mcp_config_path.exists() {
@@ -148,16 +479,11 @@ impl MCPConfig {
         }
         
         let content = std::fs::read_to_string(mcp_config_path)?;
-        let mut config: MCPConfig = serde_json::from_str(&content)?;
-        
-        // Expand environment variables in all fields
-        config.expand_env_vars();
-        
-        Ok(config)
+        Ok(serde_json::from_str(&content)?)
     }
     
     /// Get the path to mcp_config.json
-    fn mcp_config_path() -> PathBuf {
+    pub fn mcp_config_path() -> PathBuf {
         dirs::data_dir()
             .unwrap_or_else(|| PathBuf::from("."))
             .join("cosmic_llm")
@@ -178,22 +504,9 @@ impl MCPConfig {
     }
     
     /// Expand environment variables in format ${env:VAR_NAME}
-    fn expand_env_vars(&mut self) {
+    pub fn expand_env_vars(&mut self) {
         for server_config in self.servers.values_mut() {
-            // Expand command
-            server_config.command = Self::expand_env_var_string(&server_config.command);
-            
-            // Expand args
-            server_config.args = server_config.args
-                .iter()
-                .map(|arg| Self::expand_env_var_string(arg))
-                .collect();
-            
-            // Expand env values
-            server_config.env = server_config.env
-                .iter()
-                .map(|(k, v)| (k.clone(), Self::expand_env_var_string(v)))
-                .collect();
+            server_config.expand_env_vars();
         }
     }
     
@@ -214,4 +527,61 @@ impl MCPConfig {
         
         result
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_persona_allows_tool() {
+        let persona = Persona::default();
+        assert!(persona.allows_tool("filesystem__read_file", "filesystem"));
+
+        let persona = Persona {
+            tools: Some(vec!["web__search".to_string(), "filesystem".to_string()]),
+            ..Persona::default()
+        };
+        assert!(persona.allows_tool("web__search", "web"));
+        assert!(!persona.allows_tool("web__fetch", "web"));
+        // A server name allows every tool it serves
+        assert!(persona.allows_tool("filesystem__read_file", "filesystem"));
+        assert!(!persona.allows_tool("mail__send_email", "mail"));
+
+        // Bare tool names are not matched: the same name can exist on several servers
+        let persona = Persona { tools: Some(vec!["search".to_string()]), ..Persona::default() };
+        assert!(!persona.allows_tool("web__search", "web"));
+
+        let persona = Persona { tools: Some(Vec::new()), ..Persona::default() };
+        assert!(!persona.allows_tool("web__search", "web"));
+    }
+
+    fn profile(model: &str) -> LlmProfile {
+        LlmProfile { model: model.to_string(), ..LlmProfile::default() }
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut config = AppConfig::default();
+        config.profiles.insert("openai".to_string(), profile("gpt-4o"));
+        config.profiles.insert("local".to_string(), profile("llama3"));
+        assert!(config.validate().is_ok());
+
+        config.default = "missing".to_string();
+        assert!(config.validate().unwrap_err().contains("default profile 'missing'"));
+        config.default = "openai".to_string();
+
+        config.profiles.insert("empty".to_string(), profile("  "));
+        assert!(config.validate().unwrap_err().contains("profile 'empty' has no model"));
+        config.profiles.remove("empty");
+
+        config.profiles.get_mut("openai").unwrap().fallback = Some("openai".to_string());
+        assert!(config.validate().unwrap_err().contains("falls back to 'openai'"));
+        config.profiles.get_mut("openai").unwrap().fallback = Some("nowhere".to_string());
+        assert!(config.validate().is_err());
+        config.profiles.get_mut("openai").unwrap().fallback = Some("local".to_string());
+        assert!(config.validate().is_ok());
+
+        config.personas.insert("coder".to_string(), Persona { profile: Some("gone".to_string()), ..Persona::default() });
+        assert!(config.validate().unwrap_err().contains("persona 'coder' uses profile 'gone'"));
+    }
+}
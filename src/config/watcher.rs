@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// How often watched configuration files are checked for changes
+pub const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Detects changes to a set of files by polling their modification times.
+///
+/// Polling keeps us free of platform-specific notification APIs and also
+/// notices files that are created or deleted after startup.
+pub struct FileWatcher {
+    files: HashMap<PathBuf, Option<SystemTime>>,
+}
+
+impl FileWatcher {
+    pub fn new(paths: impl IntoIterator<Item = PathBuf>) -> Self {
+        let files = paths
+            .into_iter()
+            .map(|path| {
+                let modified = Self::modified(&path);
+                (path, modified)
+            })
+            .collect();
+        Self { files }
+    }
+
+    /// Start watching another file; a no-op if it is already watched
+    pub fn watch(&mut self, path: PathBuf) {
+        if !self.files.contains_key(&path) {
+            let modified = Self::modified(&path);
+            self.files.insert(path, modified);
+        }
+    }
+
+    /// Return the files whose modification time changed since the last poll
+    pub fn poll_changed(&mut self) -> Vec<PathBuf> {
+        let mut changed = Vec::new();
+        for (path, last_modified) in self.files.iter_mut() {
+            let modified = Self::modified(path);
+            if modified != *last_modified {
+                *last_modified = modified;
+                changed.push(path.clone());
+            }
+        }
+        changed
+    }
+
+    fn modified(path: &Path) -> Option<SystemTime> {
+        std::fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn touch(path: &Path, secs: u64) {
+        fs::write(path, secs.to_string()).unwrap();
+        let file = fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(secs)).unwrap();
+    }
+
+    #[test]
+    fn test_poll_reports_watched_changes_once() {
+        let dir = std::env::temp_dir().join(format!("cosmic_llm_watcher_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("config.toml");
+        let other = dir.join("other.toml");
+        touch(&config, 1_000);
+        touch(&other, 1_000);
+
+        let mut watcher = FileWatcher::new(vec![config.clone()]);
+        assert!(watcher.poll_changed().is_empty());
+
+        // Several writes between two polls are reported as one change; unwatched files never are
+        touch(&config, 2_000);
+        touch(&config, 3_000);
+        touch(&other, 2_000);
+        assert_eq!(watcher.poll_changed(), vec![config.clone()]);
+        assert!(watcher.poll_changed().is_empty());
+
+        // Files created or deleted after startup count as changes
+        let template = dir.join("template.md");
+        watcher.watch(template.clone());
+        watcher.watch(template.clone());
+        touch(&template, 1_000);
+        assert_eq!(watcher.poll_changed(), vec![template.clone()]);
+        fs::remove_file(&template).unwrap();
+        assert_eq!(watcher.poll_changed(), vec![template]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
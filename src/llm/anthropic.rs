@@ -19,6 +19,8 @@ struct AnthropicRequest {
     tools: Option<Vec<AnthropicToolDefinition>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     tool_choice: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thinking: Option<serde_json::Value>,
     stream: bool,
 }
 
@@ -37,6 +39,20 @@ enum AnthropicContentBlock {
     ToolUse { id: String, name: String, input: serde_json::Value },
     #[serde(rename = "tool_result")]
     ToolResult { tool_use_id: String, #[serde(skip_serializing_if = "Option::is_none")] content: Option<String>, #[serde(skip_serializing_if = "Option::is_none")] is_error: Option<bool> },
+    #[serde(rename = "thinking")]
+    Thinking { thinking: String, signature: String },
+    #[serde(rename = "redacted_thinking")]
+    RedactedThinking { data: String },
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicModelList {
+    data: Vec<AnthropicModel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicModel {
+    id: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -51,9 +67,13 @@ enum AnthropicResponseBlock {
     Text { text: String },
     #[serde(rename = "tool_use")]
     ToolUse { id: String, name: String, input: serde_json::Value },
+    #[serde(rename = "thinking")]
+    Thinking { thinking: String, signature: String },
+    #[serde(rename = "redacted_thinking")]
+    RedactedThinking { data: String },
 }
 
-// Streaming event minimal structs (we only care about text deltas)
+// Streaming event minimal structs (we only care about text and thinking deltas)
 #[derive(Debug, Deserialize)]
 struct AnthropicSseDelta {
     delta: Option<AnthropicDelta>,
@@ -62,6 +82,7 @@ struct AnthropicSseDelta {
 #[derive(Debug, Deserialize)]
 struct AnthropicDelta {
     text: Option<String>,
+    thinking: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -83,6 +104,101 @@ impl AnthropicClient {
             profile,
         }
     }
+    
+    /// `/v1/models` on the host of the configured messages endpoint
+    fn models_url(&self) -> String {
+        let endpoint = self.profile.endpoint.trim_end_matches('/');
+        let base = endpoint.strip_suffix("/messages").unwrap_or(endpoint);
+        if base.ends_with("/v1") {
+            format!("{}/models?limit=1000", base)
+        } else {
+            format!("{}/v1/models?limit=1000", base)
+        }
+    }
+    
+    /// Extended thinking settings when the profile has a thinking budget
+    fn thinking(&self) -> Option<serde_json::Value> {
+        self.profile.thinking_budget.map(|budget| json!({
+            "type": "enabled",
+            // The API's minimum budget
+            "budget_tokens": budget.max(1024),
+        }))
+    }
+    
+    /// `max_tokens` covers thinking too, so the budget is added on top of the answer limit
+    fn max_tokens(&self, max_tokens: Option<u32>) -> u32 {
+        let answer_tokens = max_tokens.or(self.profile.max_tokens).unwrap_or(1000);
+        match self.profile.thinking_budget {
+            Some(budget) => answer_tokens + budget.max(1024),
+            None => answer_tokens,
+        }
+    }
+    
+    /// Thinking requires the default temperature
+    fn temperature(&self, temperature: Option<f32>) -> Option<f32> {
+        if self.profile.thinking_budget.is_some() {
+            None
+        } else {
+            temperature.or(self.profile.temperature)
+        }
+    }
+}
+
+/// Text and thinking deltas of a streamed event; only content_block_delta events carry any
+fn stream_chunks(data: &str) -> Vec<StreamChunk> {
+    let mut chunks = Vec::new();
+    if let Ok(delta) = serde_json::from_str::<AnthropicSseDelta>(data) {
+        if let Some(d) = delta.delta {
+            if let Some(t) = d.thinking { chunks.push(StreamChunk::Reasoning(t)); }
+            if let Some(t) = d.text { chunks.push(StreamChunk::Text(t)); }
+        }
+    }
+    chunks
+}
+
+/// Answer, tool calls and thinking of a response; thinking blocks are kept whole to be sent back
+fn chat_response(response: AnthropicResponse) -> ChatResponse {
+    let mut content = String::new();
+    let mut tool_calls: Vec<ToolCall> = Vec::new();
+    let mut reasoning = Reasoning::default();
+    for block in response.content.into_iter() {
+        match block {
+            AnthropicResponseBlock::Text { text } => content.push_str(&text),
+            AnthropicResponseBlock::ToolUse { id, name, input } => {
+                tool_calls.push(ToolCall { id, name, parameters: input });
+            }
+            AnthropicResponseBlock::Thinking { thinking, signature } => {
+                reasoning.text.push_str(&thinking);
+                reasoning.blocks.push(json!({ "type": "thinking", "thinking": thinking, "signature": signature }));
+            }
+            AnthropicResponseBlock::RedactedThinking { data } => {
+                reasoning.blocks.push(json!({ "type": "redacted_thinking", "data": data }));
+            }
+        }
+    }
+    ChatResponse { content, tool_calls, reasoning: (!reasoning.is_empty()).then_some(reasoning) }
+}
+
+/// Content of an assistant turn. Signed thinking blocks go back first and unchanged, or tool turns are rejected.
+fn assistant_blocks(m: Message) -> Vec<AnthropicContentBlock> {
+    let mut content_blocks: Vec<AnthropicContentBlock> = m
+        .reasoning
+        .iter()
+        .flat_map(|reasoning| reasoning.blocks.iter())
+        .filter_map(|block| serde_json::from_value(block.clone()).ok())
+        .collect();
+    if !m.content.is_empty() {
+        content_blocks.push(AnthropicContentBlock::Text { text: m.content });
+    }
+    if let Some(tool_calls) = m.tool_calls {
+        for tc in tool_calls.into_iter() {
+            content_blocks.push(AnthropicContentBlock::ToolUse { id: tc.id, name: tc.name, input: tc.parameters });
+        }
+    }
+    if content_blocks.is_empty() {
+        content_blocks.push(AnthropicContentBlock::Text { text: String::new() });
+    }
+    content_blocks
 }
 
 #[async_trait]
@@ -93,7 +209,7 @@ impl LlmClient for AnthropicClient {
         messages: Vec<Message>,
         temperature: Option<f32>,
         max_tokens: Option<u32>,
-    ) -> Result<Pin<Box<dyn Stream<Item = Result<String, LlmError>> + Send>>, LlmError> {
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamChunk, LlmError>> + Send>>, LlmError> {
         // Extract first system prompt if present; Anthropic expects it separately
         let mut system_prompt: Option<String> = None;
         let mut user_assistant: Vec<Message> = Vec::new();
@@ -161,11 +277,12 @@ impl LlmClient for AnthropicClient {
         let request = AnthropicRequest {
             model: self.profile.model.clone(),
             messages: anthropic_messages,
-            max_tokens: max_tokens.or(self.profile.max_tokens).unwrap_or(1000),
-            temperature: temperature.or(self.profile.temperature),
+            max_tokens: self.max_tokens(max_tokens),
+            temperature: self.temperature(temperature),
             system: system_prompt,
             tools: None,
             tool_choice: None,
+            thinking: self.thinking(),
             stream: true,
         };
 
@@ -180,41 +297,23 @@ impl LlmClient for AnthropicClient {
             .await?;
 
         if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(LlmError::Api(format!("Anthropic API error: {}", error_text)));
+            return Err(LlmError::from_response("Anthropic", response).await);
         }
 
-        let stream = response.bytes_stream();
-        let stream = futures::StreamExt::map(stream, |chunk_result| {
-            chunk_result
-                .map_err(|e| LlmError::Http(e))
-                .and_then(|chunk| {
-                    let chunk_str = String::from_utf8(chunk.to_vec())
-                        .map_err(|e| LlmError::Api(format!("Invalid UTF-8: {}", e)))?;
-
-                    // SSE format: lines beginning with "data: "
-                    let mut content = String::new();
-                    for line in chunk_str.lines() {
-                        if let Some(data) = line.strip_prefix("data: ") {
-                            if data == "[DONE]" { continue; }
-                            // Try parse minimal delta structure
-                            if let Ok(delta) = serde_json::from_str::<AnthropicSseDelta>(data) {
-                                if let Some(d) = delta.delta {
-                                    if let Some(t) = d.text { content.push_str(&t); }
-                                }
-                            }
-                        }
-                    }
+        let stream = futures::StreamExt::map(stream_decoder::sse_events(response), |event| {
+            let event = event?;
+            if let Some(message) = event.error_message() {
+                return Err(LlmError::Api(format!("Anthropic API error: {}", message)));
+            }
 
-                    if content.is_empty() { Ok(None) } else { Ok(Some(content)) }
-                })
+            Ok(stream_chunks(&event.data))
         });
-        let stream = futures::StreamExt::filter_map(stream, |result| async move {
-            match result {
-                Ok(Some(content)) => Some(Ok(content)),
-                Ok(None) => None,
-                Err(e) => Some(Err(e)),
-            }
+        let stream = futures::StreamExt::flat_map(stream, |result| {
+            let items: Vec<Result<StreamChunk, LlmError>> = match result {
+                Ok(chunks) => chunks.into_iter().map(Ok).collect(),
+                Err(e) => vec![Err(e)],
+            };
+            futures::stream::iter(items)
         });
 
         Ok(Box::pin(stream))
@@ -284,19 +383,7 @@ impl LlmClient for AnthropicClient {
                     });
                 }
                 Role::Assistant => {
-                    let mut content_blocks: Vec<AnthropicContentBlock> = Vec::new();
-                    if !m.content.is_empty() {
-                        content_blocks.push(AnthropicContentBlock::Text { text: m.content });
-                    }
-                    if let Some(tool_calls) = m.tool_calls.clone() {
-                        for tc in tool_calls.into_iter() {
-                            content_blocks.push(AnthropicContentBlock::ToolUse { id: tc.id, name: tc.name, input: tc.parameters });
-                        }
-                    }
-                    if content_blocks.is_empty() {
-                        content_blocks.push(AnthropicContentBlock::Text { text: String::new() });
-                    }
-                    anthropic_messages.push(AnthropicMessage { role: "assistant".to_string(), content: content_blocks });
+                    anthropic_messages.push(AnthropicMessage { role: "assistant".to_string(), content: assistant_blocks(m) });
                 }
                 Role::Tool => {
                     // Convert tool result message into a user message with a tool_result block
@@ -330,11 +417,12 @@ impl LlmClient for AnthropicClient {
         let request = AnthropicRequest {
             model: self.profile.model.clone(),
             messages: anthropic_messages,
-            max_tokens: max_tokens.or(self.profile.max_tokens).unwrap_or(1000),
-            temperature: temperature.or(self.profile.temperature),
+            max_tokens: self.max_tokens(max_tokens),
+            temperature: self.temperature(temperature),
             system: system_prompt,
             tools,
             tool_choice: if has_tools { Some(json!({"type": "auto"})) } else { None },
+            thinking: self.thinking(),
             stream: false,
         };
 
@@ -349,23 +437,82 @@ impl LlmClient for AnthropicClient {
             .await?;
 
         if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(LlmError::Api(format!("Anthropic API error: {}", error_text)));
+            return Err(LlmError::from_response("Anthropic", response).await);
         }
 
         let response_data: AnthropicResponse = response.json().await?;
-        let mut content = String::new();
-        let mut tool_calls: Vec<ToolCall> = Vec::new();
-        for block in response_data.content.into_iter() {
-            match block {
-                AnthropicResponseBlock::Text { text } => content.push_str(&text),
-                AnthropicResponseBlock::ToolUse { id, name, input } => {
-                    tool_calls.push(ToolCall { id, name, parameters: input });
-                }
-            }
+        Ok(chat_response(response_data))
+    }
+    
+    async fn list_models(&self) -> Result<Vec<String>, LlmError> {
+        let response = self
+            .client
+            .get(self.models_url())
+            .header("x-api-key", &self.profile.api_key)
+            .header("anthropic-version", "2023-06-01")
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(LlmError::from_response("Anthropic", response).await);
         }
 
-        Ok(ChatResponse { content, tool_calls })
+        let list: AnthropicModelList = response.json().await?;
+        Ok(list.data.into_iter().map(|model| model.id).collect())
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_chunks() {
+        assert_eq!(
+            stream_chunks(r#"{"type":"content_block_delta","index":0,"delta":{"type":"thinking_delta","thinking":"Let me add"}}"#),
+            vec![StreamChunk::Reasoning("Let me add".to_string())]
+        );
+        assert_eq!(
+            stream_chunks(r#"{"type":"content_block_delta","index":1,"delta":{"type":"text_delta","text":"4"}}"#),
+            vec![StreamChunk::Text("4".to_string())]
+        );
+        // Signatures and bookkeeping events carry nothing to show
+        assert!(stream_chunks(r#"{"type":"content_block_delta","index":0,"delta":{"type":"signature_delta","signature":"EqQB"}}"#).is_empty());
+        assert!(stream_chunks(r#"{"type":"message_stop"}"#).is_empty());
+    }
+
+    #[test]
+    fn test_thinking_round_trip() {
+        let response: AnthropicResponse = serde_json::from_value(json!({
+            "content": [
+                { "type": "thinking", "thinking": "I should look it up.", "signature": "EqQBsig" },
+                { "type": "redacted_thinking", "data": "EmwKAhgB" },
+                { "type": "text", "text": "Checking." },
+                { "type": "tool_use", "id": "toolu_1", "name": "web__search", "input": { "q": "rust" } },
+            ]
+        })).unwrap();
+        let response = chat_response(response);
+        assert_eq!(response.content, "Checking.");
+        assert_eq!(response.tool_calls[0].id, "toolu_1");
+        let reasoning = response.reasoning.clone().unwrap();
+        assert_eq!(reasoning.text, "I should look it up.");
+        assert_eq!(reasoning.blocks.len(), 2);
+
+        // The assistant turn goes back with the signed blocks first and unchanged
+        let mut message = Message::new_with_tool_calls(Role::Assistant, response.content, response.tool_calls);
+        message.reasoning = response.reasoning;
+        let blocks = serde_json::to_value(assistant_blocks(message)).unwrap();
+        assert_eq!(blocks, json!([
+            { "type": "thinking", "thinking": "I should look it up.", "signature": "EqQBsig" },
+            { "type": "redacted_thinking", "data": "EmwKAhgB" },
+            { "type": "text", "text": "Checking." },
+            { "type": "tool_use", "id": "toolu_1", "name": "web__search", "input": { "q": "rust" } },
+        ]));
+    }
+
+    #[test]
+    fn test_assistant_without_content_gets_empty_text() {
+        let blocks = serde_json::to_value(assistant_blocks(Message::new(Role::Assistant, String::new()))).unwrap();
+        assert_eq!(blocks, json!([{ "type": "text", "text": "" }]));
+    }
+}
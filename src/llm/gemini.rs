@@ -22,7 +22,12 @@ struct GeminiContent {
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(untagged)]
 enum GeminiPart {
-    Text { text: String },
+    Text {
+        text: String,
+        /// Set on thinking parts when `include_thoughts` is on
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        thought: Option<bool>,
+    },
     FunctionCall { 
         #[serde(rename = "functionCall")]
         function_call: GeminiFunctionCall 
@@ -51,6 +56,14 @@ struct GeminiGenerationConfig {
     temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     max_output_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thinking_config: Option<GeminiThinkingConfig>,
+}
+
+#[derive(Debug, Serialize)]
+struct GeminiThinkingConfig {
+    thinking_budget: u32,
+    include_thoughts: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -76,6 +89,19 @@ struct GeminiCandidate {
     content: GeminiContent,
 }
 
+#[derive(Debug, Deserialize)]
+struct GeminiModelList {
+    #[serde(default)]
+    models: Vec<GeminiModel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiModel {
+    name: String,
+    #[serde(rename = "supportedGenerationMethods", default)]
+    supported_generation_methods: Vec<String>,
+}
+
 pub struct GeminiClient {
     client: Client,
     profile: LlmProfile,
@@ -89,6 +115,25 @@ impl GeminiClient {
         }
     }
 
+    /// Thinking budget and thought summaries when the profile sets a budget
+    fn thinking_config(&self) -> Option<GeminiThinkingConfig> {
+        self.profile.thinking_budget.map(|budget| GeminiThinkingConfig {
+            thinking_budget: budget,
+            include_thoughts: true,
+        })
+    }
+
+    /// `models.list` URL for the API version in the configured endpoint
+    fn models_url(&self) -> String {
+        let endpoint = self.profile.endpoint.trim_end_matches('/');
+        let base = match endpoint.find("/models") {
+            Some(index) => endpoint[..index].to_string(),
+            None if endpoint.ends_with("/v1") || endpoint.ends_with("/v1beta") => endpoint.to_string(),
+            None => format!("{}/v1beta", endpoint),
+        };
+        format!("{}/models?pageSize=1000&key={}", base, self.profile.api_key)
+    }
+
     /// Sanitize JSON Schema to only include fields supported by Gemini API
     /// Gemini only supports: type, nullable, required, format, description, properties, items, enum
     fn sanitize_schema(&self, schema: serde_json::Value) -> serde_json::Value {
@@ -211,7 +256,7 @@ impl GeminiClient {
                     }
                 }
                 
-                current_parts.push(GeminiPart::Text { text: text_content });
+                current_parts.push(GeminiPart::Text { text: text_content, thought: None });
             }
 
             current_role = Some(role.to_string());
@@ -231,6 +276,57 @@ impl GeminiClient {
     }
 }
 
+/// Text and thought summaries of one streamed response
+fn stream_chunks(data: &str) -> Vec<StreamChunk> {
+    let mut chunks = Vec::new();
+    if let Ok(response) = serde_json::from_str::<GeminiResponse>(data) {
+        if let Some(candidate) = response.candidates.first() {
+            for part in &candidate.content.parts {
+                if let GeminiPart::Text { text, thought } = part {
+                    if thought.unwrap_or(false) {
+                        chunks.push(StreamChunk::Reasoning(text.clone()));
+                    } else {
+                        chunks.push(StreamChunk::Text(text.clone()));
+                    }
+                }
+            }
+        }
+    }
+    chunks
+}
+
+/// Answer, thought summary and function calls of a candidate
+fn chat_response(content: &GeminiContent) -> ChatResponse {
+    let mut text_content = String::new();
+    let mut reasoning = String::new();
+    let mut tool_calls = Vec::new();
+
+    for part in &content.parts {
+        match part {
+            GeminiPart::Text { text, thought: Some(true) } => {
+                reasoning.push_str(text);
+            }
+            GeminiPart::Text { text, .. } => {
+                text_content.push_str(text);
+            }
+            GeminiPart::FunctionCall { function_call } => {
+                tool_calls.push(ToolCall {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    name: function_call.name.clone(),
+                    parameters: function_call.args.clone(),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    ChatResponse {
+        content: text_content,
+        tool_calls,
+        reasoning: (!reasoning.is_empty()).then(|| Reasoning { text: reasoning, blocks: Vec::new() }),
+    }
+}
+
 #[async_trait]
 impl LlmClient for GeminiClient {
 
@@ -239,12 +335,13 @@ impl LlmClient for GeminiClient {
         messages: Vec<Message>,
         temperature: Option<f32>,
         max_tokens: Option<u32>,
-    ) -> Result<Pin<Box<dyn Stream<Item = Result<String, LlmError>> + Send>>, LlmError> {
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamChunk, LlmError>> + Send>>, LlmError> {
         let contents = self.convert_messages_to_gemini(messages);
 
         let generation_config = GeminiGenerationConfig {
             temperature: temperature.or(self.profile.temperature),
             max_output_tokens: max_tokens.or(self.profile.max_tokens),
+            thinking_config: self.thinking_config(),
         };
 
         let request = GeminiRequest {
@@ -253,8 +350,8 @@ impl LlmClient for GeminiClient {
             tools: None,
         };
 
-        // Build endpoint with model
-        let endpoint = format!("{}:streamGenerateContent?key={}", 
+        // alt=sse streams one JSON response per SSE event instead of one large JSON array
+        let endpoint = format!("{}:streamGenerateContent?alt=sse&key={}", 
             self.profile.endpoint.trim_end_matches('/'),
             self.profile.api_key
         );
@@ -268,51 +365,24 @@ impl LlmClient for GeminiClient {
             .await?;
 
         if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(LlmError::Api(format!("Gemini API error: {}", error_text)));
+            return Err(LlmError::from_response("Gemini", response).await);
         }
 
-        let stream = response.bytes_stream();
-        let stream = futures::StreamExt::map(stream, |chunk_result| {
-            chunk_result
-                .map_err(|e| LlmError::Http(e))
-                .and_then(|chunk| {
-                    let chunk_str = String::from_utf8(chunk.to_vec())
-                        .map_err(|e| LlmError::Api(format!("Invalid UTF-8: {}", e)))?;
-                    
-                    let mut content = String::new();
-                    
-                    // Gemini streaming returns JSON objects separated by newlines
-                    for line in chunk_str.lines() {
-                        if line.trim().is_empty() {
-                            continue;
-                        }
-                        
-                        if let Ok(response) = serde_json::from_str::<GeminiResponse>(line) {
-                            if let Some(candidate) = response.candidates.first() {
-                                for part in &candidate.content.parts {
-                                    if let GeminiPart::Text { text } = part {
-                                        content.push_str(text);
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    
-                    if content.is_empty() {
-                        Ok(None)
-                    } else {
-                        Ok(Some(content))
-                    }
-                })
-        });
-        
-        let stream = futures::StreamExt::filter_map(stream, |result| async move {
-            match result {
-                Ok(Some(content)) => Some(Ok(content)),
-                Ok(None) => None,
-                Err(e) => Some(Err(e)),
+        let stream = futures::StreamExt::map(stream_decoder::sse_events(response), |event| {
+            let event = event?;
+            if let Some(message) = event.error_message() {
+                return Err(LlmError::Api(format!("Gemini API error: {}", message)));
             }
+
+            Ok(stream_chunks(&event.data))
+        });
+
+        let stream = futures::StreamExt::flat_map(stream, |result| {
+            let items: Vec<Result<StreamChunk, LlmError>> = match result {
+                Ok(chunks) => chunks.into_iter().map(Ok).collect(),
+                Err(e) => vec![Err(e)],
+            };
+            futures::stream::iter(items)
         });
 
         Ok(Box::pin(stream))
@@ -330,6 +400,7 @@ impl LlmClient for GeminiClient {
         let generation_config = GeminiGenerationConfig {
             temperature: temperature.or(self.profile.temperature),
             max_output_tokens: max_tokens.or(self.profile.max_tokens),
+            thinking_config: self.thinking_config(),
         };
 
         let tools = if available_tools.is_empty() {
@@ -372,8 +443,7 @@ impl LlmClient for GeminiClient {
             .await?;
 
         if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(LlmError::Api(format!("Gemini API error: {}", error_text)));
+            return Err(LlmError::from_response("Gemini", response).await);
         }
 
         let response_data: GeminiResponse = response.json().await?;
@@ -383,29 +453,55 @@ impl LlmClient for GeminiClient {
             .first()
             .ok_or_else(|| LlmError::Api("No response from Gemini".to_string()))?;
 
-        let mut content = String::new();
-        let mut tool_calls = Vec::new();
+        Ok(chat_response(&candidate.content))
+    }
+    
+    async fn list_models(&self) -> Result<Vec<String>, LlmError> {
+        let response = self.client.get(self.models_url()).send().await?;
 
-        for part in &candidate.content.parts {
-            match part {
-                GeminiPart::Text { text } => {
-                    content.push_str(text);
-                }
-                GeminiPart::FunctionCall { function_call } => {
-                    tool_calls.push(ToolCall {
-                        id: uuid::Uuid::new_v4().to_string(),
-                        name: function_call.name.clone(),
-                        parameters: function_call.args.clone(),
-                    });
-                }
-                _ => {}
-            }
+        if !response.status().is_success() {
+            return Err(LlmError::from_response("Gemini", response).await);
         }
 
-        Ok(ChatResponse {
-            content,
-            tool_calls,
-        })
+        // Only models that can chat; names come back as "models/<id>"
+        let list: GeminiModelList = response.json().await?;
+        Ok(list
+            .models
+            .into_iter()
+            .filter(|model| model.supported_generation_methods.iter().any(|method| method == "generateContent"))
+            .map(|model| model.name.trim_start_matches("models/").to_string())
+            .collect())
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_chunks() {
+        let data = r#"{"candidates":[{"content":{"role":"model","parts":[
+            {"text":"**Planning**","thought":true},
+            {"text":"Hello"}
+        ]}}]}"#;
+        assert_eq!(stream_chunks(data), vec![
+            StreamChunk::Reasoning("**Planning**".to_string()),
+            StreamChunk::Text("Hello".to_string()),
+        ]);
+        assert!(stream_chunks(r#"{"candidates":[]}"#).is_empty());
+    }
+
+    #[test]
+    fn test_chat_response_separates_thoughts() {
+        let response: GeminiResponse = serde_json::from_str(r#"{"candidates":[{"content":{"role":"model","parts":[
+            {"text":"Weather needs a lookup.","thought":true},
+            {"text":"Let me check.","thought":false},
+            {"functionCall":{"name":"weather__today","args":{"city":"Oslo"}}}
+        ]}}]}"#).unwrap();
+        let response = chat_response(&response.candidates[0].content);
+        assert_eq!(response.content, "Let me check.");
+        assert_eq!(response.reasoning.unwrap().text, "Weather needs a lookup.");
+        assert_eq!(response.tool_calls[0].name, "weather__today");
+        assert_eq!(response.tool_calls[0].parameters, serde_json::json!({ "city": "Oslo" }));
+    }
+}
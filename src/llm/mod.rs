@@ -3,7 +3,9 @@ use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use futures::Stream;
 use std::pin::Pin;
+use std::sync::Arc;
 use anyhow::Result;
+use crate::config::LlmProfile;
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub enum Role {
@@ -22,6 +24,25 @@ pub struct Message {
     pub tool_call_id: Option<String>, // For tool result messages
     pub tool_calls: Option<Vec<ToolCall>>, // For assistant messages with tool calls
     pub attachments: Option<Vec<Attachment>>, // File attachments
+    #[serde(default)]
+    pub reasoning: Option<Reasoning>, // Model reasoning behind an assistant message
+}
+
+/// Reasoning a model produced before answering
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Reasoning {
+    /// Readable thinking text or summary, for display
+    pub text: String,
+    /// Provider blocks that must be sent back verbatim on the next request,
+    /// e.g. Anthropic's signed `thinking` and `redacted_thinking` blocks
+    #[serde(default)]
+    pub blocks: Vec<serde_json::Value>,
+}
+
+impl Reasoning {
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty() && self.blocks.is_empty()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -43,6 +64,7 @@ impl Message {
             tool_call_id: None,
             tool_calls: None,
             attachments: None,
+            reasoning: None,
         }
     }
     
@@ -55,6 +77,7 @@ impl Message {
             tool_call_id: None,
             tool_calls: None,
             attachments: Some(attachments),
+            reasoning: None,
         }
     }
     
@@ -68,6 +91,7 @@ impl Message {
             tool_call_id: Some(tool_call_id),
             tool_calls: None,
             attachments: None,
+            reasoning: None,
         }
     }
 
@@ -80,6 +104,7 @@ impl Message {
             tool_call_id: None,
             tool_calls: Some(tool_calls),
             attachments: None,
+            reasoning: None,
         }
     }
 }
@@ -92,8 +117,85 @@ pub enum LlmError {
     Http(#[from] reqwest::Error),
     #[error("API error: {0}")]
     Api(String),
+    #[error("Authentication failed: {0}")]
+    Auth(String),
+    #[error("Rate limited: {message}")]
+    RateLimited { message: String, retry_after: Option<std::time::Duration> },
+    #[error("Server error ({status}): {message}")]
+    Server { status: u16, message: String },
+    #[error("Context length exceeded: {0}")]
+    ContextLength(String),
+    #[error("Blocked by content filter: {0}")]
+    ContentFilter(String),
     #[error("Configuration error: {0}")]
     Config(String),
+    #[error("Request cancelled")]
+    Cancelled,
+}
+
+impl LlmError {
+    /// Classify a failed HTTP response from `api` (e.g. "OpenAI") by status, headers and body
+    pub async fn from_response(api: &str, response: reqwest::Response) -> Self {
+        let status = response.status().as_u16();
+        let retry_after = retry_after(response.headers());
+        let body = response.text().await.unwrap_or_default();
+        Self::from_status(api, status, retry_after, &body)
+    }
+    
+    pub fn from_status(api: &str, status: u16, retry_after: Option<std::time::Duration>, body: &str) -> Self {
+        let message = format!("{} API error: {}", api, body);
+        let lower = body.to_lowercase();
+        let mentions = |needles: &[&str]| needles.iter().any(|needle| lower.contains(needle));
+        
+        if mentions(&["context_length_exceeded", "maximum context length", "prompt is too long", "context window", "too many tokens"]) {
+            return LlmError::ContextLength(message);
+        }
+        if mentions(&["content_filter", "content_policy", "content management policy"]) {
+            return LlmError::ContentFilter(message);
+        }
+        match status {
+            401 | 403 => LlmError::Auth(message),
+            // An exhausted quota will not recover by waiting
+            429 if mentions(&["insufficient_quota"]) => LlmError::Api(message),
+            429 => LlmError::RateLimited { message, retry_after },
+            408 | 500..=599 => LlmError::Server { status, message },
+            _ => LlmError::Api(message),
+        }
+    }
+    
+    /// Whether the same request may succeed if sent again
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            LlmError::Http(e) => e.is_timeout() || e.is_connect() || e.is_request(),
+            LlmError::RateLimited { .. } | LlmError::Server { .. } => true,
+            _ => false,
+        }
+    }
+    
+    /// Whether another profile may succeed where this one failed
+    pub fn allows_failover(&self) -> bool {
+        !matches!(self, LlmError::Cancelled | LlmError::ContentFilter(_))
+    }
+}
+
+/// Delay requested by `retry-after-ms` (OpenAI) or `retry-after` in seconds
+fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<std::time::Duration> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok()).and_then(|value| value.trim().parse::<f64>().ok());
+    header("retry-after-ms")
+        .map(|ms| std::time::Duration::from_secs_f64(ms.max(0.0) / 1000.0))
+        .or_else(|| header("retry-after").map(|secs| std::time::Duration::from_secs_f64(secs.max(0.0))))
+}
+
+/// Run an LLM request until it completes or `cancel` fires. Dropping the
+/// request future aborts the underlying HTTP call.
+pub async fn cancellable<T>(
+    cancel: &tokio_util::sync::CancellationToken,
+    request: impl std::future::Future<Output = Result<T, LlmError>>,
+) -> Result<T, LlmError> {
+    tokio::select! {
+        _ = cancel.cancelled() => Err(LlmError::Cancelled),
+        result = request => result,
+    }
 }
 
 // Tool-related types
@@ -116,6 +218,14 @@ pub struct ToolDefinition {
 pub struct ChatResponse {
     pub content: String,
     pub tool_calls: Vec<ToolCall>,
+    pub reasoning: Option<Reasoning>,
+}
+
+/// A piece of a streamed response
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamChunk {
+    Text(String),
+    Reasoning(String),
 }
 
 #[derive(Debug, Clone)]
@@ -132,7 +242,7 @@ pub trait LlmClient: Send + Sync {
         messages: Vec<Message>,
         temperature: Option<f32>,
         max_tokens: Option<u32>,
-    ) -> Result<Pin<Box<dyn Stream<Item = Result<String, LlmError>> + Send>>, LlmError>;
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamChunk, LlmError>> + Send>>, LlmError>;
     
     // New method for tool-enabled chat
     async fn send_message_with_tools(
@@ -142,10 +252,176 @@ pub trait LlmClient: Send + Sync {
         temperature: Option<f32>,
         max_tokens: Option<u32>,
     ) -> Result<ChatResponse, LlmError>;
+    
+    /// Model ids the backend offers for this profile's endpoint and key
+    async fn list_models(&self) -> Result<Vec<String>, LlmError>;
+    
+    /// Issue a short request to check the endpoint, key and model. It keeps the profile's
+    /// own token limit: reasoning models reject tiny budgets and thinking needs room.
+    async fn test_connection(&self) -> Result<(), LlmError> {
+        self.send_message_with_tools(
+            vec![Message::new(Role::User, "Reply with OK".to_string())],
+            Vec::new(),
+            None,
+            None,
+        )
+        .await
+        .map(|_| ())
+    }
+}
+
+/// A backend that can serve an `LlmProfile`, selected by `LlmProfile.backend`
+pub struct Provider {
+    pub name: &'static str,
+    /// Hosted APIs reject every request without a key; local servers usually need none
+    pub needs_api_key: bool,
+    constructor: fn(LlmProfile) -> Arc<dyn LlmClient>,
+}
+
+/// Backends compiled into this build, one per enabled Cargo feature
+pub const PROVIDERS: &[Provider] = &[
+    #[cfg(feature = "openai")]
+    Provider { name: "openai", needs_api_key: false, constructor: |profile| Arc::new(openai::OpenAIClient::new(profile)) },
+    // DeepSeek speaks the OpenAI chat completions protocol
+    #[cfg(feature = "deepseek")]
+    Provider { name: "deepseek", needs_api_key: true, constructor: |profile| Arc::new(openai::OpenAIClient::new(profile)) },
+    #[cfg(feature = "openai-responses")]
+    Provider { name: "openai-responses", needs_api_key: true, constructor: |profile| Arc::new(openai_responses::ResponsesClient::new(profile)) },
+    #[cfg(feature = "azure")]
+    Provider { name: "azure", needs_api_key: true, constructor: |profile| Arc::new(openai::OpenAIClient::new(profile)) },
+    #[cfg(feature = "anthropic")]
+    Provider { name: "anthropic", needs_api_key: true, constructor: |profile| Arc::new(anthropic::AnthropicClient::new(profile)) },
+    #[cfg(feature = "ollama")]
+    Provider { name: "ollama", needs_api_key: false, constructor: |profile| Arc::new(ollama::OllamaClient::new(profile)) },
+    #[cfg(feature = "gemini")]
+    Provider { name: "gemini", needs_api_key: true, constructor: |profile| Arc::new(gemini::GeminiClient::new(profile)) },
+];
+
+/// Every backend this crate knows about, whether or not its feature is enabled
+const KNOWN_BACKENDS: &[&str] = &["openai", "openai-responses", "deepseek", "azure", "anthropic", "ollama", "gemini"];
+
+/// Build the client for a profile's backend
+pub fn create_client(profile: LlmProfile) -> Result<Arc<dyn LlmClient>, LlmError> {
+    if let Some(provider) = PROVIDERS.iter().find(|provider| provider.name == profile.backend) {
+        if profile.endpoint.trim().is_empty() {
+            return Err(LlmError::Config(format!("backend '{}' needs an endpoint URL", profile.backend)));
+        }
+        if provider.needs_api_key && profile.api_key.trim().is_empty() {
+            return Err(LlmError::Config(format!("backend '{}' needs an API key", profile.backend)));
+        }
+        return Ok((provider.constructor)(profile));
+    }
+    
+    let available: Vec<&str> = PROVIDERS.iter().map(|provider| provider.name).collect();
+    if KNOWN_BACKENDS.contains(&profile.backend.as_str()) {
+        Err(LlmError::Config(format!(
+            "backend '{}' is not enabled in this build (rebuild with the '{}' feature); available: {}",
+            profile.backend, profile.backend, available.join(", ")
+        )))
+    } else {
+        Err(LlmError::Config(format!(
+            "unknown backend '{}'; available: {}",
+            profile.backend, available.join(", ")
+        )))
+    }
+}
+
+/// Stands in for a client that could not be built, failing every request with the reason
+pub struct UnavailableClient {
+    reason: String,
+}
+
+impl UnavailableClient {
+    pub fn new(reason: impl Into<String>) -> Self {
+        Self { reason: reason.into() }
+    }
+}
+
+#[async_trait]
+impl LlmClient for UnavailableClient {
+    async fn send_message_stream(
+        &self,
+        _messages: Vec<Message>,
+        _temperature: Option<f32>,
+        _max_tokens: Option<u32>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamChunk, LlmError>> + Send>>, LlmError> {
+        Err(LlmError::Config(self.reason.clone()))
+    }
+    
+    async fn send_message_with_tools(
+        &self,
+        _messages: Vec<Message>,
+        _available_tools: Vec<ToolDefinition>,
+        _temperature: Option<f32>,
+        _max_tokens: Option<u32>,
+    ) -> Result<ChatResponse, LlmError> {
+        Err(LlmError::Config(self.reason.clone()))
+    }
+    
+    async fn list_models(&self) -> Result<Vec<String>, LlmError> {
+        Err(LlmError::Config(self.reason.clone()))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_error(profile: LlmProfile) -> String {
+        match create_client(profile) {
+            Err(LlmError::Config(message)) => message,
+            Err(e) => panic!("expected a configuration error, got {}", e),
+            Ok(_) => panic!("expected a configuration error, got a client"),
+        }
+    }
+
+    #[test]
+    fn test_create_client_rejects_unknown_backend() {
+        let profile = LlmProfile { backend: "mistral".to_string(), ..LlmProfile::default() };
+        let message = config_error(profile);
+        assert!(message.starts_with("unknown backend 'mistral'"));
+        for provider in PROVIDERS {
+            assert!(message.contains(provider.name));
+        }
+    }
+
+    #[cfg(all(feature = "anthropic", feature = "ollama"))]
+    #[test]
+    fn test_create_client_checks_key_and_endpoint() {
+        let anthropic = LlmProfile {
+            backend: "anthropic".to_string(),
+            endpoint: "https://api.anthropic.com/v1/messages".to_string(),
+            ..LlmProfile::default()
+        };
+        assert_eq!(config_error(anthropic.clone()), "backend 'anthropic' needs an API key");
+        let with_key = LlmProfile { api_key: "sk-ant".to_string(), ..anthropic };
+        assert!(create_client(with_key.clone()).is_ok());
+        assert_eq!(config_error(LlmProfile { endpoint: " ".to_string(), ..with_key }), "backend 'anthropic' needs an endpoint URL");
+
+        // Local servers run without a key
+        let ollama = LlmProfile { backend: "ollama".to_string(), endpoint: "http://localhost:11434".to_string(), ..LlmProfile::default() };
+        assert!(create_client(ollama).is_ok());
+    }
+
+    #[cfg(not(feature = "gemini"))]
+    #[test]
+    fn test_create_client_names_disabled_feature() {
+        let profile = LlmProfile { backend: "gemini".to_string(), ..LlmProfile::default() };
+        assert!(config_error(profile).contains("rebuild with the 'gemini' feature"));
+    }
 }
 
+#[cfg(feature = "openai")]
 pub mod openai;
+#[cfg(feature = "openai-responses")]
+pub mod openai_responses;
+#[cfg(feature = "anthropic")]
 pub mod anthropic;
+#[cfg(feature = "ollama")]
 pub mod ollama;
+#[cfg(feature = "gemini")]
 pub mod gemini;
-pub mod file_utils;
\ No newline at end of file
+pub mod file_utils;
+pub mod retry;
+pub mod stream_decoder;
\ No newline at end of file
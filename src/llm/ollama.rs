@@ -1,10 +1,11 @@
 use super::*;
-use crate::config::LlmProfile;
+use crate::config::{LlmProfile, OllamaApi, OllamaSettings};
 use reqwest::Client;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::pin::Pin;
 
-// Ollama uses OpenAI-compatible API format
+// OpenAI-compatible /v1/chat/completions format
 #[derive(Debug, Serialize)]
 struct OllamaRequest {
     model: String,
@@ -78,6 +79,105 @@ struct OllamaDelta {
     content: Option<String>,
 }
 
+// Native /api/chat format
+#[derive(Debug, Serialize)]
+struct NativeChatRequest {
+    model: String,
+    messages: Vec<NativeMessage>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<OllamaTool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    think: Option<bool>,
+    options: NativeOptions,
+}
+
+#[derive(Debug, Serialize)]
+struct NativeOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_ctx: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct NativeMessage {
+    role: String,
+    #[serde(default)]
+    content: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    images: Vec<String>, // base64, without the data: prefix
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<NativeToolCall>,
+    // Returned by thinking models when `think` is set; not sent back
+    #[serde(default, skip_serializing)]
+    thinking: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct NativeToolCall {
+    function: NativeToolCallFunction,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct NativeToolCallFunction {
+    name: String,
+    arguments: serde_json::Value, // An object, unlike OpenAI's JSON string
+}
+
+#[derive(Debug, Deserialize)]
+struct NativeChatResponse {
+    message: Option<NativeMessage>,
+}
+
+/// Ollama reports failures, also in the middle of a stream, as `{"error": "..."}`
+#[derive(Debug, Deserialize)]
+struct NativeError {
+    error: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TagsResponse {
+    models: Vec<OllamaModel>,
+}
+
+/// A model installed on the Ollama server, as listed by `/api/tags`
+#[derive(Debug, Clone, Deserialize)]
+pub struct OllamaModel {
+    pub name: String,
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub modified_at: String,
+}
+
+/// One progress line from `/api/pull`
+#[derive(Debug, Clone, Deserialize)]
+pub struct PullProgress {
+    pub status: String,
+    pub digest: Option<String>,
+    pub total: Option<u64>,
+    pub completed: Option<u64>,
+}
+
+impl PullProgress {
+    /// Status line for display, e.g. "pulling 8eeb52dfb3bb: 42%"
+    pub fn summary(&self) -> String {
+        match (self.total, self.completed) {
+            (Some(total), Some(completed)) if total > 0 => {
+                format!("{}: {}%", self.status, completed.min(total) * 100 / total)
+            }
+            _ => self.status.clone(),
+        }
+    }
+}
+
 pub struct OllamaClient {
     client: Client,
     profile: LlmProfile,
@@ -90,6 +190,226 @@ impl OllamaClient {
             profile,
         }
     }
+    
+    fn settings(&self) -> OllamaSettings {
+        self.profile.ollama.clone().unwrap_or_default()
+    }
+    
+    /// Server root derived from the profile endpoint, which may point at either API
+    fn base_url(&self) -> String {
+        let endpoint = self.profile.endpoint.trim_end_matches('/');
+        for suffix in ["/v1/chat/completions", "/api/chat", "/v1", "/api"] {
+            if let Some(base) = endpoint.strip_suffix(suffix) {
+                return base.to_string();
+            }
+        }
+        endpoint.to_string()
+    }
+    
+    fn native_request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let request_builder = self.client.request(method, format!("{}{}", self.base_url(), path));
+        // Only add authorization header if API key is provided (e.g. behind a proxy)
+        if self.profile.api_key.is_empty() {
+            request_builder
+        } else {
+            request_builder.header("Authorization", format!("Bearer {}", self.profile.api_key))
+        }
+    }
+    
+    /// List the models installed on the server (`/api/tags`)
+    pub async fn installed_models(&self) -> Result<Vec<OllamaModel>, LlmError> {
+        let response = self.native_request(reqwest::Method::GET, "/api/tags").send().await?;
+        if !response.status().is_success() {
+            return Err(LlmError::from_response("Ollama", response).await);
+        }
+        let tags: TagsResponse = response.json().await?;
+        Ok(tags.models)
+    }
+    
+    /// Download `model` to the server (`/api/pull`), streaming its progress
+    pub async fn pull_model(&self, model: &str) -> Result<Pin<Box<dyn Stream<Item = Result<PullProgress, LlmError>> + Send>>, LlmError> {
+        let response = self
+            .native_request(reqwest::Method::POST, "/api/pull")
+            .json(&serde_json::json!({ "model": model, "stream": true }))
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(LlmError::from_response("Ollama", response).await);
+        }
+        Ok(Box::pin(ndjson_stream::<PullProgress>(response)))
+    }
+    
+    fn native_chat_request(&self, messages: Vec<Message>, tools: Option<Vec<OllamaTool>>, stream: bool, temperature: Option<f32>, max_tokens: Option<u32>) -> NativeChatRequest {
+        let settings = self.settings();
+        NativeChatRequest {
+            model: self.profile.model.clone(),
+            messages: messages.into_iter().map(to_native_message).collect(),
+            stream,
+            tools,
+            format: settings.format,
+            keep_alive: settings.keep_alive,
+            // Ollama has no budget, only an on/off switch
+            think: self.profile.thinking_budget.map(|budget| budget > 0),
+            options: NativeOptions {
+                temperature: temperature.or(self.profile.temperature),
+                num_predict: max_tokens.or(self.profile.max_tokens),
+                num_ctx: settings.num_ctx,
+            },
+        }
+    }
+    
+    async fn send_native_chat(&self, request: &NativeChatRequest) -> Result<reqwest::Response, LlmError> {
+        let response = self
+            .native_request(reqwest::Method::POST, "/api/chat")
+            .json(request)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(LlmError::from_response("Ollama", response).await);
+        }
+        Ok(response)
+    }
+    
+    async fn native_message_stream(
+        &self,
+        messages: Vec<Message>,
+        temperature: Option<f32>,
+        max_tokens: Option<u32>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamChunk, LlmError>> + Send>>, LlmError> {
+        let request = self.native_chat_request(messages, None, true, temperature, max_tokens);
+        let response = self.send_native_chat(&request).await?;
+        
+        let stream = futures::StreamExt::flat_map(ndjson_stream::<NativeChatResponse>(response), |result| {
+            let items: Vec<Result<StreamChunk, LlmError>> = match result {
+                Ok(chunk) => chunk.message.map(native_chunks).unwrap_or_default().into_iter().map(Ok).collect(),
+                Err(e) => vec![Err(e)],
+            };
+            futures::stream::iter(items)
+        });
+        Ok(Box::pin(stream))
+    }
+    
+    async fn native_message_with_tools(
+        &self,
+        messages: Vec<Message>,
+        available_tools: Vec<ToolDefinition>,
+        temperature: Option<f32>,
+        max_tokens: Option<u32>,
+    ) -> Result<ChatResponse, LlmError> {
+        let tools = if available_tools.is_empty() {
+            None
+        } else {
+            Some(available_tools.into_iter().map(|tool| OllamaTool {
+                r#type: "function".to_string(),
+                function: OllamaToolFunction {
+                    name: tool.name,
+                    description: tool.description,
+                    parameters: tool.parameters,
+                },
+            }).collect())
+        };
+        let request = self.native_chat_request(messages, tools, false, temperature, max_tokens);
+        let response = self.send_native_chat(&request).await?;
+        
+        let body = response.text().await?;
+        if let Ok(NativeError { error }) = serde_json::from_str::<NativeError>(&body) {
+            return Err(LlmError::Api(format!("Ollama API error: {}", error)));
+        }
+        let response_data: NativeChatResponse = serde_json::from_str(&body)
+            .map_err(|e| LlmError::Api(format!("Invalid Ollama response: {}", e)))?;
+        let message = response_data
+            .message
+            .ok_or_else(|| LlmError::Api("No response from Ollama".to_string()))?;
+        
+        // Native tool calls carry no id, so make one up for the tool result round trip
+        let tool_calls = message.tool_calls.into_iter().map(|tc| ToolCall {
+            id: format!("call_{}", uuid::Uuid::new_v4().simple()),
+            name: tc.function.name,
+            parameters: tc.function.arguments,
+        }).collect();
+        
+        Ok(ChatResponse {
+            content: message.content,
+            tool_calls,
+            reasoning: message.thinking.filter(|thinking| !thinking.is_empty()).map(|text| Reasoning { text, blocks: Vec::new() }),
+        })
+    }
+}
+
+fn role_name(role: &Role) -> String {
+    match role {
+        Role::User => "user".to_string(),
+        Role::Assistant => "assistant".to_string(),
+        Role::System => "system".to_string(),
+        Role::Tool => "tool".to_string(),
+    }
+}
+
+fn to_native_message(msg: Message) -> NativeMessage {
+    let mut content = msg.content;
+    let mut images = Vec::new();
+    if let Some(attachments) = msg.attachments {
+        for attachment in attachments {
+            match (attachment.mime_type.as_str(), &attachment.content) {
+                // Images are sent natively, base64 encoded; attachments only carry their path
+                (mime, _) if mime.starts_with("image/") => match std::fs::read(&attachment.file_path) {
+                    Ok(bytes) => {
+                        use base64::Engine;
+                        images.push(base64::engine::general_purpose::STANDARD.encode(bytes));
+                    }
+                    Err(e) => {
+                        log::warn!("Could not read image {}: {}", attachment.file_path, e);
+                        content.push_str(&format!("\nFile attached: {} (could not be read)", attachment.file_name));
+                    }
+                },
+                (mime, Some(file_content)) if mime.starts_with("text/") => {
+                    content.push_str(&format!("\n\nFile: {}\nContent:\n{}", attachment.file_name, file_content));
+                }
+                _ => {
+                    content.push_str(&format!("\nFile attached: {} ({} bytes)", attachment.file_name, attachment.file_size));
+                }
+            }
+        }
+    }
+    
+    let tool_calls = msg.tool_calls.unwrap_or_default().into_iter().map(|tc| NativeToolCall {
+        function: NativeToolCallFunction {
+            name: tc.name,
+            arguments: tc.parameters,
+        },
+    }).collect();
+    
+    NativeMessage {
+        role: role_name(&msg.role),
+        content,
+        images,
+        tool_calls,
+        thinking: None,
+    }
+}
+
+/// Stream chunks of one native response line; a line can carry thinking and content together
+fn native_chunks(message: NativeMessage) -> Vec<StreamChunk> {
+    let mut chunks = Vec::new();
+    if let Some(thinking) = message.thinking.filter(|thinking| !thinking.is_empty()) {
+        chunks.push(StreamChunk::Reasoning(thinking));
+    }
+    if !message.content.is_empty() {
+        chunks.push(StreamChunk::Text(message.content));
+    }
+    chunks
+}
+
+/// Parse a newline-delimited JSON response into `T`s
+fn ndjson_stream<T: DeserializeOwned + Send + 'static>(response: reqwest::Response) -> impl Stream<Item = Result<T, LlmError>> + Send {
+    futures::StreamExt::map(stream_decoder::ndjson_lines(response), |line| parse_ndjson_line(&line?))
+}
+
+fn parse_ndjson_line<T: DeserializeOwned>(line: &str) -> Result<T, LlmError> {
+    if let Ok(NativeError { error }) = serde_json::from_str::<NativeError>(line) {
+        return Err(LlmError::Api(format!("Ollama API error: {}", error)));
+    }
+    serde_json::from_str(line).map_err(|e| LlmError::Api(format!("Invalid Ollama response: {}", e)))
 }
 
 #[async_trait]
@@ -100,7 +420,11 @@ impl LlmClient for OllamaClient {
         messages: Vec<Message>,
         temperature: Option<f32>,
         max_tokens: Option<u32>,
-    ) -> Result<Pin<Box<dyn Stream<Item = Result<String, LlmError>> + Send>>, LlmError> {
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamChunk, LlmError>> + Send>>, LlmError> {
+        if self.settings().api == OllamaApi::Native {
+            return self.native_message_stream(messages, temperature, max_tokens).await;
+        }
+        
         let ollama_messages: Vec<OllamaMessage> = messages
             .into_iter()
             .map(|msg| {
@@ -166,53 +490,26 @@ impl LlmClient for OllamaClient {
             .await?;
 
         if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(LlmError::Api(format!("Ollama API error: {}", error_text)));
+            return Err(LlmError::from_response("Ollama", response).await);
         }
 
-        let stream = response.bytes_stream();
-        let stream = futures::StreamExt::map(stream, |chunk_result| {
-            chunk_result
-                .map_err(|e| LlmError::Http(e))
-                .and_then(|chunk| {
-                    let chunk_str = String::from_utf8(chunk.to_vec())
-                        .map_err(|e| LlmError::Api(format!("Invalid UTF-8: {}", e)))?;
-                    
-                    // Parse SSE format
-                    let lines: Vec<&str> = chunk_str.lines().collect();
-                    let mut content = String::new();
-                    
-                    for line in lines {
-                        if line.starts_with("data: ") {
-                            let data = &line[6..]; // Remove "data: " prefix
-                            if data == "[DONE]" {
-                                break;
-                            }
-                            
-                            // Parse JSON
-                            if let Ok(stream_response) = serde_json::from_str::<OllamaStreamResponse>(data) {
-                                if let Some(choice) = stream_response.choices.first() {
-                                    if let Some(content_delta) = &choice.delta.content {
-                                        content.push_str(content_delta);
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    
-                    if content.is_empty() {
-                        Ok(None)
-                    } else {
-                        Ok(Some(content))
-                    }
-                })
-        });
-        let stream = futures::StreamExt::filter_map(stream, |result| async move {
-            match result {
-                Ok(Some(content)) => Some(Ok(content)),
-                Ok(None) => None,
-                Err(e) => Some(Err(e)),
+        let stream = futures::StreamExt::filter_map(stream_decoder::sse_events(response), |event| async move {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => return Some(Err(e)),
+            };
+            if let Some(message) = event.error_message() {
+                return Some(Err(LlmError::Api(format!("Ollama API error: {}", message))));
+            }
+            if event.is_done() {
+                return None;
             }
+            serde_json::from_str::<OllamaStreamResponse>(&event.data)
+                .ok()
+                .and_then(|stream_response| stream_response.choices.into_iter().next())
+                .and_then(|choice| choice.delta.content)
+                .filter(|content| !content.is_empty())
+                .map(|content| Ok(StreamChunk::Text(content)))
         });
 
         Ok(Box::pin(stream))
@@ -225,6 +522,10 @@ impl LlmClient for OllamaClient {
         temperature: Option<f32>,
         max_tokens: Option<u32>,
     ) -> Result<ChatResponse, LlmError> {
+        if self.settings().api == OllamaApi::Native {
+            return self.native_message_with_tools(messages, available_tools, temperature, max_tokens).await;
+        }
+        
         let ollama_messages: Vec<OllamaMessage> = messages
             .into_iter()
             .map(|msg| {
@@ -317,8 +618,7 @@ impl LlmClient for OllamaClient {
             .await?;
 
         if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(LlmError::Api(format!("Ollama API error: {}", error_text)));
+            return Err(LlmError::from_response("Ollama", response).await);
         }
 
         let response_data: OllamaResponse = response.json().await?;
@@ -343,7 +643,114 @@ impl LlmClient for OllamaClient {
         Ok(ChatResponse {
             content,
             tool_calls,
+            reasoning: None,
         })
     }
+    
+    async fn list_models(&self) -> Result<Vec<String>, LlmError> {
+        let models = self.installed_models().await?;
+        Ok(models.into_iter().map(|model| model.name).collect())
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(endpoint: &str) -> OllamaClient {
+        OllamaClient::new(LlmProfile {
+            backend: "ollama".to_string(),
+            endpoint: endpoint.to_string(),
+            model: "llama3.2".to_string(),
+            ..LlmProfile::default()
+        })
+    }
+
+    #[test]
+    fn test_base_url() {
+        for endpoint in [
+            "http://localhost:11434",
+            "http://localhost:11434/",
+            "http://localhost:11434/v1",
+            "http://localhost:11434/v1/",
+            "http://localhost:11434/v1/chat/completions",
+            "http://localhost:11434/api",
+            "http://localhost:11434/api/chat",
+        ] {
+            assert_eq!(client(endpoint).base_url(), "http://localhost:11434", "{}", endpoint);
+        }
+        // Only whole path segments are stripped
+        assert_eq!(client("http://ollama.lan/v1x").base_url(), "http://ollama.lan/v1x");
+    }
+
+    #[test]
+    fn test_native_chat_request() {
+        let mut ollama = client("http://localhost:11434/api/chat");
+        ollama.profile.thinking_budget = Some(1024);
+        ollama.profile.ollama = Some(OllamaSettings {
+            api: OllamaApi::Native,
+            num_ctx: Some(8192),
+            keep_alive: Some("10m".to_string()),
+            format: None,
+        });
+
+        let image_path = std::env::temp_dir().join(format!("ollama_test_{}.png", std::process::id()));
+        std::fs::write(&image_path, b"png").unwrap();
+        let image = Attachment {
+            file_path: image_path.to_string_lossy().into_owned(),
+            file_name: "cat.png".to_string(),
+            mime_type: "image/png".to_string(),
+            file_size: 3,
+            content: None,
+        };
+        let lookup = ToolCall { id: "call_1".to_string(), name: "lookup".to_string(), parameters: serde_json::json!({ "q": "cats" }) };
+        let messages = vec![
+            Message::new_with_attachments(Role::User, "What is this?".to_string(), vec![image]),
+            Message::new_with_tool_calls(Role::Assistant, String::new(), vec![lookup]),
+        ];
+
+        let request = ollama.native_chat_request(messages, None, true, Some(0.2), None);
+        let body = serde_json::to_value(&request).unwrap();
+        let _ = std::fs::remove_file(&image_path);
+
+        assert_eq!(body["model"], "llama3.2");
+        assert_eq!(body["stream"], true);
+        assert_eq!(body["think"], true);
+        assert_eq!(body["keep_alive"], "10m");
+        assert!(body.get("tools").is_none());
+        assert!(body.get("format").is_none());
+        // Options fall back to the profile where the call sets nothing
+        assert_eq!(body["options"]["temperature"].as_f64().unwrap() as f32, 0.2);
+        assert_eq!(body["options"]["num_predict"], 1000);
+        assert_eq!(body["options"]["num_ctx"], 8192);
+
+        assert_eq!(body["messages"][0]["images"], serde_json::json!(["cG5n"]));
+        assert!(body["messages"][0].get("tool_calls").is_none());
+        assert_eq!(body["messages"][1]["tool_calls"][0]["function"]["arguments"], serde_json::json!({ "q": "cats" }));
+        assert!(body["messages"][1].get("thinking").is_none());
+    }
+
+    fn chunks(line: &str) -> Vec<StreamChunk> {
+        let response: NativeChatResponse = parse_ndjson_line(line).unwrap();
+        response.message.map(native_chunks).unwrap_or_default()
+    }
+
+    #[test]
+    fn test_native_stream_chunks() {
+        assert_eq!(
+            chunks(r#"{"message":{"role":"assistant","content":"","thinking":"Let me see"},"done":false}"#),
+            vec![StreamChunk::Reasoning("Let me see".to_string())]
+        );
+        // The chunk where thinking ends can already carry the answer
+        assert_eq!(
+            chunks(r#"{"message":{"role":"assistant","content":"Paris","thinking":"."},"done":false}"#),
+            vec![StreamChunk::Reasoning(".".to_string()), StreamChunk::Text("Paris".to_string())]
+        );
+        assert_eq!(
+            chunks(r#"{"message":{"role":"assistant","content":"!"},"done":false}"#),
+            vec![StreamChunk::Text("!".to_string())]
+        );
+        assert!(chunks(r#"{"message":{"role":"assistant","content":""},"done":true}"#).is_empty());
+        assert!(parse_ndjson_line::<NativeChatResponse>(r#"{"error":"model not found"}"#).is_err());
+    }
+}
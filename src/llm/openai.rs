@@ -1,5 +1,5 @@
 use super::*;
-use crate::config::LlmProfile;
+use crate::config::{AzureAuth, AzureSettings, LlmProfile};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::pin::Pin;
@@ -21,6 +21,9 @@ struct OpenAIMessage {
     content: Option<serde_json::Value>,
     tool_calls: Option<Vec<OpenAIToolCall>>,
     tool_call_id: Option<String>,
+    // DeepSeek reasoning models; never sent back, DeepSeek rejects it in requests
+    #[serde(default, skip_serializing)]
+    reasoning_content: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -59,6 +62,16 @@ struct OpenAIChoice {
     message: OpenAIMessage,
 }
 
+#[derive(Debug, Deserialize)]
+struct OpenAIModelList {
+    data: Vec<OpenAIModel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIModel {
+    id: String,
+}
+
 #[derive(Debug, Deserialize)]
 struct OpenAIStreamResponse {
     choices: Vec<OpenAIStreamChoice>,
@@ -72,11 +85,20 @@ struct OpenAIStreamChoice {
 #[derive(Debug, Deserialize)]
 struct OpenAIDelta {
     content: Option<String>,
+    reasoning_content: Option<String>,
 }
 
+/// Newer Azure data-plane API versions no longer list deployments
+const AZURE_DEPLOYMENTS_API_VERSION: &str = "2022-12-01";
+
+/// Entra ID tokens from `token_command` are reused for this long
+const ENTRA_TOKEN_TTL: std::time::Duration = std::time::Duration::from_secs(30 * 60);
+
+/// Serves the "openai" and "deepseek" backends, and "azure" with Azure's URLs and auth
 pub struct OpenAIClient {
     client: Client,
     profile: LlmProfile,
+    entra_token: tokio::sync::Mutex<Option<(String, std::time::Instant)>>,
 }
 
 impl OpenAIClient {
@@ -84,10 +106,125 @@ impl OpenAIClient {
         Self {
             client: Client::new(),
             profile,
+            entra_token: tokio::sync::Mutex::new(None),
+        }
+    }
+    
+    fn azure(&self) -> Option<AzureSettings> {
+        (self.profile.backend == "azure").then(|| self.profile.azure.clone().unwrap_or_default())
+    }
+    
+    fn api_name(&self) -> &'static str {
+        if self.azure().is_some() { "Azure OpenAI" } else { "OpenAI" }
+    }
+    
+    /// Resource root of an Azure endpoint, e.g. `https://my-resource.openai.azure.com`
+    fn azure_root(&self) -> &str {
+        let endpoint = self.profile.endpoint.trim_end_matches('/');
+        match endpoint.find("/openai") {
+            Some(index) => &endpoint[..index],
+            None => endpoint,
+        }
+    }
+    
+    /// Chat completions URL; Azure addresses a deployment and needs an `api-version`
+    fn chat_url(&self) -> String {
+        let Some(azure) = self.azure() else {
+            return self.profile.endpoint.clone();
+        };
+        let endpoint = self.profile.endpoint.trim_end_matches('/');
+        if endpoint.contains("/openai/deployments/") && endpoint.contains("/chat/completions") {
+            if endpoint.contains("api-version=") {
+                endpoint.to_string()
+            } else {
+                let separator = if endpoint.contains('?') { '&' } else { '?' };
+                format!("{}{}api-version={}", endpoint, separator, azure.api_version)
+            }
+        } else {
+            let deployment = azure.deployment.as_deref().unwrap_or(&self.profile.model);
+            format!(
+                "{}/openai/deployments/{}/chat/completions?api-version={}",
+                self.azure_root(), deployment, azure.api_version
+            )
+        }
+    }
+    
+    /// `/models` next to the configured chat completions endpoint. On Azure the model
+    /// picker needs deployment names, which are what chat URLs address.
+    fn models_url(&self) -> String {
+        if self.azure().is_some() {
+            return format!("{}/openai/deployments?api-version={}", self.azure_root(), AZURE_DEPLOYMENTS_API_VERSION);
         }
+        let endpoint = self.profile.endpoint.trim_end_matches('/');
+        let base = endpoint.strip_suffix("/chat/completions").unwrap_or(endpoint);
+        format!("{}/models", base)
+    }
+    
+    /// Add the credentials this backend expects to a request
+    async fn authorize(&self, request_builder: reqwest::RequestBuilder) -> Result<reqwest::RequestBuilder, LlmError> {
+        match self.azure() {
+            None => Ok(request_builder.header("Authorization", format!("Bearer {}", self.profile.api_key))),
+            Some(azure) => match azure.auth {
+                AzureAuth::ApiKey => Ok(request_builder.header("api-key", &self.profile.api_key)),
+                AzureAuth::Entra => {
+                    let token = self.entra_token(&azure).await?;
+                    Ok(request_builder.header("Authorization", format!("Bearer {}", token)))
+                }
+            },
+        }
+    }
+    
+    /// Entra ID token from `token_command` (cached), or `api_key` when no command is set
+    async fn entra_token(&self, azure: &AzureSettings) -> Result<String, LlmError> {
+        let Some(command) = &azure.token_command else {
+            return Ok(self.profile.api_key.clone());
+        };
+        
+        let mut cached = self.entra_token.lock().await;
+        if let Some((token, fetched_at)) = cached.as_ref() {
+            if fetched_at.elapsed() < ENTRA_TOKEN_TTL {
+                return Ok(token.clone());
+            }
+        }
+        
+        let output = tokio::process::Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .output()
+            .await
+            .map_err(|e| LlmError::Config(format!("Failed to run token_command: {}", e)))?;
+        if !output.status.success() {
+            return Err(LlmError::Config(format!(
+                "token_command failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        let token = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if token.is_empty() {
+            return Err(LlmError::Config("token_command printed no token".to_string()));
+        }
+        
+        *cached = Some((token.clone(), std::time::Instant::now()));
+        Ok(token)
     }
 }
 
+/// Text and reasoning (DeepSeek's `reasoning_content`) in one streamed delta
+fn stream_chunks(data: &str) -> Vec<StreamChunk> {
+    let mut chunks = Vec::new();
+    if let Ok(stream_response) = serde_json::from_str::<OpenAIStreamResponse>(data) {
+        if let Some(choice) = stream_response.choices.into_iter().next() {
+            if let Some(reasoning_delta) = choice.delta.reasoning_content.filter(|r| !r.is_empty()) {
+                chunks.push(StreamChunk::Reasoning(reasoning_delta));
+            }
+            if let Some(content_delta) = choice.delta.content.filter(|c| !c.is_empty()) {
+                chunks.push(StreamChunk::Text(content_delta));
+            }
+        }
+    }
+    chunks
+}
+
 #[async_trait]
 impl LlmClient for OpenAIClient {
 
@@ -96,7 +233,7 @@ impl LlmClient for OpenAIClient {
         messages: Vec<Message>,
         temperature: Option<f32>,
         max_tokens: Option<u32>,
-    ) -> Result<Pin<Box<dyn Stream<Item = Result<String, LlmError>> + Send>>, LlmError> {
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamChunk, LlmError>> + Send>>, LlmError> {
         let openai_messages: Vec<OpenAIMessage> = messages
             .into_iter()
             .map(|msg| {
@@ -164,6 +301,7 @@ impl LlmClient for OpenAIClient {
                     content: Some(content),
                     tool_calls: None,
                     tool_call_id: msg.tool_call_id,
+                    reasoning_content: None,
                 }
             })
             .collect();
@@ -179,62 +317,35 @@ impl LlmClient for OpenAIClient {
         };
 
         let response = self
-            .client
-            .post(&self.profile.endpoint)
-            .header("Authorization", format!("Bearer {}", self.profile.api_key))
+            .authorize(self.client.post(self.chat_url()))
+            .await?
             .header("Content-Type", "application/json")
             .json(&request)
             .send()
             .await?;
 
         if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(LlmError::Api(format!("OpenAI API error: {}", error_text)));
+            return Err(LlmError::from_response(self.api_name(), response).await);
         }
 
-        let stream = response.bytes_stream();
-        let stream = futures::StreamExt::map(stream, |chunk_result| {
-            chunk_result
-                .map_err(|e| LlmError::Http(e))
-                .and_then(|chunk| {
-                    let chunk_str = String::from_utf8(chunk.to_vec())
-                        .map_err(|e| LlmError::Api(format!("Invalid UTF-8: {}", e)))?;
-                    
-                    // Parse SSE format
-                    let lines: Vec<&str> = chunk_str.lines().collect();
-                    let mut content = String::new();
-                    
-                    for line in lines {
-                        if line.starts_with("data: ") {
-                            let data = &line[6..]; // Remove "data: " prefix
-                            if data == "[DONE]" {
-                                break;
-                            }
-                            
-                            // Parse JSON
-                            if let Ok(stream_response) = serde_json::from_str::<OpenAIStreamResponse>(data) {
-                                if let Some(choice) = stream_response.choices.first() {
-                                    if let Some(content_delta) = &choice.delta.content {
-                                        content.push_str(content_delta);
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    
-                    if content.is_empty() {
-                        Ok(None)
-                    } else {
-                        Ok(Some(content))
-                    }
-                })
-        });
-        let stream = futures::StreamExt::filter_map(stream, |result| async move {
-            match result {
-                Ok(Some(content)) => Some(Ok(content)),
-                Ok(None) => None,
-                Err(e) => Some(Err(e)),
+        let api = self.api_name();
+        let stream = futures::StreamExt::map(stream_decoder::sse_events(response), move |event| {
+            let event = event?;
+            if let Some(message) = event.error_message() {
+                return Err(LlmError::Api(format!("{} API error: {}", api, message)));
+            }
+
+            if event.is_done() {
+                return Ok(Vec::new());
             }
+            Ok(stream_chunks(&event.data))
+        });
+        let stream = futures::StreamExt::flat_map(stream, |result| {
+            let items: Vec<Result<StreamChunk, LlmError>> = match result {
+                Ok(chunks) => chunks.into_iter().map(Ok).collect(),
+                Err(e) => vec![Err(e)],
+            };
+            futures::stream::iter(items)
         });
 
         Ok(Box::pin(stream))
@@ -327,6 +438,7 @@ impl LlmClient for OpenAIClient {
                     content: Some(content),
                     tool_calls,
                     tool_call_id: msg.tool_call_id,
+                    reasoning_content: None,
                 }
             })
             .collect();
@@ -356,17 +468,15 @@ impl LlmClient for OpenAIClient {
         };
 
         let response = self
-            .client
-            .post(&self.profile.endpoint)
-            .header("Authorization", format!("Bearer {}", self.profile.api_key))
+            .authorize(self.client.post(self.chat_url()))
+            .await?
             .header("Content-Type", "application/json")
             .json(&request)
             .send()
             .await?;
 
         if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(LlmError::Api(format!("OpenAI API error: {}", error_text)));
+            return Err(LlmError::from_response(self.api_name(), response).await);
         }
 
         let response_data: OpenAIResponse = response.json().await?;
@@ -374,7 +484,7 @@ impl LlmClient for OpenAIClient {
         let choice = response_data
             .choices
             .first()
-            .ok_or_else(|| LlmError::Api("No response from OpenAI".to_string()))?;
+            .ok_or_else(|| LlmError::Api(format!("No response from {}", self.api_name())))?;
 
         let content = match choice.message.content.clone() {
             Some(serde_json::Value::String(s)) => s,
@@ -403,10 +513,104 @@ impl LlmClient for OpenAIClient {
             Vec::new()
         };
 
+        let reasoning = choice
+            .message
+            .reasoning_content
+            .clone()
+            .filter(|text| !text.is_empty())
+            .map(|text| Reasoning { text, blocks: Vec::new() });
+
         Ok(ChatResponse {
             content,
             tool_calls,
+            reasoning,
         })
     }
+    
+    async fn list_models(&self) -> Result<Vec<String>, LlmError> {
+        let response = self
+            .authorize(self.client.get(self.models_url()))
+            .await?
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(LlmError::from_response(self.api_name(), response).await);
+        }
+
+        let list: OpenAIModelList = response.json().await?;
+        let mut models: Vec<String> = list.data.into_iter().map(|model| model.id).collect();
+        models.sort();
+        Ok(models)
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn azure_client(endpoint: &str, deployment: Option<&str>) -> OpenAIClient {
+        OpenAIClient::new(LlmProfile {
+            backend: "azure".to_string(),
+            endpoint: endpoint.to_string(),
+            model: "gpt-4o".to_string(),
+            azure: Some(AzureSettings {
+                deployment: deployment.map(str::to_string),
+                ..AzureSettings::default()
+            }),
+            ..LlmProfile::default()
+        })
+    }
+
+    #[test]
+    fn test_azure_chat_url() {
+        let client = azure_client("https://res.openai.azure.com/", None);
+        assert_eq!(client.chat_url(), "https://res.openai.azure.com/openai/deployments/gpt-4o/chat/completions?api-version=2024-10-21");
+
+        let client = azure_client("https://res.openai.azure.com/openai", Some("prod-4o"));
+        assert_eq!(client.chat_url(), "https://res.openai.azure.com/openai/deployments/prod-4o/chat/completions?api-version=2024-10-21");
+
+        // Full deployment URLs are kept, with api-version added when missing
+        let full = "https://res.openai.azure.com/openai/deployments/x/chat/completions";
+        assert_eq!(azure_client(full, None).chat_url(), format!("{}?api-version=2024-10-21", full));
+        let versioned = format!("{}?api-version=2025-01-01-preview", full);
+        assert_eq!(azure_client(&versioned, None).chat_url(), versioned);
+    }
+
+    #[test]
+    fn test_models_url() {
+        let client = azure_client("https://res.openai.azure.com/openai/deployments/x/chat/completions", None);
+        assert_eq!(client.models_url(), "https://res.openai.azure.com/openai/deployments?api-version=2022-12-01");
+
+        let client = OpenAIClient::new(LlmProfile {
+            endpoint: "https://api.openai.com/v1/chat/completions".to_string(),
+            ..LlmProfile::default()
+        });
+        assert_eq!(client.chat_url(), "https://api.openai.com/v1/chat/completions");
+        assert_eq!(client.models_url(), "https://api.openai.com/v1/models");
+    }
+
+    #[test]
+    fn test_reasoning_content_stream_chunks() {
+        assert_eq!(
+            stream_chunks(r#"{"choices":[{"index":0,"delta":{"role":"assistant","content":null,"reasoning_content":"Hmm"}}]}"#),
+            vec![StreamChunk::Reasoning("Hmm".to_string())]
+        );
+        assert_eq!(
+            stream_chunks(r#"{"choices":[{"index":0,"delta":{"content":"Hi","reasoning_content":""}}]}"#),
+            vec![StreamChunk::Text("Hi".to_string())]
+        );
+        assert!(stream_chunks(r#"{"choices":[]}"#).is_empty());
+    }
+
+    #[test]
+    fn test_reasoning_content_is_not_sent_back() {
+        let response: OpenAIResponse = serde_json::from_str(
+            r#"{"choices":[{"message":{"role":"assistant","content":"42","reasoning_content":"Six times seven"}}]}"#
+        ).unwrap();
+        let message = &response.choices[0].message;
+        assert_eq!(message.reasoning_content.as_deref(), Some("Six times seven"));
+        // DeepSeek rejects requests that echo it
+        assert!(serde_json::to_value(message).unwrap().get("reasoning_content").is_none());
+    }
+}
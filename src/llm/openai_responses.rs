@@ -0,0 +1,620 @@
+use super::*;
+use crate::config::{LlmProfile, ResponsesSettings};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::hash::{Hash, Hasher};
+use std::pin::Pin;
+
+// OpenAI Responses API (/v1/responses)
+#[derive(Debug, Serialize)]
+struct ResponsesRequest {
+    model: String,
+    input: Vec<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instructions: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<ResponsesTool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    previous_response_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reasoning: Option<ReasoningConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_output_tokens: Option<u32>,
+    store: bool,
+    stream: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct ResponsesTool {
+    r#type: &'static str,
+    name: String,
+    description: String,
+    parameters: serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+struct ReasoningConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    effort: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    summary: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponsesResponse {
+    id: String,
+    #[serde(default)]
+    output: Vec<OutputItem>,
+    error: Option<ResponsesError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponsesError {
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum OutputItem {
+    Message {
+        #[serde(default)]
+        content: Vec<OutputContent>,
+    },
+    FunctionCall {
+        call_id: String,
+        name: String,
+        arguments: String,
+    },
+    Reasoning {
+        #[serde(default)]
+        summary: Vec<SummaryPart>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum OutputContent {
+    OutputText { text: String },
+    Refusal { refusal: String },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct SummaryPart {
+    text: String,
+}
+
+/// Semantic events of a streamed response; only the ones we act on are listed
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+enum StreamEvent {
+    #[serde(rename = "response.output_text.delta")]
+    OutputTextDelta { delta: String },
+    #[serde(rename = "response.reasoning_summary_text.delta")]
+    ReasoningSummaryDelta { delta: String },
+    #[serde(rename = "response.failed")]
+    Failed { response: ResponsesResponse },
+    #[serde(rename = "error")]
+    Error { message: String },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelList {
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+/// The last stored response and the history it covers, for `previous_response_id`
+struct Chain {
+    response_id: String,
+    /// Length of the message history that produced the response
+    covered: usize,
+    digest: u64,
+}
+
+pub struct ResponsesClient {
+    client: Client,
+    profile: LlmProfile,
+    chain: std::sync::Mutex<Option<Chain>>,
+}
+
+impl ResponsesClient {
+    pub fn new(profile: LlmProfile) -> Self {
+        Self {
+            client: Client::new(),
+            profile,
+            chain: std::sync::Mutex::new(None),
+        }
+    }
+
+    fn settings(&self) -> ResponsesSettings {
+        self.profile.responses.clone().unwrap_or_default()
+    }
+
+    /// API root, accepting `.../v1`, `.../v1/responses` or `.../v1/chat/completions`
+    fn base_url(&self) -> &str {
+        let endpoint = self.profile.endpoint.trim_end_matches('/');
+        for suffix in ["/responses", "/chat/completions"] {
+            if let Some(base) = endpoint.strip_suffix(suffix) {
+                return base;
+            }
+        }
+        endpoint
+    }
+
+    fn build_request(
+        &self,
+        messages: &[Message],
+        tools: Vec<ToolDefinition>,
+        temperature: Option<f32>,
+        max_tokens: Option<u32>,
+        stream: bool,
+    ) -> (ResponsesRequest, Option<Chain>) {
+        let settings = self.settings();
+
+        let instructions: Vec<&str> = messages
+            .iter()
+            .filter(|msg| msg.role == Role::System)
+            .map(|msg| msg.content.as_str())
+            .collect();
+
+        // Continue from the stored response when the history only grew past it
+        let chained = if settings.chain && !stream {
+            self.chain.lock().unwrap().take().filter(|chain| {
+                messages.len() > chain.covered + 1
+                    && messages[chain.covered].role == Role::Assistant
+                    && history_digest(&messages[..chain.covered]) == chain.digest
+            })
+        } else {
+            None
+        };
+        let new_messages = match &chained {
+            Some(chain) => &messages[chain.covered + 1..],
+            None => messages,
+        };
+
+        let reasoning = (settings.reasoning_effort.is_some() || settings.reasoning_summary.is_some()).then(|| ReasoningConfig {
+            effort: settings.reasoning_effort.clone(),
+            summary: settings.reasoning_summary.clone(),
+        });
+
+        let request = ResponsesRequest {
+            model: self.profile.model.clone(),
+            input: new_messages.iter().flat_map(to_input_items).collect(),
+            instructions: (!instructions.is_empty()).then(|| instructions.join("\n\n")),
+            tools: tools.into_iter().map(|tool| ResponsesTool {
+                r#type: "function",
+                name: tool.name,
+                description: tool.description,
+                parameters: tool.parameters,
+            }).collect(),
+            previous_response_id: chained.as_ref().map(|chain| chain.response_id.clone()),
+            reasoning,
+            // Reasoning models reject sampling parameters
+            temperature: if settings.reasoning_effort.is_some() {
+                None
+            } else {
+                temperature.or(self.profile.temperature)
+            },
+            // The API refuses limits below 16 tokens
+            max_output_tokens: max_tokens.or(self.profile.max_tokens).map(|tokens| tokens.max(16)),
+            store: settings.chain,
+            stream,
+        };
+        (request, chained)
+    }
+
+    async fn post(&self, request: &ResponsesRequest) -> Result<reqwest::Response, LlmError> {
+        let response = self
+            .client
+            .post(format!("{}/responses", self.base_url()))
+            .header("Authorization", format!("Bearer {}", self.profile.api_key))
+            .header("Content-Type", "application/json")
+            .json(request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(LlmError::from_response("OpenAI Responses", response).await);
+        }
+        Ok(response)
+    }
+}
+
+fn role_name(role: &Role) -> &'static str {
+    match role {
+        Role::User | Role::Tool => "user",
+        Role::Assistant => "assistant",
+        Role::System => "developer",
+    }
+}
+
+/// Convert a message into Responses input items; system messages go to `instructions`
+fn to_input_items(msg: &Message) -> Vec<serde_json::Value> {
+    let mut items = Vec::new();
+    match msg.role {
+        Role::System => {}
+        Role::Tool => {
+            items.push(serde_json::json!({
+                "type": "function_call_output",
+                "call_id": msg.tool_call_id.clone().unwrap_or_default(),
+                "output": msg.content,
+            }));
+        }
+        Role::Assistant => {
+            if !msg.content.is_empty() {
+                items.push(serde_json::json!({
+                    "role": "assistant",
+                    "content": msg.content,
+                }));
+            }
+            for tool_call in msg.tool_calls.iter().flatten() {
+                items.push(serde_json::json!({
+                    "type": "function_call",
+                    "call_id": tool_call.id,
+                    "name": tool_call.name,
+                    "arguments": serde_json::to_string(&tool_call.parameters).unwrap_or_else(|_| "{}".to_string()),
+                }));
+            }
+        }
+        Role::User => {
+            let mut content = vec![serde_json::json!({ "type": "input_text", "text": msg.content })];
+            for attachment in msg.attachments.iter().flatten() {
+                let Some(data) = &attachment.content else { continue };
+                if attachment.mime_type.starts_with("image/") {
+                    content.push(serde_json::json!({
+                        "type": "input_image",
+                        "image_url": format!("data:{};base64,{}", attachment.mime_type, data),
+                    }));
+                } else if attachment.mime_type.starts_with("text/") {
+                    content.push(serde_json::json!({
+                        "type": "input_text",
+                        "text": format!("File: {}\nContent:\n{}", attachment.file_name, data),
+                    }));
+                }
+            }
+            items.push(serde_json::json!({
+                "role": role_name(&msg.role),
+                "content": content,
+            }));
+        }
+    }
+    items
+}
+
+/// Identity of a message prefix, to check a later request extends the chained one
+fn history_digest(messages: &[Message]) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    for msg in messages {
+        role_name(&msg.role).hash(&mut hasher);
+        msg.content.hash(&mut hasher);
+        msg.tool_call_id.hash(&mut hasher);
+        for tool_call in msg.tool_calls.iter().flatten() {
+            tool_call.id.hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+/// What a streamed event adds to the answer, if anything
+fn stream_chunk(event: StreamEvent) -> Option<Result<StreamChunk, LlmError>> {
+    match event {
+        StreamEvent::OutputTextDelta { delta } => Some(Ok(StreamChunk::Text(delta))),
+        StreamEvent::ReasoningSummaryDelta { delta } => Some(Ok(StreamChunk::Reasoning(delta))),
+        StreamEvent::Failed { response } => Some(Err(LlmError::Api(format!(
+            "OpenAI Responses API error: {}",
+            response.error.map(|e| e.message).unwrap_or_else(|| "response failed".to_string())
+        )))),
+        StreamEvent::Error { message } => Some(Err(LlmError::Api(format!("OpenAI Responses API error: {}", message)))),
+        StreamEvent::Other => None,
+    }
+}
+
+/// Answer, tool calls and reasoning summary of a response's output items.
+/// Reasoning items stay on the server and are picked up again through chaining.
+fn chat_response(output: Vec<OutputItem>) -> ChatResponse {
+    let mut content = String::new();
+    let mut reasoning = String::new();
+    let mut tool_calls = Vec::new();
+    for item in output {
+        match item {
+            OutputItem::Message { content: parts } => {
+                for part in parts {
+                    match part {
+                        OutputContent::OutputText { text } => content.push_str(&text),
+                        OutputContent::Refusal { refusal } => content.push_str(&refusal),
+                        OutputContent::Other => {}
+                    }
+                }
+            }
+            OutputItem::FunctionCall { call_id, name, arguments } => {
+                tool_calls.push(ToolCall {
+                    id: call_id,
+                    name,
+                    parameters: serde_json::from_str(&arguments).unwrap_or_default(),
+                });
+            }
+            OutputItem::Reasoning { summary } => {
+                for part in summary {
+                    reasoning.push_str(&part.text);
+                }
+            }
+            OutputItem::Other => {}
+        }
+    }
+
+    ChatResponse {
+        content,
+        tool_calls,
+        reasoning: (!reasoning.is_empty()).then(|| Reasoning { text: reasoning, blocks: Vec::new() }),
+    }
+}
+
+/// Typed events of the SSE body; the event type is repeated in each payload
+fn event_stream(response: reqwest::Response) -> impl Stream<Item = Result<StreamEvent, LlmError>> + Send {
+    futures::StreamExt::filter_map(stream_decoder::sse_events(response), |event| {
+        futures::future::ready(match event {
+            Ok(event) => serde_json::from_str::<StreamEvent>(&event.data).ok().map(Ok),
+            Err(e) => Some(Err(e)),
+        })
+    })
+}
+
+#[async_trait]
+impl LlmClient for ResponsesClient {
+    async fn send_message_stream(
+        &self,
+        messages: Vec<Message>,
+        temperature: Option<f32>,
+        max_tokens: Option<u32>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamChunk, LlmError>> + Send>>, LlmError> {
+        let (request, _) = self.build_request(&messages, Vec::new(), temperature, max_tokens, true);
+        let response = self.post(&request).await?;
+
+        let stream = futures::StreamExt::filter_map(event_stream(response), |event| {
+            futures::future::ready(match event {
+                Ok(event) => stream_chunk(event),
+                Err(e) => Some(Err(e)),
+            })
+        });
+
+        Ok(Box::pin(stream))
+    }
+
+    async fn send_message_with_tools(
+        &self,
+        messages: Vec<Message>,
+        available_tools: Vec<ToolDefinition>,
+        temperature: Option<f32>,
+        max_tokens: Option<u32>,
+    ) -> Result<ChatResponse, LlmError> {
+        let (request, chained) = self.build_request(&messages, available_tools, temperature, max_tokens, false);
+        let response = match self.post(&request).await {
+            // A stored response can expire; fall back to sending the whole history
+            Err(e @ LlmError::Api(_)) if chained.is_some() => {
+                log::warn!("Chained request failed, resending full history: {}", e);
+                let mut request = request;
+                request.previous_response_id = None;
+                request.input = messages.iter().flat_map(to_input_items).collect();
+                self.post(&request).await?
+            }
+            result => result?,
+        };
+
+        let response_data: ResponsesResponse = response.json().await?;
+        if let Some(error) = response_data.error {
+            return Err(LlmError::Api(format!("OpenAI Responses API error: {}", error.message)));
+        }
+
+        if self.settings().chain {
+            *self.chain.lock().unwrap() = Some(Chain {
+                response_id: response_data.id,
+                covered: messages.len(),
+                digest: history_digest(&messages),
+            });
+        }
+
+        Ok(chat_response(response_data.output))
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>, LlmError> {
+        let response = self
+            .client
+            .get(format!("{}/models", self.base_url()))
+            .header("Authorization", format!("Bearer {}", self.profile.api_key))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(LlmError::from_response("OpenAI Responses", response).await);
+        }
+
+        let list: ModelList = response.json().await?;
+        let mut models: Vec<String> = list.data.into_iter().map(|model| model.id).collect();
+        models.sort();
+        Ok(models)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Answer consecutive requests on a local port with `replies`, recording the request bodies
+    async fn mock_server(replies: Vec<(u16, serde_json::Value)>) -> (String, Arc<Mutex<Vec<serde_json::Value>>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/v1", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let received = requests.clone();
+        tokio::spawn(async move {
+            for (status, body) in replies {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut data = Vec::new();
+                let mut buf = [0u8; 4096];
+                let body_start = loop {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    data.extend_from_slice(&buf[..n]);
+                    if let Some(end) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+                        break end + 4;
+                    }
+                };
+                let headers = String::from_utf8_lossy(&data[..body_start]).to_lowercase();
+                let length: usize = headers
+                    .lines()
+                    .find_map(|line| line.strip_prefix("content-length:"))
+                    .map(|value| value.trim().parse().unwrap())
+                    .unwrap_or(0);
+                while data.len() < body_start + length {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    data.extend_from_slice(&buf[..n]);
+                }
+                received.lock().unwrap().push(serde_json::from_slice(&data[body_start..]).unwrap());
+
+                let body = body.to_string();
+                let reply = format!(
+                    "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status, body.len(), body
+                );
+                socket.write_all(reply.as_bytes()).await.unwrap();
+            }
+        });
+        (url, requests)
+    }
+
+    fn client(endpoint: &str) -> ResponsesClient {
+        ResponsesClient::new(LlmProfile {
+            backend: "openai-responses".to_string(),
+            endpoint: endpoint.to_string(),
+            api_key: "sk-test".to_string(),
+            model: "gpt-5".to_string(),
+            ..LlmProfile::default()
+        })
+    }
+
+    fn tool_call_reply(id: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "output": [{ "type": "function_call", "call_id": "call_1", "name": "lookup", "arguments": "{\"q\":\"cats\"}" }],
+        })
+    }
+
+    fn text_reply(id: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "output": [{ "type": "message", "content": [{ "type": "output_text", "text": "Cats." }] }],
+        })
+    }
+
+    /// The history after the model asked for `lookup` and got its result
+    fn tool_turn(first: &[Message], response: &ChatResponse) -> Vec<Message> {
+        let mut messages = first.to_vec();
+        messages.push(Message::new_with_tool_calls(Role::Assistant, response.content.clone(), response.tool_calls.clone()));
+        messages.push(Message::new_tool_result("call_1".to_string(), "Cats are mammals".to_string(), false));
+        messages
+    }
+
+    #[tokio::test]
+    async fn test_tool_turn_continues_previous_response() {
+        let (url, requests) = mock_server(vec![(200, tool_call_reply("resp_1")), (200, text_reply("resp_2"))]).await;
+        let client = client(&url);
+        let first = vec![Message::new(Role::System, "Be brief".to_string()), Message::new(Role::User, "Cats?".to_string())];
+
+        let response = client.send_message_with_tools(first.clone(), Vec::new(), None, None).await.unwrap();
+        assert_eq!(response.tool_calls[0].name, "lookup");
+        let response = client.send_message_with_tools(tool_turn(&first, &response), Vec::new(), None, None).await.unwrap();
+        assert_eq!(response.content, "Cats.");
+
+        let requests = requests.lock().unwrap();
+        assert!(requests[0].get("previous_response_id").is_none());
+        assert_eq!(requests[0]["instructions"], "Be brief");
+        assert_eq!(requests[0]["store"], true);
+        // Only the tool result is new to the server
+        assert_eq!(requests[1]["previous_response_id"], "resp_1");
+        assert_eq!(requests[1]["input"], serde_json::json!([
+            { "type": "function_call_output", "call_id": "call_1", "output": "Cats are mammals" },
+        ]));
+    }
+
+    #[tokio::test]
+    async fn test_expired_response_resends_history() {
+        let expired = serde_json::json!({ "error": { "message": "Previous response with id 'resp_1' not found." } });
+        let (url, requests) = mock_server(vec![(200, tool_call_reply("resp_1")), (400, expired), (200, text_reply("resp_2"))]).await;
+        let client = client(&url);
+        let first = vec![Message::new(Role::User, "Cats?".to_string())];
+
+        let response = client.send_message_with_tools(first.clone(), Vec::new(), None, None).await.unwrap();
+        let response = client.send_message_with_tools(tool_turn(&first, &response), Vec::new(), None, None).await.unwrap();
+        assert_eq!(response.content, "Cats.");
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[1]["previous_response_id"], "resp_1");
+        assert!(requests[2].get("previous_response_id").is_none());
+        let types: Vec<&str> = requests[2]["input"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["type"].as_str().unwrap_or("message"))
+            .collect();
+        assert_eq!(types, vec!["message", "function_call", "function_call_output"]);
+    }
+
+    #[test]
+    fn test_reasoning_summary() {
+        let event = |data: &str| stream_chunk(serde_json::from_str(data).unwrap()).map(|chunk| chunk.unwrap());
+        assert_eq!(
+            event(r#"{"type":"response.reasoning_summary_text.delta","item_id":"rs_1","delta":"Weighing options"}"#),
+            Some(StreamChunk::Reasoning("Weighing options".to_string()))
+        );
+        assert_eq!(
+            event(r#"{"type":"response.output_text.delta","item_id":"msg_1","delta":"Done"}"#),
+            Some(StreamChunk::Text("Done".to_string()))
+        );
+        assert_eq!(event(r#"{"type":"response.reasoning_summary_part.added","item_id":"rs_1"}"#), None);
+
+        let response: ResponsesResponse = serde_json::from_value(serde_json::json!({
+            "id": "resp_1",
+            "output": [
+                { "type": "reasoning", "id": "rs_1", "summary": [{ "type": "summary_text", "text": "Weighing options" }] },
+                { "type": "message", "content": [{ "type": "output_text", "text": "Done" }] },
+            ],
+        })).unwrap();
+        let response = chat_response(response.output);
+        assert_eq!(response.content, "Done");
+        assert_eq!(response.reasoning.unwrap().text, "Weighing options");
+    }
+
+    #[test]
+    fn test_changed_history_is_not_chained() {
+        let client = client("https://api.openai.com/v1/responses");
+        let first = vec![Message::new(Role::User, "Cats?".to_string())];
+        let mut messages = tool_turn(&first, &ChatResponse { content: String::new(), tool_calls: Vec::new(), reasoning: None });
+
+        *client.chain.lock().unwrap() = Some(Chain { response_id: "resp_1".to_string(), covered: 1, digest: history_digest(&first) });
+        let (request, chained) = client.build_request(&messages, Vec::new(), None, None, false);
+        assert_eq!(request.previous_response_id.as_deref(), Some("resp_1"));
+        assert!(chained.is_some());
+
+        // An edited earlier message, or no chain at all, sends everything
+        messages[0].content = "Dogs?".to_string();
+        *client.chain.lock().unwrap() = Some(Chain { response_id: "resp_1".to_string(), covered: 1, digest: history_digest(&first) });
+        let (request, chained) = client.build_request(&messages, Vec::new(), None, None, false);
+        assert!(request.previous_response_id.is_none() && chained.is_none());
+        assert_eq!(request.input.len(), 2);
+        let (request, _) = client.build_request(&messages, Vec::new(), None, None, false);
+        assert!(request.previous_response_id.is_none());
+    }
+}
@@ -0,0 +1,172 @@
+use super::*;
+use crate::config::{AppConfig, RetryPolicy};
+use std::future::Future;
+use std::time::Duration;
+
+/// Wraps a client with retries for transient failures and an optional fallback profile
+pub struct RetryingClient {
+    primary: Arc<dyn LlmClient>,
+    fallback: Option<(String, Arc<dyn LlmClient>)>,
+    policy: RetryPolicy,
+}
+
+impl RetryingClient {
+    pub fn new(primary: Arc<dyn LlmClient>, fallback: Option<(String, Arc<dyn LlmClient>)>, policy: RetryPolicy) -> Self {
+        Self { primary, fallback, policy }
+    }
+
+    /// Client for `profile` with the configured retry policy and its `fallback` profile
+    pub fn for_profile(profile: LlmProfile, config: &AppConfig) -> Result<Arc<dyn LlmClient>, LlmError> {
+        let fallback = profile.fallback.clone().and_then(|name| {
+            let fallback_profile = config.get_profile(&name)?.clone();
+            match create_client(fallback_profile) {
+                Ok(client) => Some((name, client)),
+                Err(e) => {
+                    log::warn!("⚠️ Fallback profile '{}' is unavailable: {}", name, e);
+                    None
+                }
+            }
+        });
+        let primary = create_client(profile)?;
+        Ok(Arc::new(Self::new(primary, fallback, config.retry.clone())))
+    }
+
+    /// Delay before retry number `attempt` (0-based), or None when waiting would exceed the policy
+    fn backoff(&self, attempt: u32, error: &LlmError) -> Option<Duration> {
+        let max = Duration::from_millis(self.policy.max_backoff_ms);
+        match error {
+            LlmError::RateLimited { retry_after: Some(delay), .. } => (*delay <= max).then_some(*delay),
+            _ => {
+                let delay = self.policy.initial_backoff_ms.saturating_mul(1u64 << attempt.min(20));
+                Some(Duration::from_millis(delay).min(max))
+            }
+        }
+    }
+
+    async fn with_retries<T, F, Fut>(&self, client: &Arc<dyn LlmClient>, call: &F) -> Result<T, LlmError>
+    where
+        F: Fn(Arc<dyn LlmClient>) -> Fut,
+        Fut: Future<Output = Result<T, LlmError>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match call(client.clone()).await {
+                Err(e) if e.is_retryable() && attempt < self.policy.max_retries => {
+                    let Some(delay) = self.backoff(attempt, &e) else {
+                        return Err(e);
+                    };
+                    attempt += 1;
+                    log::warn!("⚠️ LLM request failed ({}), retry {}/{} in {:?}", e, attempt, self.policy.max_retries, delay);
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn run<T, F, Fut>(&self, call: F) -> Result<T, LlmError>
+    where
+        F: Fn(Arc<dyn LlmClient>) -> Fut,
+        Fut: Future<Output = Result<T, LlmError>>,
+    {
+        match self.with_retries(&self.primary, &call).await {
+            Err(e) if e.allows_failover() => match &self.fallback {
+                Some((name, fallback)) => {
+                    log::warn!("⚠️ LLM request failed ({}), failing over to profile '{}'", e, name);
+                    self.with_retries(fallback, &call).await
+                }
+                None => Err(e),
+            },
+            result => result,
+        }
+    }
+}
+
+#[async_trait]
+impl LlmClient for RetryingClient {
+    /// Only opening the stream is retried; errors in the middle of a stream are passed on
+    async fn send_message_stream(
+        &self,
+        messages: Vec<Message>,
+        temperature: Option<f32>,
+        max_tokens: Option<u32>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamChunk, LlmError>> + Send>>, LlmError> {
+        self.run(|client| {
+            let messages = messages.clone();
+            async move { client.send_message_stream(messages, temperature, max_tokens).await }
+        })
+        .await
+    }
+
+    async fn send_message_with_tools(
+        &self,
+        messages: Vec<Message>,
+        available_tools: Vec<ToolDefinition>,
+        temperature: Option<f32>,
+        max_tokens: Option<u32>,
+    ) -> Result<ChatResponse, LlmError> {
+        self.run(|client| {
+            let messages = messages.clone();
+            let available_tools = available_tools.clone();
+            async move { client.send_message_with_tools(messages, available_tools, temperature, max_tokens).await }
+        })
+        .await
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>, LlmError> {
+        self.primary.list_models().await
+    }
+
+    async fn test_connection(&self) -> Result<(), LlmError> {
+        self.primary.test_connection().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(policy: RetryPolicy) -> RetryingClient {
+        RetryingClient::new(Arc::new(UnavailableClient::new("test")), None, policy)
+    }
+
+    #[test]
+    fn test_error_classification() {
+        assert!(matches!(LlmError::from_status("OpenAI", 401, None, "invalid key"), LlmError::Auth(_)));
+        assert!(matches!(
+            LlmError::from_status("OpenAI", 429, Some(Duration::from_secs(2)), "slow down"),
+            LlmError::RateLimited { retry_after: Some(_), .. }
+        ));
+        assert!(matches!(LlmError::from_status("OpenAI", 429, None, r#"{"error":{"code":"insufficient_quota"}}"#), LlmError::Api(_)));
+        assert!(matches!(LlmError::from_status("Anthropic", 529, None, "overloaded"), LlmError::Server { status: 529, .. }));
+        assert!(matches!(
+            LlmError::from_status("OpenAI", 400, None, r#"{"error":{"code":"context_length_exceeded"}}"#),
+            LlmError::ContextLength(_)
+        ));
+        assert!(matches!(LlmError::from_status("Azure OpenAI", 400, None, r#"{"error":{"code":"content_filter"}}"#), LlmError::ContentFilter(_)));
+        assert!(matches!(LlmError::from_status("Gemini", 400, None, "bad request"), LlmError::Api(_)));
+    }
+
+    #[test]
+    fn test_retryable_errors() {
+        assert!(LlmError::from_status("OpenAI", 503, None, "").is_retryable());
+        assert!(LlmError::from_status("OpenAI", 429, None, "").is_retryable());
+        assert!(!LlmError::from_status("OpenAI", 401, None, "").is_retryable());
+        assert!(!LlmError::Cancelled.allows_failover());
+        assert!(LlmError::from_status("OpenAI", 401, None, "").allows_failover());
+    }
+
+    #[test]
+    fn test_backoff() {
+        let client = client(RetryPolicy { max_retries: 5, initial_backoff_ms: 100, max_backoff_ms: 1000 });
+        let server_error = LlmError::Server { status: 500, message: String::new() };
+        assert_eq!(client.backoff(0, &server_error), Some(Duration::from_millis(100)));
+        assert_eq!(client.backoff(2, &server_error), Some(Duration::from_millis(400)));
+        assert_eq!(client.backoff(10, &server_error), Some(Duration::from_millis(1000)));
+
+        let honored = LlmError::RateLimited { message: String::new(), retry_after: Some(Duration::from_millis(700)) };
+        assert_eq!(client.backoff(0, &honored), Some(Duration::from_millis(700)));
+        let too_long = LlmError::RateLimited { message: String::new(), retry_after: Some(Duration::from_secs(60)) };
+        assert_eq!(client.backoff(0, &too_long), None);
+    }
+}
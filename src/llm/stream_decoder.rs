@@ -0,0 +1,364 @@
+//! Incremental decoders for streamed response bodies.
+//!
+//! Network chunks do not line up with events: an SSE event, a JSON line or a
+//! multi-byte UTF-8 character can be split across any number of chunks. The
+//! decoders here buffer raw bytes and only decode complete lines.
+
+use super::LlmError;
+use futures::{Stream, StreamExt};
+use std::collections::VecDeque;
+use std::pin::Pin;
+
+/// One Server-Sent Event
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SseEvent {
+    /// The `event:` field; None for the default "message" type
+    pub event: Option<String>,
+    /// All `data:` lines of the event, joined with newlines
+    pub data: String,
+}
+
+impl SseEvent {
+    /// OpenAI-style end of stream marker
+    pub fn is_done(&self) -> bool {
+        self.data == "[DONE]"
+    }
+
+    /// Error carried by the event: an `event: error` or a JSON payload with an `error` field
+    pub fn error_message(&self) -> Option<String> {
+        let value = serde_json::from_str::<serde_json::Value>(&self.data).ok();
+        let error = value.as_ref().and_then(|value| value.get("error"));
+        match (self.event.as_deref(), error) {
+            (_, Some(error)) => Some(
+                error
+                    .get("message")
+                    .and_then(|message| message.as_str())
+                    .map(str::to_string)
+                    .unwrap_or_else(|| error.to_string()),
+            ),
+            (Some("error"), None) => Some(self.data.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// Turns body chunks into complete items
+pub trait Decoder {
+    type Item;
+
+    fn push(&mut self, chunk: &[u8]) -> Vec<Result<Self::Item, LlmError>>;
+
+    /// Flush whatever is left once the body ends
+    fn finish(&mut self) -> Vec<Result<Self::Item, LlmError>>;
+}
+
+/// Splits buffered bytes into lines ending in `\n`, `\r\n` or `\r`
+#[derive(Debug, Default)]
+struct LineBuffer {
+    buffer: Vec<u8>,
+    /// The last chunk ended in `\r`, so a leading `\n` in the next one belongs to it
+    skip_newline: bool,
+}
+
+impl LineBuffer {
+    fn push(&mut self, chunk: &[u8]) -> Vec<Result<String, LlmError>> {
+        let mut chunk = chunk;
+        if self.skip_newline && chunk.first() == Some(&b'\n') {
+            chunk = &chunk[1..];
+        }
+        self.skip_newline = false;
+        self.buffer.extend_from_slice(chunk);
+
+        let mut lines = Vec::new();
+        let mut start = 0;
+        let mut i = 0;
+        while i < self.buffer.len() {
+            match self.buffer[i] {
+                b'\n' => {
+                    lines.push(decode_line(&self.buffer[start..i]));
+                    start = i + 1;
+                }
+                b'\r' => {
+                    lines.push(decode_line(&self.buffer[start..i]));
+                    if self.buffer.get(i + 1) == Some(&b'\n') {
+                        i += 1;
+                    } else if i + 1 == self.buffer.len() {
+                        self.skip_newline = true;
+                    }
+                    start = i + 1;
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        self.buffer.drain(..start);
+        lines
+    }
+
+    fn finish(&mut self) -> Option<Result<String, LlmError>> {
+        if self.buffer.is_empty() {
+            return None;
+        }
+        let line = decode_line(&self.buffer);
+        self.buffer.clear();
+        Some(line)
+    }
+}
+
+fn decode_line(line: &[u8]) -> Result<String, LlmError> {
+    String::from_utf8(line.to_vec()).map_err(|e| LlmError::Api(format!("Invalid UTF-8 in stream: {}", e)))
+}
+
+/// Server-Sent Events decoder (`text/event-stream`)
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    lines: LineBuffer,
+    event: Option<String>,
+    data: Vec<String>,
+}
+
+impl SseDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn take_event(&mut self) -> Option<SseEvent> {
+        let event = self.event.take();
+        if self.data.is_empty() {
+            return None;
+        }
+        Some(SseEvent { event, data: std::mem::take(&mut self.data).join("\n") })
+    }
+
+    fn process_line(&mut self, line: String) -> Option<SseEvent> {
+        // A blank line dispatches the event
+        if line.is_empty() {
+            return self.take_event();
+        }
+        // Comments, used as keep-alives
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line.as_str(), ""),
+        };
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => self.data.push(value.to_string()),
+            // `id` and `retry` only matter for reconnecting, which we don't do
+            _ => {}
+        }
+        None
+    }
+}
+
+impl Decoder for SseDecoder {
+    type Item = SseEvent;
+
+    fn push(&mut self, chunk: &[u8]) -> Vec<Result<SseEvent, LlmError>> {
+        let mut events = Vec::new();
+        for line in self.lines.push(chunk) {
+            match line {
+                Ok(line) => events.extend(self.process_line(line).map(Ok)),
+                Err(e) => events.push(Err(e)),
+            }
+        }
+        events
+    }
+
+    fn finish(&mut self) -> Vec<Result<SseEvent, LlmError>> {
+        let mut events = Vec::new();
+        match self.lines.finish() {
+            Some(Ok(line)) => events.extend(self.process_line(line).map(Ok)),
+            Some(Err(e)) => events.push(Err(e)),
+            None => {}
+        }
+        // Servers don't always end the last event with a blank line
+        events.extend(self.take_event().map(Ok));
+        events
+    }
+}
+
+/// Newline-delimited JSON decoder; yields each non-blank line
+#[derive(Debug, Default)]
+pub struct NdjsonDecoder {
+    lines: LineBuffer,
+}
+
+impl NdjsonDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Decoder for NdjsonDecoder {
+    type Item = String;
+
+    fn push(&mut self, chunk: &[u8]) -> Vec<Result<String, LlmError>> {
+        self.lines
+            .push(chunk)
+            .into_iter()
+            .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+            .collect()
+    }
+
+    fn finish(&mut self) -> Vec<Result<String, LlmError>> {
+        self.lines
+            .finish()
+            .into_iter()
+            .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+            .collect()
+    }
+}
+
+/// Run a decoder over a byte stream
+pub fn decode<D, S, B>(bytes: S, decoder: D) -> impl Stream<Item = Result<D::Item, LlmError>> + Send
+where
+    D: Decoder + Send + 'static,
+    D::Item: Send + 'static,
+    S: Stream<Item = Result<B, reqwest::Error>> + Send + 'static,
+    B: AsRef<[u8]>,
+{
+    struct State<D: Decoder> {
+        bytes: Pin<Box<dyn Stream<Item = Result<Vec<u8>, reqwest::Error>> + Send>>,
+        decoder: D,
+        pending: VecDeque<Result<D::Item, LlmError>>,
+        done: bool,
+    }
+
+    let state = State {
+        bytes: Box::pin(bytes.map(|chunk| chunk.map(|chunk| chunk.as_ref().to_vec()))),
+        decoder,
+        pending: VecDeque::new(),
+        done: false,
+    };
+
+    futures::stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(item) = state.pending.pop_front() {
+                return Some((item, state));
+            }
+            if state.done {
+                return None;
+            }
+            match state.bytes.next().await {
+                Some(Ok(chunk)) => state.pending.extend(state.decoder.push(&chunk)),
+                Some(Err(e)) => {
+                    state.done = true;
+                    state.pending.push_back(Err(LlmError::Http(e)));
+                }
+                None => {
+                    state.done = true;
+                    state.pending.extend(state.decoder.finish());
+                }
+            }
+        }
+    })
+}
+
+/// SSE events of a response body
+pub fn sse_events(response: reqwest::Response) -> impl Stream<Item = Result<SseEvent, LlmError>> + Send {
+    decode(response.bytes_stream(), SseDecoder::new())
+}
+
+/// JSON lines of a response body
+pub fn ndjson_lines(response: reqwest::Response) -> impl Stream<Item = Result<String, LlmError>> + Send {
+    decode(response.bytes_stream(), NdjsonDecoder::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed<D: Decoder>(decoder: &mut D, chunks: &[&[u8]]) -> Vec<D::Item> {
+        let mut items = Vec::new();
+        for chunk in chunks {
+            items.extend(decoder.push(chunk).into_iter().map(|item| item.ok().unwrap()));
+        }
+        items.extend(decoder.finish().into_iter().map(|item| item.ok().unwrap()));
+        items
+    }
+
+    fn data(events: &[SseEvent]) -> Vec<&str> {
+        events.iter().map(|event| event.data.as_str()).collect()
+    }
+
+    #[test]
+    fn test_sse_event_split_across_chunks() {
+        let mut decoder = SseDecoder::new();
+        let events = feed(&mut decoder, &[b"da", b"ta: {\"a\":", b"1}\n", b"\ndata: two\n\n"]);
+        assert_eq!(data(&events), vec!["{\"a\":1}", "two"]);
+    }
+
+    #[test]
+    fn test_sse_every_byte_boundary() {
+        let body = "event: content_block_delta\ndata: {\"text\":\"héllo 👋\"}\n\ndata: [DONE]\n\n".as_bytes();
+        for split in 0..body.len() {
+            let mut decoder = SseDecoder::new();
+            let events = feed(&mut decoder, &[&body[..split], &body[split..]]);
+            assert_eq!(events.len(), 2, "split at {}", split);
+            assert_eq!(events[0].event.as_deref(), Some("content_block_delta"));
+            assert_eq!(events[0].data, "{\"text\":\"héllo 👋\"}");
+            assert!(events[1].is_done());
+        }
+    }
+
+    #[test]
+    fn test_sse_multiline_data_and_comments() {
+        let mut decoder = SseDecoder::new();
+        let events = feed(&mut decoder, &[b": keep-alive\n\ndata: line one\ndata:line two\nid: 7\n\n"]);
+        assert_eq!(data(&events), vec!["line one\nline two"]);
+        assert_eq!(events[0].event, None);
+    }
+
+    #[test]
+    fn test_sse_crlf_split_between_chunks() {
+        let mut decoder = SseDecoder::new();
+        let events = feed(&mut decoder, &[b"data: a\r", b"\n\r", b"\ndata: b\r\n\r\n"]);
+        assert_eq!(data(&events), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_sse_last_event_without_blank_line() {
+        let mut decoder = SseDecoder::new();
+        let events = feed(&mut decoder, &[b"data: first\n\ndata: last"]);
+        assert_eq!(data(&events), vec!["first", "last"]);
+    }
+
+    #[test]
+    fn test_sse_error_events() {
+        let anthropic = SseEvent {
+            event: Some("error".to_string()),
+            data: r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#.to_string(),
+        };
+        assert_eq!(anthropic.error_message().as_deref(), Some("Overloaded"));
+
+        let openai = SseEvent { event: None, data: r#"{"error":{"message":"Server busy"}}"#.to_string() };
+        assert_eq!(openai.error_message().as_deref(), Some("Server busy"));
+
+        let plain = SseEvent { event: Some("error".to_string()), data: "boom".to_string() };
+        assert_eq!(plain.error_message().as_deref(), Some("boom"));
+
+        let normal = SseEvent { event: None, data: r#"{"choices":[]}"#.to_string() };
+        assert_eq!(normal.error_message(), None);
+    }
+
+    #[test]
+    fn test_ndjson_lines_split_across_chunks() {
+        let mut decoder = NdjsonDecoder::new();
+        let lines = feed(&mut decoder, &[b"{\"a\":", b"1}\n\n{\"b\"", b":\"\xc3", b"\xa9\"}\n{\"c\":3}"]);
+        assert_eq!(lines, vec!["{\"a\":1}", "{\"b\":\"é\"}", "{\"c\":3}"]);
+    }
+
+    #[test]
+    fn test_decode_stream_flushes_at_end() {
+        let chunks: Vec<Result<Vec<u8>, reqwest::Error>> = vec![Ok(b"data: one\n\nda".to_vec()), Ok(b"ta: two".to_vec())];
+        let events: Vec<SseEvent> = tokio_test::block_on(
+            decode(futures::stream::iter(chunks), SseDecoder::new())
+                .map(|event| event.ok().unwrap())
+                .collect(),
+        );
+        assert_eq!(data(&events), vec!["one", "two"]);
+    }
+}
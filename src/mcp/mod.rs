@@ -4,4 +4,4 @@ pub mod transport;
 pub mod stdio_client;
 
 pub use registry::MCPServerRegistry;
-pub use transport::MCPTransport;
+pub use transport::{MCPTransport, TransportError};
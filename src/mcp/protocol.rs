@@ -66,3 +66,28 @@ impl MCPRequest {
     }
 }
 
+
+/// A JSON-RPC notification (no `id`) sent by the server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MCPNotification {
+    pub jsonrpc: String,
+    pub method: String,
+    pub params: Option<serde_json::Value>,
+}
+
+impl MCPNotification {
+    pub const TOOLS_LIST_CHANGED: &'static str = "notifications/tools/list_changed";
+    pub const CANCELLED: &'static str = "notifications/cancelled";
+    
+    /// Tell the server we no longer want the result of `request_id`
+    pub fn cancelled(request_id: u64, reason: &str) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            method: Self::CANCELLED.to_string(),
+            params: Some(serde_json::json!({
+                "requestId": request_id,
+                "reason": reason,
+            })),
+        }
+    }
+}
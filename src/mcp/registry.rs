@@ -1,11 +1,24 @@
 use crate::llm::{ToolDefinition, ToolCall, ToolResult};
-use crate::config::MCPConfig;
+use crate::config::{MCPConfig, MCPServerConfig};
+use crate::config::watcher::{self, FileWatcher};
+use crate::mcp::protocol::MCPNotification;
 use crate::mcp::transport::MCPTransport;
 use anyhow::Result;
-use log::{error, info};
-use std::collections::HashMap;
+use log::{debug, error, info, warn};
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, mpsc, RwLock};
+use tokio_util::sync::CancellationToken;
+
+/// Longest tool name accepted by every backend we talk to.
+const MAX_TOOL_NAME_LEN: usize = 64;
+
+/// Separator between the server and tool parts of a qualified tool name.
+const TOOL_NAME_SEPARATOR: &str = "__";
+
+/// How long a server under test gets to start and list its tools.
+const SERVER_TEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(15);
+
 
 pub enum MCPTransportEnum {
     Stdio(super::stdio_client::StdioMCPClient),
@@ -31,27 +44,157 @@ impl super::transport::MCPTransport for MCPTransportEnum {
         }
     }
     
-    async fn call_tool(&mut self, tool_call: ToolCall) -> Result<ToolResult> {
+    async fn call_tool(&mut self, tool_call: ToolCall, cancel: &CancellationToken) -> Result<ToolResult> {
         match self {
-            MCPTransportEnum::Stdio(client) => client.call_tool(tool_call).await,
+            MCPTransportEnum::Stdio(client) => client.call_tool(tool_call, cancel).await,
         }
     }
 }
 
+/// Where a model-facing (qualified) tool name is routed to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolRoute {
+    pub server_name: String,
+    pub tool_name: String,
+}
+
+/// A bare tool name exposed by more than one server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolConflict {
+    pub tool_name: String,
+    pub servers: Vec<String>,
+}
+
+/// Changes to the set of servers or tools, broadcast to subscribers (the UI)
+#[derive(Debug, Clone)]
+pub enum MCPRegistryEvent {
+    ServerAdded { server_name: String },
+    ServerRemoved { server_name: String },
+    ServerFailed { server_name: String, error: String },
+    ToolsChanged { server_name: String },
+    /// mcp_config.json was re-read; `error` is set when it could not be parsed
+    ConfigReloaded { error: Option<String> },
+}
+
+/// What `apply_config` has to do to bring the running servers in line with a new config
+#[derive(Debug, Default, PartialEq)]
+struct ConfigChanges {
+    /// Only `disabled` changed: no restart needed
+    toggled: Vec<String>,
+    /// Removed or changed servers to stop
+    stopped: Vec<String>,
+    /// New, changed or not yet running servers to start
+    started: Vec<String>,
+}
+
+impl ConfigChanges {
+    /// `running` holds the servers that started successfully, so ones that failed are started again
+    fn between(running: &HashMap<String, MCPServerConfig>, wanted: &HashMap<String, MCPServerConfig>) -> Self {
+        let mut changes = Self::default();
+        for (name, config) in running {
+            match wanted.get(name) {
+                Some(new) if new == config => {}
+                Some(new) if new.same_process(config) => changes.toggled.push(name.clone()),
+                Some(_) => {
+                    changes.stopped.push(name.clone());
+                    changes.started.push(name.clone());
+                }
+                None => changes.stopped.push(name.clone()),
+            }
+        }
+        changes.started.extend(wanted.keys().filter(|name| !running.contains_key(*name)).cloned());
+        changes.toggled.sort();
+        changes.stopped.sort();
+        changes.started.sort();
+        changes
+    }
+}
+
+/// Everything the UI needs to render the current tool state
+#[derive(Debug, Clone, Default)]
+pub struct MCPRegistrySnapshot {
+    pub tools: Vec<ToolDefinition>,
+    pub tool_states: HashMap<String, bool>,
+    pub server_states: HashMap<String, bool>,
+    pub tool_servers: HashMap<String, String>,
+    pub conflicts: Vec<ToolConflict>,
+}
+
+/// Build the model-facing name for `tool_name` on `server_name`.
+///
+/// The result satisfies the strictest provider rules: OpenAI and Anthropic
+/// accept `^[a-zA-Z0-9_-]{1,64}$`, Gemini additionally requires a leading
+/// letter or underscore. Names that would be too long are truncated and
+/// suffixed with a short hash of the original so they stay unique.
+pub fn qualified_tool_name(server_name: &str, tool_name: &str) -> String {
+    let raw = format!("{}{}{}", server_name, TOOL_NAME_SEPARATOR, tool_name);
+    let mut name: String = raw
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+        .collect();
+
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        name.insert(0, '_');
+    }
+
+    if name.len() > MAX_TOOL_NAME_LEN {
+        use sha2::{Digest, Sha256};
+        let digest = Sha256::digest(raw.as_bytes());
+        let suffix: String = digest.iter().take(4).map(|b| format!("{:02x}", b)).collect();
+        name.truncate(MAX_TOOL_NAME_LEN - suffix.len() - 1);
+        name.push('_');
+        name.push_str(&suffix);
+    }
+
+    name
+}
+
 pub struct MCPServerRegistry {
     pub servers: HashMap<String, Arc<RwLock<MCPTransportEnum>>>,
-    pub tool_index: HashMap<String, String>, // tool_name -> server_name
+    pub tool_index: HashMap<String, ToolRoute>, // qualified tool name -> server/tool
     pub all_tools: Vec<ToolDefinition>,
-    pub enabled_tools: HashMap<String, bool>, // tool_name -> enabled
+    pub enabled_tools: HashMap<String, bool>, // qualified tool name -> enabled
+    pub enabled_servers: HashMap<String, bool>, // server_name -> enabled
+    pub server_configs: HashMap<String, MCPServerConfig>, // config each server was started with
+    events: broadcast::Sender<MCPRegistryEvent>,
+    notification_tx: mpsc::UnboundedSender<(String, MCPNotification)>,
+    notification_rx: Option<mpsc::UnboundedReceiver<(String, MCPNotification)>>,
 }
 
 impl MCPServerRegistry {
     pub fn new() -> Self {
+        let (events, _) = broadcast::channel(64);
+        let (notification_tx, notification_rx) = mpsc::unbounded_channel();
         Self {
             servers: HashMap::new(),
             tool_index: HashMap::new(),
             all_tools: Vec::new(),
             enabled_tools: HashMap::new(),
+            enabled_servers: HashMap::new(),
+            server_configs: HashMap::new(),
+            events,
+            notification_tx,
+            notification_rx: Some(notification_rx),
+        }
+    }
+    
+    /// Receive server/tool change events
+    pub fn subscribe(&self) -> broadcast::Receiver<MCPRegistryEvent> {
+        self.events.subscribe()
+    }
+    
+    fn emit(&self, event: MCPRegistryEvent) {
+        // No receivers is fine: nobody is interested yet
+        let _ = self.events.send(event);
+    }
+    
+    pub fn snapshot(&self) -> MCPRegistrySnapshot {
+        MCPRegistrySnapshot {
+            tools: self.get_available_tools(),
+            tool_states: self.get_tool_states(),
+            server_states: self.get_server_states(),
+            tool_servers: self.get_tool_servers(),
+            conflicts: self.get_conflicts(),
         }
     }
     
@@ -66,8 +209,12 @@ impl MCPServerRegistry {
             .collect()
     }
     
+    /// A tool is offered to the model only if both it and its server are enabled
     pub fn is_tool_enabled(&self, tool_name: &str) -> bool {
-        self.enabled_tools.get(tool_name).copied().unwrap_or(true)
+        let server_enabled = self.tool_index.get(tool_name)
+            .map(|route| self.is_server_enabled(&route.server_name))
+            .unwrap_or(true);
+        server_enabled && self.enabled_tools.get(tool_name).copied().unwrap_or(true)
     }
     
     pub fn set_tool_enabled(&mut self, tool_name: &str, enabled: bool) {
@@ -90,42 +237,224 @@ impl MCPServerRegistry {
         self.enabled_tools.clone()
     }
     
-    pub fn get_server_for_tool(&self, tool_name: &str) -> Result<&String> {
+    pub fn is_server_enabled(&self, server_name: &str) -> bool {
+        self.enabled_servers.get(server_name).copied().unwrap_or(true)
+    }
+    
+    pub fn set_server_enabled(&mut self, server_name: &str, enabled: bool) {
+        self.enabled_servers.insert(server_name.to_string(), enabled);
+    }
+    
+    pub fn get_server_states(&self) -> HashMap<String, bool> {
+        self.servers.keys()
+            .map(|name| (name.clone(), self.is_server_enabled(name)))
+            .collect()
+    }
+    
+    /// Qualified tool name -> owning server name
+    pub fn get_tool_servers(&self) -> HashMap<String, String> {
+        self.tool_index.iter()
+            .map(|(qualified, route)| (qualified.clone(), route.server_name.clone()))
+            .collect()
+    }
+    
+    pub fn get_route_for_tool(&self, tool_name: &str) -> Result<&ToolRoute> {
         self.tool_index.get(tool_name)
             .ok_or_else(|| anyhow::anyhow!("Tool {} not found", tool_name))
     }
     
-    pub async fn call_tool(&mut self, tool_call: ToolCall) -> Result<ToolResult> {
-        let server_name = self.get_server_for_tool(&tool_call.name)?;
-        let server = self.servers.get(server_name)
-            .ok_or_else(|| anyhow::anyhow!("Server {} not found", server_name))?;
+    /// Bare tool names that more than one server exposes
+    pub fn get_conflicts(&self) -> Vec<ToolConflict> {
+        let mut by_name: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        for route in self.tool_index.values() {
+            by_name.entry(route.tool_name.as_str()).or_default().push(route.server_name.clone());
+        }
+        by_name.into_iter()
+            .filter(|(_, servers)| servers.len() > 1)
+            .map(|(tool_name, mut servers)| {
+                servers.sort();
+                ToolConflict { tool_name: tool_name.to_string(), servers }
+            })
+            .collect()
+    }
+    
+    pub async fn call_tool(&mut self, tool_call: ToolCall, cancel: &CancellationToken) -> Result<ToolResult> {
+        // We may have waited for the registry lock; don't start work nobody wants anymore
+        if cancel.is_cancelled() {
+            return Err(anyhow::anyhow!("Tool call {} was cancelled", tool_call.name));
+        }
+        let route = self.get_route_for_tool(&tool_call.name)?.clone();
+        if !self.is_server_enabled(&route.server_name) {
+            return Err(anyhow::anyhow!("Server {} is disabled", route.server_name));
+        }
+        let server = self.servers.get(&route.server_name)
+            .ok_or_else(|| anyhow::anyhow!("Server {} not found", route.server_name))?;
         
+        // The server only knows its own, unqualified tool name
+        let server_call = ToolCall {
+            name: route.tool_name,
+            ..tool_call
+        };
         let mut server_guard = server.write().await;
-        server_guard.call_tool(tool_call).await
+        server_guard.call_tool(server_call, cancel).await
     }
     
     pub async fn initialize_from_config(&mut self, mcp_config: &MCPConfig) -> Result<()> {
         // Load MCP servers from configuration (Claude Desktop format)
         for (server_name, server_config) in &mcp_config.servers {
-            match self.add_stdio_server(
-                server_name.clone(),
-                server_config.command.clone(),
-                server_config.args.clone(),
-                server_config.env.clone(),
-            ).await {
-                Ok(_) => {
-                    info!("Successfully connected to MCP server {}", server_name);
-                },
-                Err(e) => {
-                    error!("Failed to connect to MCP server {}: {}", server_name, e);
-                }
+            self.start_server(server_name, server_config).await;
+        }
+        
+        self.log_conflicts();
+        Ok(())
+    }
+    
+    /// Bring the running servers in line with `mcp_config`: stop removed
+    /// servers, restart changed ones and start new ones
+    pub async fn apply_config(&mut self, mcp_config: &MCPConfig) {
+        let changes = ConfigChanges::between(&self.server_configs, &mcp_config.servers);
+        
+        // Enabling or disabling a server does not need a restart
+        for server_name in changes.toggled {
+            let server_config = &mcp_config.servers[&server_name];
+            self.server_configs.insert(server_name.clone(), server_config.clone());
+            self.set_server_enabled(&server_name, !server_config.disabled);
+            self.emit(MCPRegistryEvent::ToolsChanged { server_name });
+        }
+        
+        for server_name in changes.stopped {
+            info!("MCP server {} removed or changed in config, stopping", server_name);
+            self.remove_server(&server_name).await;
+        }
+        
+        for server_name in changes.started {
+            self.start_server(&server_name, &mcp_config.servers[&server_name]).await;
+        }
+        
+        self.log_conflicts();
+    }
+    
+    /// Start a server; its config is only recorded once it runs, so a failed
+    /// server is tried again on the next `apply_config`
+    async fn start_server(&mut self, server_name: &str, server_config: &MCPServerConfig) {
+        self.set_server_enabled(server_name, !server_config.disabled);
+        match self.add_stdio_server(
+            server_name.to_string(),
+            server_config.command.clone(),
+            server_config.args.clone(),
+            server_config.env.clone(),
+        ).await {
+            Ok(_) => {
+                info!("Successfully connected to MCP server {}", server_name);
+                self.server_configs.insert(server_name.to_string(), server_config.clone());
+            },
+            Err(e) => {
+                error!("Failed to connect to MCP server {}: {}", server_name, e);
+                self.emit(MCPRegistryEvent::ServerFailed { server_name: server_name.to_string(), error: e.to_string() });
+            }
+        }
+    }
+    
+    /// Disconnect a server and forget its tools
+    pub async fn remove_server(&mut self, server_name: &str) {
+        if let Some(server) = self.servers.remove(server_name) {
+            let mut server_guard = server.write().await;
+            if let Err(e) = server_guard.disconnect().await {
+                warn!("Failed to disconnect MCP server {}: {}", server_name, e);
             }
         }
+        self.remove_server_tools(server_name);
+        self.server_configs.remove(server_name);
+        self.enabled_servers.remove(server_name);
+        self.emit(MCPRegistryEvent::ServerRemoved { server_name: server_name.to_string() });
+    }
+    
+    /// Re-run tool discovery for one server, e.g. after `tools/list_changed`
+    pub async fn refresh_server_tools(&mut self, server_name: &str) -> Result<()> {
+        let server = self.servers.get(server_name)
+            .ok_or_else(|| anyhow::anyhow!("Server {} not found", server_name))?
+            .clone();
+        let tools = server.write().await.discover_tools().await?;
+        info!("MCP server {} now has {} tools", server_name, tools.len());
+        
+        self.remove_server_tools(server_name);
+        self.index_server_tools(server_name, tools);
+        self.emit(MCPRegistryEvent::ToolsChanged { server_name: server_name.to_string() });
         Ok(())
     }
     
+    /// React to server notifications and to edits of `mcp_config.json` until
+    /// the app exits. Only the first caller gets to run the loop.
+    pub async fn watch(registry: Arc<RwLock<Self>>) {
+        let Some(mut notifications) = registry.write().await.notification_rx.take() else {
+            return;
+        };
+        let mut config_watcher = FileWatcher::new(vec![MCPConfig::mcp_config_path()]);
+        let mut poll = tokio::time::interval(watcher::POLL_INTERVAL);
+        
+        loop {
+            tokio::select! {
+                Some((server_name, notification)) = notifications.recv() => {
+                    if notification.method == MCPNotification::TOOLS_LIST_CHANGED {
+                        info!("MCP server {} reported a changed tool list", server_name);
+                        if let Err(e) = registry.write().await.refresh_server_tools(&server_name).await {
+                            warn!("Failed to refresh tools of MCP server {}: {}", server_name, e);
+                        }
+                    } else {
+                        debug!("Unhandled notification {} from MCP server {}", notification.method, server_name);
+                    }
+                }
+                _ = poll.tick() => {
+                    if config_watcher.poll_changed().is_empty() {
+                        continue;
+                    }
+                    // Stringify the error right away: the boxed error is not Send
+                    match MCPConfig::load_from_json().map_err(|e| e.to_string()) {
+                        Ok(mcp_config) => {
+                            info!("mcp_config.json changed, applying {} servers", mcp_config.servers.len());
+                            let mut registry = registry.write().await;
+                            registry.apply_config(&mcp_config).await;
+                            registry.emit(MCPRegistryEvent::ConfigReloaded { error: None });
+                        }
+                        Err(e) => {
+                            warn!("Ignoring invalid mcp_config.json: {}", e);
+                            registry.read().await.emit(MCPRegistryEvent::ConfigReloaded { error: Some(e) });
+                        }
+                    }
+                }
+            }
+        }
+    }
+    
+    fn log_conflicts(&self) {
+        for conflict in self.get_conflicts() {
+            warn!("MCP tool '{}' is exposed by several servers: {}", conflict.tool_name, conflict.servers.join(", "));
+        }
+    }
+    
+    /// Start a server just long enough to list its tools, without registering it
+    pub async fn test_server(server_config: &MCPServerConfig) -> Result<Vec<ToolDefinition>> {
+        let mut client = super::stdio_client::StdioMCPClient::new(
+            server_config.command.clone(),
+            server_config.args.clone(),
+            server_config.env.clone(),
+        );
+        let tools = tokio::time::timeout(SERVER_TEST_TIMEOUT, async {
+            client.connect().await?;
+            client.discover_tools().await
+        })
+        .await
+        .unwrap_or_else(|_| Err(anyhow::anyhow!("No answer within {} seconds", SERVER_TEST_TIMEOUT.as_secs())));
+        // A failed or timed out handshake can leave the process running as well
+        if let Err(e) = client.disconnect().await {
+            warn!("Failed to disconnect test connection to {}: {}", server_config.command, e);
+        }
+        tools
+    }
+    
     pub async fn add_stdio_server(&mut self, name: String, command: String, args: Vec<String>, env: HashMap<String, String>) -> Result<()> {
         let mut client = super::stdio_client::StdioMCPClient::new(command, args, env);
+        client.set_notification_sender(name.clone(), self.notification_tx.clone());
         
         // Try to connect
         match client.connect().await {
@@ -135,17 +464,11 @@ impl MCPServerRegistry {
                 let tools = client.discover_tools().await?;
                 info!("MCP server {} discovered {} tools", name, tools.len());
                 
-                // Index tools
-                for tool in &tools {
-                    info!("MCP server {} tool: {}", name, tool.name);
-                    self.tool_index.insert(tool.name.clone(), name.clone());
-                    // Enable new tools by default
-                    self.enabled_tools.insert(tool.name.clone(), true);
-                }
-                self.all_tools.extend(tools);
+                self.index_server_tools(&name, tools);
                 
                 // Store client
                 self.servers.insert(name.clone(), Arc::new(RwLock::new(MCPTransportEnum::Stdio(client))));
+                self.emit(MCPRegistryEvent::ServerAdded { server_name: name.clone() });
             },
             Err(e) => {
                 return Err(anyhow::anyhow!("Failed to connect to MCP server {}: {}", name, e));
@@ -153,4 +476,144 @@ impl MCPServerRegistry {
         }
         Ok(())
     }
+    
+    /// Drop every tool route and definition that belongs to `server_name`
+    fn remove_server_tools(&mut self, server_name: &str) {
+        let removed: Vec<String> = self.tool_index.iter()
+            .filter(|(_, route)| route.server_name == server_name)
+            .map(|(qualified, _)| qualified.clone())
+            .collect();
+        for qualified in &removed {
+            self.tool_index.remove(qualified);
+        }
+        self.all_tools.retain(|tool| !removed.contains(&tool.name));
+    }
+    
+    /// Expose a server's tools under their qualified names
+    fn index_server_tools(&mut self, server_name: &str, tools: Vec<ToolDefinition>) {
+        for mut tool in tools {
+            let qualified = qualified_tool_name(server_name, &tool.name);
+            let route = ToolRoute {
+                server_name: server_name.to_string(),
+                tool_name: tool.name.clone(),
+            };
+            
+            if let Some(existing) = self.tool_index.get(&qualified) {
+                if existing != &route {
+                    warn!("MCP server {} tool {} maps to '{}' which is already used by {}/{}; skipping",
+                        server_name, tool.name, qualified, existing.server_name, existing.tool_name);
+                    continue;
+                }
+                // Re-registration of the same tool: drop the stale definition
+                self.all_tools.retain(|t| t.name != qualified);
+            }
+            
+            info!("MCP server {} tool: {} (exposed as {})", server_name, tool.name, qualified);
+            self.tool_index.insert(qualified.clone(), route);
+            // Enable new tools by default, keep the user's choice for known ones
+            self.enabled_tools.entry(qualified.clone()).or_insert(true);
+            tool.name = qualified;
+            self.all_tools.push(tool);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool(name: &str) -> ToolDefinition {
+        ToolDefinition {
+            name: name.to_string(),
+            description: String::new(),
+            parameters: serde_json::json!({ "type": "object" }),
+        }
+    }
+
+    #[test]
+    fn test_qualified_tool_name() {
+        assert_eq!(qualified_tool_name("files", "read_file"), "files__read_file");
+        // Characters providers reject are replaced, a leading digit gets an underscore
+        assert_eq!(qualified_tool_name("my.server", "get data"), "my_server__get_data");
+        assert_eq!(qualified_tool_name("2fa", "check"), "_2fa__check");
+
+        let long_tool = "x".repeat(80);
+        let long = qualified_tool_name("server", &long_tool);
+        assert_eq!(long.len(), MAX_TOOL_NAME_LEN);
+        assert_ne!(long, qualified_tool_name("server", &"x".repeat(81)));
+    }
+
+    #[test]
+    fn test_qualified_name_routes_back_to_server() {
+        let mut registry = MCPServerRegistry::new();
+        registry.index_server_tools("my.server", vec![tool("get data")]);
+
+        let qualified = qualified_tool_name("my.server", "get data");
+        assert_eq!(registry.get_available_tools()[0].name, qualified);
+        let route = registry.get_route_for_tool(&qualified).unwrap();
+        assert_eq!(route, &ToolRoute { server_name: "my.server".to_string(), tool_name: "get data".to_string() });
+        assert!(registry.get_route_for_tool("get data").is_err());
+    }
+
+    #[test]
+    fn test_same_tool_on_two_servers() {
+        let mut registry = MCPServerRegistry::new();
+        registry.index_server_tools("work", vec![tool("search"), tool("open")]);
+        registry.index_server_tools("home", vec![tool("search")]);
+
+        // Both stay callable under their own names
+        assert_eq!(registry.get_route_for_tool("work__search").unwrap().server_name, "work");
+        assert_eq!(registry.get_route_for_tool("home__search").unwrap().server_name, "home");
+        assert_eq!(registry.get_conflicts(), vec![ToolConflict {
+            tool_name: "search".to_string(),
+            servers: vec!["home".to_string(), "work".to_string()],
+        }]);
+
+        registry.remove_server_tools("home");
+        assert!(registry.get_conflicts().is_empty());
+        assert_eq!(registry.get_available_tools().len(), 2);
+    }
+
+    fn configs(servers: &[(&str, MCPServerConfig)]) -> HashMap<String, MCPServerConfig> {
+        servers.iter().map(|(name, config)| (name.to_string(), config.clone())).collect()
+    }
+
+    #[test]
+    fn test_config_changes() {
+        let files = MCPServerConfig::new("mcp-files".to_string());
+        let web = MCPServerConfig::new("mcp-web".to_string());
+        let running = configs(&[("files", files.clone()), ("web", web.clone())]);
+
+        assert_eq!(ConfigChanges::between(&running, &running), ConfigChanges::default());
+
+        let disabled_files = MCPServerConfig { disabled: true, ..files.clone() };
+        let moved_web = MCPServerConfig { args: vec!["--port=1".to_string()], ..web.clone() };
+        let git = MCPServerConfig::new("mcp-git".to_string());
+        let wanted = configs(&[("files", disabled_files), ("web", moved_web), ("git", git.clone())]);
+        assert_eq!(ConfigChanges::between(&running, &wanted), ConfigChanges {
+            toggled: vec!["files".to_string()],
+            stopped: vec!["web".to_string()],
+            started: vec!["git".to_string(), "web".to_string()],
+        });
+
+        let wanted = configs(&[("files", files)]);
+        assert_eq!(ConfigChanges::between(&running, &wanted), ConfigChanges {
+            stopped: vec!["web".to_string()],
+            ..ConfigChanges::default()
+        });
+    }
+
+    #[tokio::test]
+    async fn test_failed_server_is_retried() {
+        let mut registry = MCPServerRegistry::new();
+        let mcp_config = MCPConfig {
+            servers: configs(&[("broken", MCPServerConfig::new("/nonexistent/mcp-server".to_string()))]),
+        };
+        registry.apply_config(&mcp_config).await;
+        assert!(registry.server_configs.is_empty());
+
+        // Saving the same config again starts it again
+        let changes = ConfigChanges::between(&registry.server_configs, &mcp_config.servers);
+        assert_eq!(changes.started, vec!["broken".to_string()]);
+    }
 }
@@ -1,14 +1,25 @@
-use super::MCPTransport;
+use super::{MCPTransport, TransportError};
+use super::protocol::{MCPNotification, MCPResponse};
 use crate::llm::ToolResult;
 use crate::llm::{ToolDefinition, ToolCall};
 use anyhow::Result;
 use async_trait::async_trait;
-use log::debug;
+use log::{debug, warn};
 use serde_json;
 use std::collections::HashMap;
 use std::process::Stdio;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+/// Requests waiting for their response, keyed by JSON-RPC id
+type PendingRequests = Arc<Mutex<HashMap<u64, oneshot::Sender<MCPResponse>>>>;
+
+/// Where server notifications are forwarded, tagged with the server name
+pub type NotificationSender = mpsc::UnboundedSender<(String, MCPNotification)>;
 
 pub struct StdioMCPClient {
     pub command: String,
@@ -16,9 +27,11 @@ pub struct StdioMCPClient {
     pub env: HashMap<String, String>,
     pub process: Option<Child>,
     pub stdin: Option<ChildStdin>,
-    pub stdout: Option<BufReader<ChildStdout>>,
     pub tools: Vec<ToolDefinition>,
     pub request_id: u64,
+    pending: PendingRequests,
+    reader_task: Option<JoinHandle<()>>,
+    notification_sender: Option<(String, NotificationSender)>,
 }
 
 impl StdioMCPClient {
@@ -29,39 +42,64 @@ impl StdioMCPClient {
             env,
             process: None,
             stdin: None,
-            stdout: None,
             tools: Vec::new(),
             request_id: 1,
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            reader_task: None,
+            notification_sender: None,
+        }
+    }
+    
+    /// Forward notifications from this server (e.g. `tools/list_changed`) to `sender`
+    pub fn set_notification_sender(&mut self, server_name: String, sender: NotificationSender) {
+        self.notification_sender = Some((server_name, sender));
+    }
+    
+    async fn send_request(&mut self, request: super::protocol::MCPRequest) -> Result<MCPResponse> {
+        // The reader task resolves the request once the matching response arrives
+        self.start_request(request)
+            .await?
+            .await
+            .map_err(|_| anyhow::Error::from(TransportError("MCP server closed the connection before responding".to_string())))
+    }
+    
+    /// Like `send_request`, but gives up when `cancel` fires and tells the
+    /// server with `notifications/cancelled`
+    async fn send_request_cancellable(&mut self, request: super::protocol::MCPRequest, cancel: &CancellationToken) -> Result<MCPResponse> {
+        let request_id = request.id;
+        let response_rx = self.start_request(request).await?;
+        
+        tokio::select! {
+            response = response_rx => {
+                response.map_err(|_| anyhow::Error::from(TransportError("MCP server closed the connection before responding".to_string())))
+            }
+            _ = cancel.cancelled() => {
+                self.pending.lock().unwrap().remove(&request_id);
+                let notification_json = serde_json::to_string(&MCPNotification::cancelled(request_id, "Cancelled by user"))?;
+                if let Err(e) = self.write_line(&notification_json).await {
+                    debug!("Failed to send cancellation for request {}: {}", request_id, e);
+                }
+                Err(anyhow::anyhow!("Request {} was cancelled", request_id))
+            }
         }
     }
     
-    async fn send_request(&mut self, request: super::protocol::MCPRequest) -> Result<super::protocol::MCPResponse> {
+    /// Write a request and register it as pending, returning where its response will arrive
+    async fn start_request(&mut self, request: super::protocol::MCPRequest) -> Result<oneshot::Receiver<MCPResponse>> {
         if self.process.is_none() {
             self.connect().await?;
         }
         
-        let request_json = serde_json::to_string(&request)?;
-        
-        if let Some(ref mut stdin) = self.stdin {
-            stdin.write_all(request_json.as_bytes()).await?;
-            stdin.write_all(b"\n").await?;
-            stdin.flush().await?;
-        }
+        let request_id = request.id;
+        let (response_tx, response_rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(request_id, response_tx);
         
-        // Read response
-        if let Some(ref mut stdout) = self.stdout {
-            let mut line = String::new();
-            match stdout.read_line(&mut line).await {
-                Ok(_) => {
-                    debug!("MCP Response: {}", line);
-                    let response: super::protocol::MCPResponse = serde_json::from_str(&line)?;
-                    Ok(response)
-                }
-                Err(e) => Err(anyhow::anyhow!("Failed to read response: {}", e))
-            }
-        } else {
-            Err(anyhow::anyhow!("No stdout available"))
+        let request_json = serde_json::to_string(&request)?;
+        if let Err(e) = self.write_line(&request_json).await {
+            self.pending.lock().unwrap().remove(&request_id);
+            return Err(e);
         }
+        Ok(response_rx)
     }
     
     async fn send_initialized_notification(&mut self) -> Result<()> {
@@ -71,14 +109,85 @@ impl StdioMCPClient {
         });
         
         let notification_json = serde_json::to_string(&notification)?;
-        
-        if let Some(ref mut stdin) = self.stdin {
-            stdin.write_all(notification_json.as_bytes()).await?;
+        self.write_line(&notification_json).await
+    }
+    
+    async fn write_line(&mut self, line: &str) -> Result<()> {
+        let stdin = self.stdin.as_mut().ok_or_else(|| TransportError("No stdin available".to_string()))?;
+        let write = async {
+            stdin.write_all(line.as_bytes()).await?;
             stdin.write_all(b"\n").await?;
-            stdin.flush().await?;
+            stdin.flush().await
+        };
+        write.await.map_err(|e| anyhow::Error::from(TransportError(format!("Failed to write to MCP server: {}", e))))
+    }
+    
+    /// Read server output line by line, routing responses to their waiting
+    /// requests and notifications to the registry
+    async fn read_loop<R: AsyncBufRead + Unpin>(
+        mut stdout: R,
+        pending: PendingRequests,
+        notification_sender: Option<(String, NotificationSender)>,
+    ) {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            match stdout.read_line(&mut line).await {
+                Ok(0) => {
+                    debug!("MCP server closed stdout");
+                    break;
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    warn!("Failed to read from MCP server: {}", e);
+                    break;
+                }
+            }
+            
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            debug!("MCP Message: {}", trimmed);
+            
+            let value: serde_json::Value = match serde_json::from_str(trimmed) {
+                Ok(value) => value,
+                Err(e) => {
+                    debug!("Ignoring non JSON-RPC output from MCP server: {}", e);
+                    continue;
+                }
+            };
+            
+            if value.get("method").is_some() {
+                if value.get("id").is_some() {
+                    debug!("Ignoring request from MCP server: {}", trimmed);
+                    continue;
+                }
+                match serde_json::from_value::<MCPNotification>(value) {
+                    Ok(notification) => {
+                        if let Some((server_name, sender)) = &notification_sender {
+                            let _ = sender.send((server_name.clone(), notification));
+                        }
+                    }
+                    Err(e) => debug!("Malformed MCP notification: {}", e),
+                }
+                continue;
+            }
+            
+            match serde_json::from_value::<MCPResponse>(value) {
+                Ok(response) => {
+                    if let Some(response_tx) = pending.lock().unwrap().remove(&response.id) {
+                        let _ = response_tx.send(response);
+                    } else {
+                        debug!("Response for unknown request id {}", response.id);
+                    }
+                }
+                Err(e) => warn!("Malformed MCP response: {}", e),
+            }
         }
         
-        Ok(())
+        // Dropping the senders fails every request still waiting for an answer
+        pending.lock().unwrap().clear();
     }
 }
 
@@ -98,14 +207,19 @@ impl MCPTransport for StdioMCPClient {
             cmd.env(key, value);
         }
         
-        let mut child = cmd.spawn()?;
+        let mut child = cmd.spawn()
+            .map_err(|e| TransportError(format!("Failed to start {}: {}", self.command, e)))?;
         
         let stdin = child.stdin.take().ok_or_else(|| anyhow::anyhow!("Failed to get stdin"))?;
         let stdout = child.stdout.take().ok_or_else(|| anyhow::anyhow!("Failed to get stdout"))?;
         
         self.stdin = Some(stdin);
-        self.stdout = Some(BufReader::new(stdout));
         self.process = Some(child);
+        self.reader_task = Some(tokio::spawn(Self::read_loop(
+            BufReader::new(stdout),
+            self.pending.clone(),
+            self.notification_sender.clone(),
+        )));
         
         // Send initialize request
         let init_request = super::protocol::MCPRequest::initialize(self.request_id);
@@ -124,8 +238,11 @@ impl MCPTransport for StdioMCPClient {
         if let Some(mut process) = self.process.take() {
             let _ = process.kill().await;
         }
+        if let Some(reader_task) = self.reader_task.take() {
+            reader_task.abort();
+        }
+        self.pending.lock().unwrap().clear();
         self.stdin = None;
-        self.stdout = None;
         Ok(())
     }
     
@@ -153,12 +270,12 @@ impl MCPTransport for StdioMCPClient {
         Ok(Vec::new())
     }
     
-    async fn call_tool(&mut self, tool_call: ToolCall) -> Result<ToolResult> {
+    async fn call_tool(&mut self, tool_call: ToolCall, cancel: &CancellationToken) -> Result<ToolResult> {
         let arguments = tool_call.parameters.clone();
         let request = super::protocol::MCPRequest::tools_call(self.request_id, tool_call.name, arguments);
         self.request_id += 1;
         
-        let response = self.send_request(request).await?;
+        let response = self.send_request_cancellable(request, cancel).await?;
         
         if let Some(error) = response.error {
             return Ok(ToolResult {
@@ -201,3 +318,33 @@ impl MCPTransport for StdioMCPClient {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_loop_routes_notifications_and_responses() {
+        let output = concat!(
+            "starting server...\n",
+            "{\"jsonrpc\":\"2.0\",\"method\":\"notifications/tools/list_changed\"}\n",
+            "{\"jsonrpc\":\"2.0\",\"id\":9,\"method\":\"roots/list\"}\n",
+            "{\"jsonrpc\":\"2.0\",\"id\":7,\"result\":{\"tools\":[]}}\n",
+        );
+        let pending: PendingRequests = Arc::new(Mutex::new(HashMap::new()));
+        let (response_tx, response_rx) = oneshot::channel();
+        pending.lock().unwrap().insert(7, response_tx);
+        let (notification_tx, mut notification_rx) = mpsc::unbounded_channel();
+
+        StdioMCPClient::read_loop(output.as_bytes(), pending.clone(), Some(("files".to_string(), notification_tx))).await;
+
+        let (server_name, notification) = notification_rx.try_recv().unwrap();
+        assert_eq!(server_name, "files");
+        assert_eq!(notification.method, MCPNotification::TOOLS_LIST_CHANGED);
+        // The server's own request is not mistaken for a notification
+        assert!(notification_rx.try_recv().is_err());
+
+        assert_eq!(response_rx.await.unwrap().id, 7);
+        assert!(pending.lock().unwrap().is_empty());
+    }
+}
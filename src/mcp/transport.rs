@@ -1,11 +1,19 @@
 use crate::llm::{ToolDefinition, ToolCall, ToolResult};
 use anyhow::Result;
+use tokio_util::sync::CancellationToken;
+
+/// The server could not be reached: it failed to start, or its pipes broke.
+/// Unlike errors reported by the server itself, these may go away on a retry.
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct TransportError(pub String);
 
 #[async_trait::async_trait]
 pub trait MCPTransport: Send + Sync {
     async fn connect(&mut self) -> Result<()>;
     async fn disconnect(&mut self) -> Result<()>;
     async fn discover_tools(&mut self) -> Result<Vec<ToolDefinition>>;
-    async fn call_tool(&mut self, tool_call: ToolCall) -> Result<ToolResult>;
+    /// Call a tool; when `cancel` fires the server is asked to stop and the call fails
+    async fn call_tool(&mut self, tool_call: ToolCall, cancel: &CancellationToken) -> Result<ToolResult>;
 }
 
@@ -1,30 +1,256 @@
 use anyhow::Result;
 use log::{debug, warn};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct PromptConfig {
     pub system_prompt_file: Option<String>,
+    /// A single prompt template, listed together with the templates directory
     pub user_prompt_file: Option<String>,
+    /// Directory of Markdown prompt templates
+    #[serde(default = "default_templates_dir")]
+    pub templates_dir: Option<String>,
+}
+
+fn data_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("cosmic_llm")
+}
+
+fn default_templates_dir() -> Option<String> {
+    Some(data_dir().join("templates").to_string_lossy().to_string())
 }
 
 impl Default for PromptConfig {
     fn default() -> Self {
         // Default to data directory alongside config and database
-        let data_dir = dirs::data_dir()
-            .unwrap_or_else(|| std::path::PathBuf::from("."))
-            .join("cosmic_llm");
+        let data_dir = data_dir();
         
         Self {
             system_prompt_file: Some(data_dir.join("system_prompt.md").to_string_lossy().to_string()),
             user_prompt_file: Some(data_dir.join("user_prompt.md").to_string_lossy().to_string()),
+            templates_dir: default_templates_dir(),
+        }
+    }
+}
+
+impl PromptConfig {
+    /// Prompt files to watch for changes; the templates directory itself catches added and removed templates
+    pub fn paths(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = [&self.system_prompt_file, &self.user_prompt_file, &self.templates_dir]
+            .into_iter()
+            .flatten()
+            .map(PathBuf::from)
+            .collect();
+        if let Some(dir) = &self.templates_dir {
+            paths.extend(template_files(Path::new(dir)));
+        }
+        paths
+    }
+}
+
+/// Markdown files in the templates directory, by name
+fn template_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "md"))
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+/// Variables filled in by the app rather than the user
+pub const BUILTIN_VARIABLES: &[&str] = &["date", "time", "clipboard", "file", "file_path"];
+
+/// A Markdown prompt with `{{variable}}` placeholders
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptTemplate {
+    /// The first `# ` heading, or the file name
+    pub name: String,
+    pub body: String,
+}
+
+impl PromptTemplate {
+    /// Parse a template; a leading `# Title` line names it and is not part of the prompt
+    pub fn parse(file_stem: &str, content: &str) -> Self {
+        let content = content.trim();
+        match content.split_once('\n') {
+            Some((first, rest)) if first.starts_with("# ") => Self {
+                name: first[2..].trim().to_string(),
+                body: rest.trim().to_string(),
+            },
+            None if content.starts_with("# ") => Self {
+                name: content[2..].trim().to_string(),
+                body: String::new(),
+            },
+            _ => Self {
+                name: file_stem.to_string(),
+                body: content.to_string(),
+            },
+        }
+    }
+
+    fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read prompt template {}: {}", path.display(), e))?;
+        let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+        Ok(Self::parse(&stem, &content))
+    }
+
+    /// Variable names in order of first use
+    pub fn variables(&self) -> Vec<String> {
+        let mut variables = Vec::new();
+        for (_, name) in placeholders(&self.body) {
+            if !variables.contains(&name) {
+                variables.push(name);
+            }
+        }
+        variables
+    }
+
+    /// Variables the user fills in through the argument form
+    pub fn fields(&self) -> Vec<String> {
+        self.variables()
+            .into_iter()
+            .filter(|name| !BUILTIN_VARIABLES.contains(&name.as_str()))
+            .collect()
+    }
+
+    /// Replace placeholders with `values`; unknown variables become empty
+    pub fn render(&self, values: &HashMap<String, String>) -> String {
+        substitute(&self.body, |name| Some(values.get(name).cloned().unwrap_or_default()))
+    }
+}
+
+/// Replace each placeholder with `lookup(name)`, keeping those it returns None for
+fn substitute(text: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let mut rendered = String::with_capacity(text.len());
+    let mut last = 0;
+    for (range, name) in placeholders(text) {
+        rendered.push_str(&text[last..range.start]);
+        match lookup(&name) {
+            Some(value) => rendered.push_str(&value),
+            None => rendered.push_str(&text[range.clone()]),
+        }
+        last = range.end;
+    }
+    rendered.push_str(&text[last..]);
+    rendered
+}
+
+/// Byte ranges and trimmed names of the `{{name}}` placeholders in `text`
+fn placeholders(text: &str) -> Vec<(std::ops::Range<usize>, String)> {
+    let mut found = Vec::new();
+    let mut offset = 0;
+    while let Some(start) = text[offset..].find("{{") {
+        let start = offset + start;
+        let Some(len) = text[start + 2..].find("}}") else {
+            break;
+        };
+        let end = start + 2 + len + 2;
+        let name = text[start + 2..end - 2].trim();
+        let valid = !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == ' ');
+        if valid {
+            found.push((start..end, name.to_string()));
+            offset = end;
+        } else {
+            offset = start + 2;
+        }
+    }
+    found
+}
+
+/// Values of the built-in variables; `attached_files` provides `file` and `file_path`
+pub fn builtin_values(attached_files: &[String]) -> HashMap<String, String> {
+    let now = chrono::Local::now();
+    let mut values = HashMap::new();
+    values.insert("date".to_string(), now.format("%Y-%m-%d").to_string());
+    values.insert("time".to_string(), now.format("%H:%M").to_string());
+    values.insert("clipboard".to_string(), cli_clipboard::get_contents().unwrap_or_default());
+    if let Some(path) = attached_files.first() {
+        values.insert("file_path".to_string(), path.clone());
+        values.insert("file".to_string(), std::fs::read_to_string(path).unwrap_or_default());
+    }
+    values
+}
+
+/// Variables available in system prompts
+pub const SYSTEM_VARIABLES: &[&str] = &["date", "time", "weekday", "locale", "username", "persona", "tools", "cwd"];
+
+/// How deep `{{include ...}}` directives may nest
+const MAX_INCLUDE_DEPTH: usize = 4;
+
+/// Values of the system prompt variables for the next request
+pub fn system_values(persona: Option<&str>, tools: &[String]) -> HashMap<String, String> {
+    let now = chrono::Local::now();
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .map(|value| value.split('.').next().unwrap_or_default().to_string())
+        .unwrap_or_default();
+    let username = std::env::var("USER").or_else(|_| std::env::var("USERNAME")).unwrap_or_default();
+    let cwd = std::env::current_dir().map(|dir| dir.to_string_lossy().to_string()).unwrap_or_default();
+
+    let mut values = HashMap::new();
+    values.insert("date".to_string(), now.format("%Y-%m-%d").to_string());
+    values.insert("time".to_string(), now.format("%H:%M").to_string());
+    values.insert("weekday".to_string(), now.format("%A").to_string());
+    values.insert("locale".to_string(), locale);
+    values.insert("username".to_string(), username);
+    values.insert("persona".to_string(), persona.unwrap_or_default().to_string());
+    values.insert("tools".to_string(), tools.join(", "));
+    values.insert("cwd".to_string(), cwd);
+    values
+}
+
+/// Replace `{{include path}}` directives with the file's contents; relative paths resolve against `base_dir`
+fn expand_includes(text: &str, base_dir: &Path, depth: usize) -> String {
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{include ") {
+        let Some(len) = rest[start..].find("}}") else {
+            break;
+        };
+        let end = start + len + 2;
+        expanded.push_str(&rest[..start]);
+
+        let target = rest[start + "{{include ".len()..start + len].trim();
+        let path = match target.strip_prefix("~/") {
+            Some(relative) => dirs::home_dir().unwrap_or_default().join(relative),
+            None => base_dir.join(target),
+        };
+        if depth >= MAX_INCLUDE_DEPTH {
+            warn!("⚠️ Not including {}: includes nested more than {} deep", path.display(), MAX_INCLUDE_DEPTH);
+        } else {
+            match std::fs::read_to_string(&path) {
+                Ok(content) => {
+                    let parent = path.parent().unwrap_or(base_dir);
+                    expanded.push_str(&expand_includes(content.trim(), parent, depth + 1));
+                }
+                Err(e) => warn!("⚠️ Failed to include {} in system prompt: {}", path.display(), e),
+            }
         }
+        rest = &rest[end..];
     }
+    expanded.push_str(rest);
+    expanded
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone)]
 pub struct PromptManager {
     system_prompt: Option<String>,
+    /// Directory `{{include ...}}` paths in system prompts are relative to
+    include_dir: PathBuf,
+    templates: Vec<PromptTemplate>,
 }
 
 impl PromptManager {
@@ -35,18 +261,47 @@ impl PromptManager {
                     debug!("✅ Loaded system prompt from: {}", path);
                     Some(content.trim().to_string())
                 },
-                Err(e) => {
-                    warn!("⚠️ Failed to load system prompt from {}: {}", path, e);
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    warn!("⚠️ System prompt file {} does not exist", path);
                     None
                 }
+                Err(e) => {
+                    return Err(anyhow::anyhow!("Failed to read system prompt from {}: {}", path, e));
+                }
             }
         } else {
             debug!("No system prompt file configured");
             None
         };
 
+        let mut template_paths: Vec<PathBuf> = config.user_prompt_file.iter()
+            .map(PathBuf::from)
+            .filter(|path| path.exists())
+            .collect();
+        if let Some(dir) = &config.templates_dir {
+            template_paths.extend(template_files(Path::new(dir)));
+        }
+        // One broken file should not cost the other templates and the system prompt
+        let templates: Vec<PromptTemplate> = template_paths
+            .iter()
+            .filter_map(|path| match PromptTemplate::load(path) {
+                Ok(template) => Some(template),
+                Err(e) => {
+                    warn!("⚠️ Skipping prompt template {}: {}", path.display(), e);
+                    None
+                }
+            })
+            .collect();
+        debug!("✅ Loaded {} prompt templates", templates.len());
+
+        let include_dir = config.system_prompt_file.as_ref()
+            .and_then(|path| Path::new(path).parent().map(Path::to_path_buf))
+            .unwrap_or_else(data_dir);
+
         Ok(Self {
             system_prompt,
+            include_dir,
+            templates,
         })
     }
 
@@ -54,4 +309,95 @@ impl PromptManager {
         self.system_prompt.as_deref()
     }
 
+    /// Expand includes and variables of a system prompt; unknown placeholders are left as written
+    pub fn render_system_prompt(&self, prompt: &str, values: &HashMap<String, String>) -> String {
+        let expanded = expand_includes(prompt, &self.include_dir, 0);
+        substitute(&expanded, |name| values.get(name).cloned())
+    }
+
+    pub fn templates(&self) -> &[PromptTemplate] {
+        &self.templates
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_template_title() {
+        let template = PromptTemplate::parse("review", "# Code review\n\nReview {{file}} for {{focus}}.");
+        assert_eq!(template.name, "Code review");
+        assert_eq!(template.body, "Review {{file}} for {{focus}}.");
+
+        let untitled = PromptTemplate::parse("summarize", "Summarize:\n{{clipboard}}");
+        assert_eq!(untitled.name, "summarize");
+        assert_eq!(untitled.body, "Summarize:\n{{clipboard}}");
+    }
+
+    #[test]
+    fn test_template_variables() {
+        let template = PromptTemplate::parse("t", "{{ tone }} reply to {{recipient}} on {{date}}, keep it {{tone}}. {{}} {{a.b}}");
+        assert_eq!(template.variables(), vec!["tone", "recipient", "date"]);
+        assert_eq!(template.fields(), vec!["tone", "recipient"]);
+    }
+
+    #[test]
+    fn test_render_template() {
+        let template = PromptTemplate::parse("t", "Dear {{recipient}},\n{{ body }} ({{missing}}) {{not closed");
+        let values = HashMap::from([
+            ("recipient".to_string(), "Ana".to_string()),
+            ("body".to_string(), "See you {{date}}".to_string()),
+        ]);
+        // Values are inserted as-is, not expanded again
+        assert_eq!(template.render(&values), "Dear Ana,\nSee you {{date}} () {{not closed");
+    }
+
+    #[test]
+    fn test_render_system_prompt() {
+        let dir = std::env::temp_dir().join(format!("cosmic_llm_prompts_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("parts")).unwrap();
+        std::fs::write(dir.join("parts/style.md"), "Answer as {{persona}}.\n{{include tone.md}}\n").unwrap();
+        std::fs::write(dir.join("parts/tone.md"), "Be brief.").unwrap();
+        std::fs::write(dir.join("loop.md"), "{{include loop.md}}").unwrap();
+
+        let manager = PromptManager::load_from_config(&PromptConfig {
+            system_prompt_file: Some(dir.join("system_prompt.md").to_string_lossy().to_string()),
+            user_prompt_file: None,
+            templates_dir: None,
+        })
+        .unwrap();
+        let values = system_values(Some("Reviewer"), &["search".to_string(), "read_file".to_string()]);
+
+        let rendered = manager.render_system_prompt("Tools: {{tools}}. {{include parts/style.md}} {{other}}", &values);
+        assert_eq!(rendered, "Tools: search, read_file. Answer as Reviewer.\nBe brief. {{other}}");
+        assert_eq!(manager.render_system_prompt("a{{include missing.md}}b", &values), "ab");
+        // Self-including files stop at the depth limit
+        assert_eq!(manager.render_system_prompt("{{include loop.md}}", &values), "");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_unreadable_template_is_skipped() {
+        let dir = std::env::temp_dir().join(format!("cosmic_llm_templates_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("templates")).unwrap();
+        std::fs::write(dir.join("system_prompt.md"), "Be helpful.").unwrap();
+        std::fs::write(dir.join("templates/review.md"), "# Review\nReview {{file}}").unwrap();
+        // Not UTF-8
+        std::fs::write(dir.join("templates/broken.md"), [0xff, 0xfe, 0x00]).unwrap();
+
+        let manager = PromptManager::load_from_config(&PromptConfig {
+            system_prompt_file: Some(dir.join("system_prompt.md").to_string_lossy().to_string()),
+            user_prompt_file: None,
+            templates_dir: Some(dir.join("templates").to_string_lossy().to_string()),
+        })
+        .unwrap();
+        assert_eq!(manager.get_system_prompt(), Some("Be helpful."));
+        let names: Vec<&str> = manager.templates().iter().map(|template| template.name.as_str()).collect();
+        assert_eq!(names, vec!["Review"]);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 }
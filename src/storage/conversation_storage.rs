@@ -39,6 +39,21 @@ pub struct Conversation {
     pub updated_at: DateTime<Utc>,
     pub messages: Vec<StoredMessage>,
     pub turns: Vec<Turn>,
+    #[serde(default)]
+    pub settings: ConversationSettings,
+}
+
+/// Model choice and parameter overrides remembered by a conversation
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConversationSettings {
+    /// Persona the conversation was started with
+    pub persona: Option<String>,
+    /// Profile name; None follows the persona's or the default profile
+    pub profile: Option<String>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    /// Replaces the system prompt from system_prompt.md
+    pub system_prompt: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -59,6 +74,7 @@ impl Conversation {
             updated_at: now,
             messages: Vec::new(),
             turns: Vec::new(),
+            settings: ConversationSettings::default(),
         }
     }
 
@@ -122,6 +138,20 @@ impl Conversation {
         
         llm_messages
     }
+
+    /// Render the conversation as a Markdown document for export
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("# {}\n\n", self.title);
+        out.push_str(&format!("_Created {}_\n", self.created_at.format("%Y-%m-%d %H:%M")));
+        for msg in &self.messages {
+            match msg.role.as_str() {
+                "user" => out.push_str(&format!("\n## You\n\n{}\n", msg.content.trim_end())),
+                "assistant" => out.push_str(&format!("\n## Assistant\n\n{}\n", msg.content.trim_end())),
+                _ => out.push_str(&format!("\n_{}_\n", msg.content.trim())),
+            }
+        }
+        out
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -130,6 +160,20 @@ pub struct ConversationIndex {
     pub title: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub folder: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub archived: bool,
+    #[serde(default)]
+    pub message_count: usize,
+    #[serde(default)]
+    pub last_model: Option<String>,
+    #[serde(default)]
+    pub last_snippet: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -223,6 +267,13 @@ impl Storage {
                 title: conv.title.clone(),
                 created_at: conv.created_at,
                 updated_at: conv.updated_at,
+                folder: None,
+                tags: Vec::new(),
+                pinned: false,
+                archived: false,
+                message_count: conv.messages.len(),
+                last_model: None,
+                last_snippet: conv.messages.last().map(|msg| msg.content.chars().take(200).collect()),
             })
             .collect();
         
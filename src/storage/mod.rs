@@ -1,6 +1,7 @@
 pub mod conversation_storage;
+pub mod search_query;
 pub mod sqlite_storage_simple;
 pub mod storage_wrapper;
 
 // Re-export the storage wrapper as the default Storage
-pub use storage_wrapper::Storage;
\ No newline at end of file
+pub use storage_wrapper::{MessagePage, Storage, StorageEvent};
\ No newline at end of file
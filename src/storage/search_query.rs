@@ -0,0 +1,211 @@
+use chrono::NaiveDate;
+
+/// A History search: free text plus `role:`, `before:`, `after:`, `tag:` and `model:` filters
+///
+/// Words and "quoted phrases" must all appear in a message; `-word` excludes messages containing it.
+/// Every term is quoted before it reaches FTS5, so operators and stray quotes in the input are plain text.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchQuery {
+    pub terms: Vec<String>,
+    pub excluded: Vec<String>,
+    /// Only messages with this role (user, assistant or notice)
+    pub role: Option<String>,
+    /// Only messages written before this day
+    pub before: Option<NaiveDate>,
+    /// Only messages written on or after this day
+    pub after: Option<NaiveDate>,
+    /// Only conversations carrying all of these tags
+    pub tags: Vec<String>,
+    /// Only conversations whose profile or model contains this text
+    pub model: Option<String>,
+    /// Filters that could not be understood and were ignored
+    pub invalid: Vec<String>,
+}
+
+impl SearchQuery {
+    pub fn parse(input: &str) -> Self {
+        let mut query = Self::default();
+        for (token, quoted) in tokenize(input) {
+            if quoted {
+                query.terms.push(token);
+                continue;
+            }
+            if let Some((key, value)) = token.split_once(':') {
+                let value = value.trim_matches('"');
+                let key = key.to_ascii_lowercase();
+                let known = match key.as_str() {
+                    "role" => match value.to_ascii_lowercase().as_str() {
+                        role @ ("user" | "assistant" | "notice") => {
+                            query.role = Some(role.to_string());
+                            Some(true)
+                        }
+                        _ => Some(false),
+                    },
+                    "before" | "after" => match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+                        Ok(date) => {
+                            if key == "before" {
+                                query.before = Some(date);
+                            } else {
+                                query.after = Some(date);
+                            }
+                            Some(true)
+                        }
+                        Err(_) => Some(false),
+                    },
+                    "tag" if !value.is_empty() => {
+                        query.tags.push(value.to_lowercase());
+                        Some(true)
+                    }
+                    "model" if !value.is_empty() => {
+                        query.model = Some(value.to_lowercase());
+                        Some(true)
+                    }
+                    "tag" | "model" => Some(false),
+                    // Not a filter, e.g. a URL or "note:"
+                    _ => None,
+                };
+                match known {
+                    Some(true) => continue,
+                    Some(false) => {
+                        query.invalid.push(token);
+                        continue;
+                    }
+                    None => {}
+                }
+            }
+            match token.strip_prefix('-') {
+                Some(excluded) if !excluded.is_empty() => query.excluded.push(excluded.to_string()),
+                _ => query.terms.push(token),
+            }
+        }
+        query
+    }
+
+    /// True when there is nothing to search for or filter by
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+            && self.excluded.is_empty()
+            && self.role.is_none()
+            && self.before.is_none()
+            && self.after.is_none()
+            && self.tags.is_empty()
+            && self.model.is_none()
+    }
+
+    /// The FTS5 MATCH expression, or None when the query only has filters or exclusions.
+    /// The last bare word matches as a prefix so results follow typing.
+    pub fn fts_query(&self, last_term_is_prefix: bool) -> Option<String> {
+        if self.terms.is_empty() {
+            return None;
+        }
+        let mut terms: Vec<String> = self.terms.iter().map(|term| quote(term)).collect();
+        if last_term_is_prefix {
+            if let Some(last) = terms.last_mut() {
+                last.push('*');
+            }
+        }
+        let mut expression = format!("({})", terms.join(" "));
+        for excluded in &self.excluded {
+            expression.push_str(" NOT ");
+            expression.push_str(&quote(excluded));
+        }
+        Some(expression)
+    }
+
+    /// FTS5 expression matching messages with any excluded word, for a query without terms
+    /// (FTS5 has no standalone NOT, so those messages are left out by id instead)
+    pub fn excluded_fts_query(&self) -> Option<String> {
+        if !self.terms.is_empty() || self.excluded.is_empty() {
+            return None;
+        }
+        let excluded: Vec<String> = self.excluded.iter().map(|term| quote(term)).collect();
+        Some(excluded.join(" OR "))
+    }
+
+    /// Whether the last term was typed as a bare word (and may still be incomplete)
+    pub fn ends_with_bare_word(input: &str) -> bool {
+        tokenize(input).last().is_some_and(|(token, quoted)| !quoted && !token.contains(':') && !token.starts_with('-'))
+    }
+}
+
+/// An FTS5 string literal
+fn quote(term: &str) -> String {
+    format!("\"{}\"", term.replace('"', "\"\""))
+}
+
+/// Split on whitespace, keeping "quoted phrases" together; the flag marks phrases
+fn tokenize(input: &str) -> Vec<(String, bool)> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if current.is_empty() => {
+                let phrase: String = chars.by_ref().take_while(|c| *c != '"').collect();
+                if !phrase.trim().is_empty() {
+                    tokens.push((phrase.trim().to_string(), true));
+                }
+            }
+            // key:"quoted value"
+            '"' if current.ends_with(':') => {
+                let value: String = chars.by_ref().take_while(|c| *c != '"').collect();
+                current.push_str(&value);
+                tokens.push((std::mem::take(&mut current), false));
+            }
+            c if c.is_whitespace() => {
+                if !current.is_empty() {
+                    tokens.push((std::mem::take(&mut current), false));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push((current, false));
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_filters() {
+        let query = SearchQuery::parse(r#"role:user tag:Rust after:2024-05-01 before:2024-06-01 model:"gpt 4" borrow checker"#);
+        assert_eq!(query.role.as_deref(), Some("user"));
+        assert_eq!(query.tags, vec!["rust".to_string()]);
+        assert_eq!(query.after, NaiveDate::from_ymd_opt(2024, 5, 1));
+        assert_eq!(query.before, NaiveDate::from_ymd_opt(2024, 6, 1));
+        assert_eq!(query.model.as_deref(), Some("gpt 4"));
+        assert_eq!(query.terms, vec!["borrow".to_string(), "checker".to_string()]);
+
+        let query = SearchQuery::parse("before:yesterday role:robot https://example.com");
+        assert_eq!(query.invalid, vec!["before:yesterday".to_string(), "role:robot".to_string()]);
+        assert_eq!(query.terms, vec!["https://example.com".to_string()]);
+    }
+
+    #[test]
+    fn test_fts_query_is_quoted() {
+        let query = SearchQuery::parse(r#"say "hello world" -AND O'Brien""#);
+        assert_eq!(
+            query.fts_query(false).unwrap(),
+            r#"("say" "hello world" "O'Brien""") NOT "AND""#
+        );
+        assert_eq!(SearchQuery::parse("rus").fts_query(true).unwrap(), r#"("rus"*)"#);
+        assert_eq!(SearchQuery::parse("tag:rust").fts_query(true), None);
+        assert_eq!(SearchQuery::parse("rust -go").excluded_fts_query(), None);
+        assert!(SearchQuery::ends_with_bare_word("role:user rus"));
+        assert!(!SearchQuery::ends_with_bare_word("\"rus\""));
+    }
+
+    #[test]
+    fn test_exclusion_only_query() {
+        let query = SearchQuery::parse("-go -OR tag:lang");
+        assert!(!query.is_empty());
+        assert!(!SearchQuery::parse("-go").is_empty());
+        assert!(SearchQuery::parse("  ").is_empty());
+        assert_eq!(query.fts_query(true), None);
+        assert_eq!(query.excluded_fts_query().unwrap(), r#""go" OR "OR""#);
+    }
+}
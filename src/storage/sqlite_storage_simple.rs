@@ -4,12 +4,27 @@ use serde::{Deserialize, Serialize};
 use std::path::Path;
 use uuid::Uuid;
 
+use super::conversation_storage::ConversationSettings;
+use super::search_query::SearchQuery;
+
 /// Represents a conversation in the database
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Conversation {
     pub id: String,
     pub title: String,
     pub created_at: i64,
+    pub folder: Option<String>,
+    pub tags: Vec<String>,
+    pub pinned: bool,
+    pub archived: bool,
+    /// Time of the latest message, or creation while there is none
+    pub updated_at: i64,
+    /// User and assistant messages, without notices
+    pub message_count: usize,
+    /// Model that wrote the latest reply
+    pub last_model: Option<String>,
+    /// Start of the latest user or assistant message
+    pub last_snippet: Option<String>,
 }
 
 /// Represents a message in the database
@@ -23,15 +38,47 @@ pub struct Message {
     pub created_at: i64,
 }
 
+/// Marks the start of a matched term in `Snippet::snippet`
+pub const MATCH_START: char = '\u{2}';
+/// Marks the end of a matched term in `Snippet::snippet`
+pub const MATCH_END: char = '\u{3}';
+
 /// Represents a search snippet from FTS5
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Snippet {
     pub conversation_id: String,
+    pub message_id: i64,
+    pub role: String,
     pub content: String,
+    /// Excerpt around the matches, which are wrapped in MATCH_START and MATCH_END
+    pub snippet: String,
+    /// Index of the message within its conversation
+    pub position: usize,
     pub timestamp: i64,
     pub rank: f64,
 }
 
+impl Snippet {
+    /// The excerpt split into pieces, flagged when they matched the query
+    pub fn highlights(&self) -> Vec<(&str, bool)> {
+        let mut parts = Vec::new();
+        let mut rest = self.snippet.as_str();
+        while let Some(start) = rest.find(MATCH_START) {
+            if start > 0 {
+                parts.push((&rest[..start], false));
+            }
+            rest = &rest[start + MATCH_START.len_utf8()..];
+            let end = rest.find(MATCH_END).unwrap_or(rest.len());
+            parts.push((&rest[..end], true));
+            rest = rest.get(end + MATCH_END.len_utf8()..).unwrap_or_default();
+        }
+        if !rest.is_empty() {
+            parts.push((rest, false));
+        }
+        parts
+    }
+}
+
 /// SQLite-based storage implementation
 pub struct SqliteStorage {
     conn: Connection,
@@ -63,6 +110,34 @@ impl SqliteStorage {
             [],
         )?;
 
+        // Per-conversation model settings, added after the tables were first created
+        self.add_column_if_missing("conversations", "profile", "TEXT")?;
+        self.add_column_if_missing("conversations", "temperature", "REAL")?;
+        self.add_column_if_missing("conversations", "max_tokens", "INTEGER")?;
+        self.add_column_if_missing("conversations", "system_prompt", "TEXT")?;
+        self.add_column_if_missing("conversations", "persona", "TEXT")?;
+
+        // Organization of the history
+        self.add_column_if_missing("conversations", "folder", "TEXT")?;
+        self.add_column_if_missing("conversations", "pinned", "INTEGER NOT NULL DEFAULT 0")?;
+        self.add_column_if_missing("conversations", "archived", "INTEGER NOT NULL DEFAULT 0")?;
+
+        // Activity, kept up to date by the messages_activity trigger
+        self.add_column_if_missing("conversations", "updated_at", "INTEGER")?;
+        self.add_column_if_missing("conversations", "message_count", "INTEGER NOT NULL DEFAULT 0")?;
+        self.add_column_if_missing("conversations", "last_model", "TEXT")?;
+        self.add_column_if_missing("conversations", "last_snippet", "TEXT")?;
+
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS conversation_tags (
+                conversation_id TEXT NOT NULL,
+                tag TEXT NOT NULL,
+                PRIMARY KEY (conversation_id, tag),
+                FOREIGN KEY (conversation_id) REFERENCES conversations (id) ON DELETE CASCADE
+            )",
+            [],
+        )?;
+
         // Create messages table
         self.conn.execute(
             "CREATE TABLE IF NOT EXISTS messages (
@@ -77,6 +152,21 @@ impl SqliteStorage {
             [],
         )?;
 
+        // Model that wrote an assistant message
+        self.add_column_if_missing("messages", "model", "TEXT")?;
+
+        // Fill in the activity of conversations from before it was tracked
+        self.conn.execute(
+            "UPDATE conversations SET
+                updated_at = coalesce((SELECT max(created_at) FROM messages WHERE conversation_id = conversations.id), created_at),
+                message_count = (SELECT count(*) FROM messages WHERE conversation_id = conversations.id AND role != 'notice'),
+                last_snippet = (SELECT substr(content, 1, 200) FROM messages
+                    WHERE conversation_id = conversations.id AND role != 'notice'
+                    ORDER BY created_at DESC, id DESC LIMIT 1)
+             WHERE updated_at IS NULL",
+            [],
+        )?;
+
         // Create FTS5 virtual table for full-text search
         self.conn.execute(
             "CREATE VIRTUAL TABLE IF NOT EXISTS messages_fts USING fts5(
@@ -97,6 +187,19 @@ impl SqliteStorage {
             [],
         )?;
 
+        // Keep the activity of the conversation current
+        self.conn.execute(
+            "CREATE TRIGGER IF NOT EXISTS messages_activity AFTER INSERT ON messages BEGIN
+                UPDATE conversations SET
+                    updated_at = max(coalesce(updated_at, 0), new.created_at),
+                    message_count = message_count + (new.role != 'notice'),
+                    last_snippet = CASE WHEN new.role = 'notice' THEN last_snippet ELSE substr(new.content, 1, 200) END,
+                    last_model = coalesce(new.model, last_model)
+                WHERE id = new.conversation_id;
+            END",
+            [],
+        )?;
+
         // Create trigger to update FTS5 when messages are updated
         self.conn.execute(
             "CREATE TRIGGER IF NOT EXISTS messages_au AFTER UPDATE ON messages BEGIN
@@ -125,6 +228,29 @@ impl SqliteStorage {
             [],
         )?;
 
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_conversation_tags_tag ON conversation_tags(tag)",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_conversations_updated_at ON conversations(updated_at)",
+            [],
+        )?;
+
+        Ok(())
+    }
+
+    /// Add a column to an existing table unless a previous run already did
+    fn add_column_if_missing(&self, table: &str, column: &str, declaration: &str) -> SqliteResult<()> {
+        let exists = self.conn.prepare(&format!("PRAGMA table_info({})", table))?
+            .query_map([], |row| row.get::<_, String>(1))?
+            .collect::<Result<Vec<String>, _>>()?
+            .iter()
+            .any(|name| name == column);
+        if !exists {
+            self.conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, declaration), [])?;
+        }
         Ok(())
     }
 
@@ -134,7 +260,7 @@ impl SqliteStorage {
         let created_at = Utc::now().timestamp();
         
         self.conn.execute(
-            "INSERT INTO conversations (id, title, created_at) VALUES (?1, ?2, ?3)",
+            "INSERT INTO conversations (id, title, created_at, updated_at) VALUES (?1, ?2, ?3, ?3)",
             params![id, title, created_at],
         )?;
 
@@ -148,6 +274,22 @@ impl SqliteStorage {
         role: &str,
         content: &str,
         embedding: Option<&[f32]>,
+    ) -> SqliteResult<()> {
+        self.insert_message_row(conversation_id, role, content, embedding, None)
+    }
+
+    /// Insert an assistant message along with the model that wrote it
+    pub fn insert_reply(&self, conversation_id: &str, content: &str, model: &str) -> SqliteResult<()> {
+        self.insert_message_row(conversation_id, "assistant", content, None, Some(model))
+    }
+
+    fn insert_message_row(
+        &self,
+        conversation_id: &str,
+        role: &str,
+        content: &str,
+        embedding: Option<&[f32]>,
+        model: Option<&str>,
     ) -> SqliteResult<()> {
         let created_at = Utc::now().timestamp();
         
@@ -159,9 +301,9 @@ impl SqliteStorage {
         };
 
         self.conn.execute(
-            "INSERT INTO messages (conversation_id, role, content, embedding, created_at) 
-             VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![conversation_id, role, content, embedding_bytes, created_at],
+            "INSERT INTO messages (conversation_id, role, content, embedding, created_at, model) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![conversation_id, role, content, embedding_bytes, created_at, model],
         )?;
 
         Ok(())
@@ -173,28 +315,10 @@ impl SqliteStorage {
             "SELECT id, conversation_id, role, content, embedding, created_at 
              FROM messages 
              WHERE conversation_id = ?1 
-             ORDER BY created_at ASC"
+             ORDER BY created_at ASC, id ASC"
         )?;
 
-        let message_iter = stmt.query_map(params![conversation_id], |row| {
-            let embedding_bytes: Option<Vec<u8>> = row.get(4)?;
-            let embedding = if let Some(bytes) = embedding_bytes {
-                Some(bytes.chunks_exact(4)
-                    .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
-                    .collect())
-            } else {
-                None
-            };
-
-            Ok(Message {
-                id: row.get(0)?,
-                conversation_id: row.get(1)?,
-                role: row.get(2)?,
-                content: row.get(3)?,
-                embedding,
-                created_at: row.get(5)?,
-            })
-        })?;
+        let message_iter = stmt.query_map(params![conversation_id], Self::message_from_row)?;
 
         let mut messages = Vec::new();
         for message in message_iter {
@@ -204,27 +328,176 @@ impl SqliteStorage {
         Ok(messages)
     }
 
+    /// The newest `limit` messages of a conversation older than the message `before_id` (or all), oldest first
+    pub fn load_messages_before(&self, conversation_id: &str, before_id: Option<i64>, limit: usize) -> SqliteResult<Vec<Message>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, conversation_id, role, content, embedding, created_at 
+             FROM messages 
+             WHERE conversation_id = ?1 AND id < ?2
+             ORDER BY id DESC
+             LIMIT ?3"
+        )?;
+
+        let message_iter = stmt.query_map(
+            params![conversation_id, before_id.unwrap_or(i64::MAX), limit as i64],
+            Self::message_from_row,
+        )?;
+
+        let mut messages = Vec::new();
+        for message in message_iter {
+            messages.push(message?);
+        }
+        messages.reverse();
+
+        Ok(messages)
+    }
+
+    /// Up to `limit` messages of a conversation from the message `from_id` on, oldest first
+    pub fn load_messages_from(&self, conversation_id: &str, from_id: i64, limit: usize) -> SqliteResult<Vec<Message>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, conversation_id, role, content, embedding, created_at 
+             FROM messages 
+             WHERE conversation_id = ?1 AND id >= ?2
+             ORDER BY id ASC
+             LIMIT ?3"
+        )?;
+
+        let message_iter = stmt.query_map(params![conversation_id, from_id, limit as i64], Self::message_from_row)?;
+
+        let mut messages = Vec::new();
+        for message in message_iter {
+            messages.push(message?);
+        }
+
+        Ok(messages)
+    }
+
+    /// Whether the conversation has messages older than the message `id`
+    pub fn has_messages_before(&self, conversation_id: &str, id: i64) -> SqliteResult<bool> {
+        self.conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM messages WHERE conversation_id = ?1 AND id < ?2)",
+            params![conversation_id, id],
+            |row| row.get(0),
+        )
+    }
+
+    /// Whether the conversation has messages newer than the message `id`
+    pub fn has_messages_after(&self, conversation_id: &str, id: i64) -> SqliteResult<bool> {
+        self.conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM messages WHERE conversation_id = ?1 AND id > ?2)",
+            params![conversation_id, id],
+            |row| row.get(0),
+        )
+    }
+
+    fn message_from_row(row: &rusqlite::Row) -> SqliteResult<Message> {
+        let embedding_bytes: Option<Vec<u8>> = row.get(4)?;
+        let embedding = if let Some(bytes) = embedding_bytes {
+            Some(bytes.chunks_exact(4)
+                .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                .collect())
+        } else {
+            None
+        };
+
+        Ok(Message {
+            id: row.get(0)?,
+            conversation_id: row.get(1)?,
+            role: row.get(2)?,
+            content: row.get(3)?,
+            embedding,
+            created_at: row.get(5)?,
+        })
+    }
+
     /// Search messages using FTS5
     pub fn search_history(&self, query: &str, limit: usize) -> SqliteResult<Vec<Snippet>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT 
+        self.search(&SearchQuery::parse(query), false, limit, 0)
+    }
+
+    /// Search messages matching the terms and filters of `query`, best matches first.
+    /// Without terms, messages passing the filters and exclusions are listed newest first.
+    pub fn search(&self, query: &SearchQuery, prefix: bool, limit: usize, offset: usize) -> SqliteResult<Vec<Snippet>> {
+        use rusqlite::types::Value;
+
+        let fts_query = query.fts_query(prefix);
+        let mut sql = String::from(
+            "SELECT
                 m.conversation_id,
+                m.id,
+                m.role,
                 m.content,
-                m.created_at,
-                rank
-             FROM messages_fts fts
-             JOIN messages m ON fts.rowid = m.id
-             WHERE messages_fts MATCH ?1
-             ORDER BY rank
-             LIMIT ?2"
-        )?;
+                m.created_at,"
+        );
+        if fts_query.is_some() {
+            sql.push_str(&format!(
+                " bm25(messages_fts), snippet(messages_fts, 1, char({}), char({}), '…', 16),",
+                MATCH_START as u32, MATCH_END as u32
+            ));
+        } else {
+            sql.push_str(" 0.0, substr(m.content, 1, 200),");
+        }
+        sql.push_str(
+            " (SELECT COUNT(*) FROM messages p WHERE p.conversation_id = m.conversation_id
+                AND (p.created_at < m.created_at OR (p.created_at = m.created_at AND p.id < m.id)))
+             FROM messages m
+             JOIN conversations c ON c.id = m.conversation_id"
+        );
+
+        let mut values: Vec<Value> = Vec::new();
+        if let Some(fts_query) = fts_query {
+            sql.push_str(" JOIN messages_fts ON messages_fts.rowid = m.id WHERE messages_fts MATCH ?");
+            values.push(Value::Text(fts_query));
+        } else {
+            sql.push_str(" WHERE 1 = 1");
+            if let Some(excluded) = query.excluded_fts_query() {
+                sql.push_str(" AND m.id NOT IN (SELECT rowid FROM messages_fts WHERE messages_fts MATCH ?)");
+                values.push(Value::Text(excluded));
+            }
+        }
+        if let Some(role) = &query.role {
+            sql.push_str(" AND m.role = ?");
+            values.push(Value::Text(role.clone()));
+        }
+        let day_start = |date: chrono::NaiveDate| {
+            date.and_time(chrono::NaiveTime::MIN)
+                .and_local_timezone(chrono::Local)
+                .earliest()
+                .map(|start| start.timestamp())
+                .unwrap_or_else(|| date.and_time(chrono::NaiveTime::MIN).and_utc().timestamp())
+        };
+        if let Some(before) = query.before {
+            sql.push_str(" AND m.created_at < ?");
+            values.push(Value::Integer(day_start(before)));
+        }
+        if let Some(after) = query.after {
+            sql.push_str(" AND m.created_at >= ?");
+            values.push(Value::Integer(day_start(after)));
+        }
+        for tag in &query.tags {
+            sql.push_str(" AND EXISTS (SELECT 1 FROM conversation_tags t WHERE t.conversation_id = m.conversation_id AND t.tag = ?)");
+            values.push(Value::Text(tag.clone()));
+        }
+        if let Some(model) = &query.model {
+            sql.push_str(" AND instr(lower(coalesce(c.profile, '') || ' ' || coalesce(c.last_model, '')), ?) > 0");
+            values.push(Value::Text(model.clone()));
+        }
+        sql.push_str(if query.terms.is_empty() { " ORDER BY m.created_at DESC, m.id DESC" } else { " ORDER BY bm25(messages_fts)" });
+        sql.push_str(" LIMIT ? OFFSET ?");
+        values.push(Value::Integer(limit as i64));
+        values.push(Value::Integer(offset as i64));
 
-        let snippet_iter = stmt.query_map(params![query, limit], |row| {
+        let mut stmt = self.conn.prepare(&sql)?;
+        let snippet_iter = stmt.query_map(rusqlite::params_from_iter(values), |row| {
             Ok(Snippet {
                 conversation_id: row.get(0)?,
-                content: row.get(1)?,
-                timestamp: row.get(2)?,
-                rank: row.get(3)?,
+                message_id: row.get(1)?,
+                role: row.get(2)?,
+                content: row.get(3)?,
+                timestamp: row.get(4)?,
+                rank: row.get(5)?,
+                snippet: row.get(6)?,
+                position: row.get::<_, i64>(7)? as usize,
             })
         })?;
 
@@ -246,34 +519,83 @@ impl SqliteStorage {
         Ok(changes > 0)
     }
 
-    /// Get conversation by ID
-    pub fn get_conversation(&self, conversation_id: &str) -> SqliteResult<Option<Conversation>> {
+    /// Get the model settings of a conversation
+    pub fn get_conversation_settings(&self, conversation_id: &str) -> SqliteResult<Option<ConversationSettings>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, title, created_at FROM conversations WHERE id = ?1"
+            "SELECT persona, profile, temperature, max_tokens, system_prompt FROM conversations WHERE id = ?1"
         )?;
 
         stmt.query_row(params![conversation_id], |row| {
-            Ok(Conversation {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                created_at: row.get(2)?,
+            Ok(ConversationSettings {
+                persona: row.get(0)?,
+                profile: row.get(1)?,
+                temperature: row.get::<_, Option<f64>>(2)?.map(|t| t as f32),
+                max_tokens: row.get(3)?,
+                system_prompt: row.get(4)?,
             })
         }).optional()
     }
 
-    /// List all conversations ordered by creation date (newest first)
-    pub fn list_conversations(&self) -> SqliteResult<Vec<Conversation>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, title, created_at FROM conversations ORDER BY created_at DESC"
+    /// Update the model settings of a conversation
+    pub fn update_conversation_settings(&self, conversation_id: &str, settings: &ConversationSettings) -> SqliteResult<bool> {
+        let changes = self.conn.execute(
+            "UPDATE conversations SET persona = ?1, profile = ?2, temperature = ?3, max_tokens = ?4, system_prompt = ?5 WHERE id = ?6",
+            params![
+                settings.persona,
+                settings.profile,
+                settings.temperature.map(|t| t as f64),
+                settings.max_tokens,
+                settings.system_prompt,
+                conversation_id
+            ],
         )?;
 
-        let conversation_iter = stmt.query_map([], |row| {
-            Ok(Conversation {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                created_at: row.get(2)?,
-            })
-        })?;
+        Ok(changes > 0)
+    }
+
+    /// Columns read into a `Conversation`, tags joined with commas
+    const CONVERSATION_COLUMNS: &'static str = "id, title, created_at, folder, pinned, archived,
+        (SELECT group_concat(tag, ',') FROM conversation_tags WHERE conversation_id = conversations.id),
+        coalesce(updated_at, created_at), message_count, last_model, last_snippet";
+
+    fn conversation_from_row(row: &rusqlite::Row) -> SqliteResult<Conversation> {
+        let mut tags: Vec<String> = row.get::<_, Option<String>>(6)?
+            .map(|tags| tags.split(',').map(str::to_string).collect())
+            .unwrap_or_default();
+        tags.sort();
+        Ok(Conversation {
+            id: row.get(0)?,
+            title: row.get(1)?,
+            created_at: row.get(2)?,
+            folder: row.get(3)?,
+            pinned: row.get(4)?,
+            archived: row.get(5)?,
+            tags,
+            updated_at: row.get(7)?,
+            message_count: row.get::<_, i64>(8)? as usize,
+            last_model: row.get(9)?,
+            last_snippet: row.get(10)?,
+        })
+    }
+
+    /// Get conversation by ID
+    pub fn get_conversation(&self, conversation_id: &str) -> SqliteResult<Option<Conversation>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM conversations WHERE id = ?1",
+            Self::CONVERSATION_COLUMNS
+        ))?;
+
+        stmt.query_row(params![conversation_id], Self::conversation_from_row).optional()
+    }
+
+    /// List all conversations, most recently active first
+    pub fn list_conversations(&self) -> SqliteResult<Vec<Conversation>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM conversations ORDER BY coalesce(updated_at, created_at) DESC, created_at DESC",
+            Self::CONVERSATION_COLUMNS
+        ))?;
+
+        let conversation_iter = stmt.query_map([], Self::conversation_from_row)?;
 
         let mut conversations = Vec::new();
         for conversation in conversation_iter {
@@ -283,12 +605,60 @@ impl SqliteStorage {
         Ok(conversations)
     }
 
+    /// Move a conversation into a folder, or out of any folder with None
+    pub fn update_folder(&self, conversation_id: &str, folder: Option<&str>) -> SqliteResult<bool> {
+        let changes = self.conn.execute(
+            "UPDATE conversations SET folder = ?1 WHERE id = ?2",
+            params![folder, conversation_id],
+        )?;
+
+        Ok(changes > 0)
+    }
+
+    /// Pin a conversation to the top of the history
+    pub fn update_pinned(&self, conversation_id: &str, pinned: bool) -> SqliteResult<bool> {
+        let changes = self.conn.execute(
+            "UPDATE conversations SET pinned = ?1 WHERE id = ?2",
+            params![pinned, conversation_id],
+        )?;
+
+        Ok(changes > 0)
+    }
+
+    /// Move a conversation to or out of the archive
+    pub fn update_archived(&self, conversation_id: &str, archived: bool) -> SqliteResult<bool> {
+        let changes = self.conn.execute(
+            "UPDATE conversations SET archived = ?1 WHERE id = ?2",
+            params![archived, conversation_id],
+        )?;
+
+        Ok(changes > 0)
+    }
+
+    /// Replace the tags of a conversation
+    pub fn update_tags(&self, conversation_id: &str, tags: &[String]) -> SqliteResult<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM conversation_tags WHERE conversation_id = ?1", params![conversation_id])?;
+        for tag in tags {
+            tx.execute(
+                "INSERT OR IGNORE INTO conversation_tags (conversation_id, tag) VALUES (?1, ?2)",
+                params![conversation_id, tag],
+            )?;
+        }
+        tx.commit()
+    }
+
     /// Delete a conversation and all its messages
     pub fn delete_conversation(&self, conversation_id: &str) -> SqliteResult<bool> {
-        let changes = self.conn.execute(
+        // Foreign keys are not enforced, so the cascade is done here
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM messages WHERE conversation_id = ?1", params![conversation_id])?;
+        tx.execute("DELETE FROM conversation_tags WHERE conversation_id = ?1", params![conversation_id])?;
+        let changes = tx.execute(
             "DELETE FROM conversations WHERE id = ?1",
             params![conversation_id],
         )?;
+        tx.commit()?;
 
         Ok(changes > 0)
     }
@@ -364,6 +734,184 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_conversation_settings() -> SqliteResult<()> {
+        let temp_dir = std::env::temp_dir();
+        let db_path = temp_dir.join("test_conversation_settings.db");
+        let _ = fs::remove_file(&db_path);
+
+        let storage = SqliteStorage::new(&db_path)?;
+        let conv_id = storage.insert_conversation("Settings Test")?;
+
+        // New conversations follow the defaults
+        let settings = storage.get_conversation_settings(&conv_id)?;
+        assert_eq!(settings, Some(ConversationSettings::default()));
+
+        let settings = ConversationSettings {
+            persona: Some("Rust reviewer".to_string()),
+            profile: Some("claude".to_string()),
+            temperature: Some(0.25),
+            max_tokens: Some(2048),
+            system_prompt: Some("Answer in French.".to_string()),
+        };
+        assert!(storage.update_conversation_settings(&conv_id, &settings)?);
+        assert_eq!(storage.get_conversation_settings(&conv_id)?, Some(settings));
+        assert_eq!(storage.get_conversation_settings("missing")?, None);
+
+        // Reopening an existing database keeps the columns and their values
+        drop(storage);
+        let storage = SqliteStorage::new(&db_path)?;
+        let reopened = storage.get_conversation_settings(&conv_id)?.unwrap();
+        assert_eq!(reopened.profile.as_deref(), Some("claude"));
+
+        let _ = fs::remove_file(&db_path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_conversation_organization() -> SqliteResult<()> {
+        let temp_dir = std::env::temp_dir();
+        let db_path = temp_dir.join("test_conversation_organization.db");
+        let _ = fs::remove_file(&db_path);
+
+        let storage = SqliteStorage::new(&db_path)?;
+        let conv_id = storage.insert_conversation("Organized")?;
+        storage.insert_message(&conv_id, "user", "Where are my notes?", None)?;
+
+        let conversation = storage.get_conversation(&conv_id)?.unwrap();
+        assert_eq!(conversation.folder, None);
+        assert!(conversation.tags.is_empty());
+        assert!(!conversation.pinned && !conversation.archived);
+
+        assert!(storage.update_folder(&conv_id, Some("Work"))?);
+        assert!(storage.update_pinned(&conv_id, true)?);
+        assert!(storage.update_archived(&conv_id, true)?);
+        storage.update_tags(&conv_id, &["rust".to_string(), "notes".to_string()])?;
+        let conversation = storage.list_conversations()?.pop().unwrap();
+        assert_eq!(conversation.folder.as_deref(), Some("Work"));
+        assert_eq!(conversation.tags, vec!["notes".to_string(), "rust".to_string()]);
+        assert!(conversation.pinned && conversation.archived);
+
+        storage.update_tags(&conv_id, &["rust".to_string()])?;
+        assert_eq!(storage.get_conversation(&conv_id)?.unwrap().tags, vec!["rust".to_string()]);
+
+        // Deleting takes the messages and tags along
+        assert!(storage.delete_conversation(&conv_id)?);
+        assert!(storage.load_conversation(&conv_id)?.is_empty());
+        assert!(storage.search_history("notes", 10)?.is_empty());
+
+        let _ = fs::remove_file(&db_path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_filters_and_snippets() -> SqliteResult<()> {
+        let temp_dir = std::env::temp_dir();
+        let db_path = temp_dir.join("test_search_filters.db");
+        let _ = fs::remove_file(&db_path);
+
+        let storage = SqliteStorage::new(&db_path)?;
+        let rust = storage.insert_conversation("Rust")?;
+        storage.insert_message(&rust, "user", "How does the borrow checker work?", None)?;
+        storage.insert_message(&rust, "assistant", "The borrow checker tracks lifetimes.", None)?;
+        storage.update_tags(&rust, &["rust".to_string()])?;
+        let other = storage.insert_conversation("Other")?;
+        storage.insert_message(&other, "user", "Is a borrow checker useful in C?", None)?;
+
+        // Operators and stray quotes are searched as text instead of failing
+        assert!(storage.search_history("\"borrow AND OR (", 10).is_ok());
+
+        let results = storage.search(&SearchQuery::parse("borrow role:assistant"), false, 10, 0)?;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].position, 1);
+        assert!(results[0].highlights().contains(&("borrow", true)));
+
+        let results = storage.search(&SearchQuery::parse("borrow tag:rust"), false, 10, 0)?;
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|result| result.conversation_id == rust));
+
+        // Prefix matching and pages
+        let first = storage.search(&SearchQuery::parse("borr"), true, 2, 0)?;
+        let rest = storage.search(&SearchQuery::parse("borr"), true, 2, 2)?;
+        assert_eq!((first.len(), rest.len()), (2, 1));
+
+        // Filters alone list messages newest first
+        let results = storage.search(&SearchQuery::parse("tag:rust role:user"), false, 10, 0)?;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].snippet, "How does the borrow checker work?");
+
+        // Exclusions alone list every message without the excluded words
+        let results = storage.search(&SearchQuery::parse("-lifetimes -useful"), false, 10, 0)?;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].snippet, "How does the borrow checker work?");
+
+        let _ = fs::remove_file(&db_path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_conversation_activity() -> SqliteResult<()> {
+        let temp_dir = std::env::temp_dir();
+        let db_path = temp_dir.join("test_conversation_activity.db");
+        let _ = fs::remove_file(&db_path);
+
+        let storage = SqliteStorage::new(&db_path)?;
+        let older = storage.insert_conversation("Older")?;
+        let newer = storage.insert_conversation("Newer")?;
+        storage.connection().execute("UPDATE conversations SET created_at = 100, updated_at = 100 WHERE id = ?1", params![older])?;
+        storage.connection().execute("UPDATE conversations SET created_at = 200, updated_at = 200 WHERE id = ?1", params![newer])?;
+        assert_eq!(storage.list_conversations()?[0].id, newer);
+
+        storage.insert_message(&older, "user", "Back to this one", None)?;
+        storage.insert_reply(&older, "Welcome back", "gpt-4o")?;
+        storage.insert_message(&older, "notice", "Switched to claude (claude-sonnet)", None)?;
+
+        // New activity moves the conversation to the top
+        let conversations = storage.list_conversations()?;
+        assert_eq!(conversations[0].id, older);
+        let older = &conversations[0];
+        assert!(older.updated_at > 200);
+        assert_eq!(older.message_count, 2);
+        assert_eq!(older.last_model.as_deref(), Some("gpt-4o"));
+        assert_eq!(older.last_snippet.as_deref(), Some("Welcome back"));
+        assert_eq!(conversations[1].message_count, 0);
+
+        let _ = fs::remove_file(&db_path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_message_pages() -> SqliteResult<()> {
+        let temp_dir = std::env::temp_dir();
+        let db_path = temp_dir.join("test_message_pages.db");
+        let _ = fs::remove_file(&db_path);
+
+        let storage = SqliteStorage::new(&db_path)?;
+        let conv_id = storage.insert_conversation("Long")?;
+        for i in 0..5 {
+            storage.insert_message(&conv_id, "user", &format!("message {}", i), None)?;
+        }
+
+        let latest = storage.load_messages_before(&conv_id, None, 2)?;
+        let contents: Vec<&str> = latest.iter().map(|msg| msg.content.as_str()).collect();
+        assert_eq!(contents, vec!["message 3", "message 4"]);
+        assert!(storage.has_messages_before(&conv_id, latest[0].id)?);
+
+        let older = storage.load_messages_before(&conv_id, Some(latest[0].id), 10)?;
+        assert_eq!(older.len(), 3);
+        assert_eq!(older[0].content, "message 0");
+        assert!(!storage.has_messages_before(&conv_id, older[0].id)?);
+
+        let from = storage.load_messages_from(&conv_id, older[1].id, 2)?;
+        let contents: Vec<&str> = from.iter().map(|msg| msg.content.as_str()).collect();
+        assert_eq!(contents, vec!["message 1", "message 2"]);
+        assert!(storage.has_messages_after(&conv_id, from[1].id)?);
+        assert!(!storage.has_messages_after(&conv_id, latest[1].id)?);
+
+        let _ = fs::remove_file(&db_path);
+        Ok(())
+    }
+
     #[test]
     fn test_embedding_storage() -> SqliteResult<()> {
         let temp_dir = std::env::temp_dir();
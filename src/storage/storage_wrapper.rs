@@ -1,21 +1,64 @@
 use chrono::{DateTime, Utc};
 use rusqlite::Result as SqliteResult;
 use std::path::Path;
+use tokio::sync::broadcast;
 use uuid::Uuid;
 
-use super::sqlite_storage_simple::SqliteStorage;
-use super::conversation_storage::{Conversation as FileConversation, StoredMessage, Turn};
+use super::sqlite_storage_simple::{Message, SqliteStorage};
+use super::conversation_storage::{Conversation as FileConversation, ConversationSettings, StoredMessage, Turn};
+
+/// Changes written through `Storage`, broadcast to subscribers (the UI)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageEvent {
+    ConversationCreated { id: Uuid },
+    /// Title, settings, labels or messages changed
+    ConversationChanged { id: Uuid },
+    ConversationDeleted { id: Uuid },
+}
+
+/// A slice of a conversation's messages, oldest first
+#[derive(Debug, Clone, Default)]
+pub struct MessagePage {
+    pub messages: Vec<StoredMessage>,
+    /// Row id of the first message, to load the page before it
+    pub first_id: Option<i64>,
+    /// Row id of the last message, to load the page after it
+    pub last_id: Option<i64>,
+    /// Whether there are older messages than this page
+    pub has_older: bool,
+    /// Whether there are newer messages than this page
+    pub has_newer: bool,
+}
 
 /// Wrapper that provides compatibility with the existing file-based storage API
 pub struct Storage {
     sqlite: SqliteStorage,
+    events: broadcast::Sender<StorageEvent>,
 }
 
 impl Storage {
     /// Create a new storage instance with SQLite backend
     pub fn new<P: AsRef<Path>>(db_path: P) -> SqliteResult<Self> {
         let sqlite = SqliteStorage::new(db_path)?;
-        Ok(Self { sqlite })
+        let (events, _) = broadcast::channel(64);
+        Ok(Self { sqlite, events })
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<StorageEvent> {
+        self.events.subscribe()
+    }
+
+    fn emit(&self, event: StorageEvent) {
+        // No receivers is fine: nobody is interested yet
+        let _ = self.events.send(event);
+    }
+
+    /// Report a change when a write succeeded
+    fn changed<T>(&self, id: &Uuid, result: SqliteResult<T>) -> SqliteResult<T> {
+        if result.is_ok() {
+            self.emit(StorageEvent::ConversationChanged { id: *id });
+        }
+        result
     }
 
     /// Create a new storage instance with default database path
@@ -34,8 +77,10 @@ impl Storage {
     /// Create a new conversation
     pub fn create_conversation(&self, title: String) -> SqliteResult<Uuid> {
         let id_str = self.sqlite.insert_conversation(&title)?;
-        Uuid::parse_str(&id_str)
-            .map_err(|e| rusqlite::Error::InvalidParameterName(format!("Invalid UUID: {}", e)))
+        let id = Uuid::parse_str(&id_str)
+            .map_err(|e| rusqlite::Error::InvalidParameterName(format!("Invalid UUID: {}", e)))?;
+        self.emit(StorageEvent::ConversationCreated { id });
+        Ok(id)
     }
 
     /// Get a conversation by ID
@@ -43,23 +88,18 @@ impl Storage {
         let id_str = id.to_string();
         if let Some(db_conv) = self.sqlite.get_conversation(&id_str)? {
             let messages = self.sqlite.load_conversation(&id_str)?;
+            let settings = self.sqlite.get_conversation_settings(&id_str)?.unwrap_or_default();
             
-            let stored_messages: Vec<StoredMessage> = messages.into_iter().map(|msg| {
-                StoredMessage {
-                    id: Uuid::parse_str(&msg.id.to_string()).unwrap_or_else(|_| Uuid::new_v4()),
-                    role: msg.role,
-                    content: msg.content,
-                    timestamp: DateTime::from_timestamp(msg.created_at, 0).unwrap_or_else(Utc::now),
-                }
-            }).collect();
+            let stored_messages: Vec<StoredMessage> = messages.into_iter().map(Self::stored_message).collect();
 
             let conversation = FileConversation {
                 id: *id,
                 title: db_conv.title,
                 created_at: DateTime::from_timestamp(db_conv.created_at, 0).unwrap_or_else(Utc::now),
-                updated_at: DateTime::from_timestamp(db_conv.created_at, 0).unwrap_or_else(Utc::now), // SQLite doesn't track updated_at yet
+                updated_at: DateTime::from_timestamp(db_conv.updated_at, 0).unwrap_or_else(Utc::now),
                 messages: stored_messages,
                 turns: Vec::new(), // Turns are not yet migrated to SQLite
+                settings,
             };
 
             Ok(Some(conversation))
@@ -75,50 +115,96 @@ impl Storage {
         None
     }
 
-    /// List all conversations
-    pub fn list_conversations(&self) -> SqliteResult<Vec<FileConversation>> {
-        let db_conversations = self.sqlite.list_conversations()?;
-        let mut conversations = Vec::new();
+    /// Model settings of a conversation, without loading its messages
+    pub fn get_conversation_settings(&self, id: &Uuid) -> SqliteResult<Option<ConversationSettings>> {
+        self.sqlite.get_conversation_settings(&id.to_string())
+    }
 
-        for db_conv in db_conversations {
-            let id = Uuid::parse_str(&db_conv.id)
-                .map_err(|e| rusqlite::Error::InvalidParameterName(format!("Invalid UUID: {}", e)))?;
-            
-            let messages = self.sqlite.load_conversation(&db_conv.id)?;
-            let stored_messages: Vec<StoredMessage> = messages.into_iter().map(|msg| {
-                StoredMessage {
-                    id: Uuid::parse_str(&msg.id.to_string()).unwrap_or_else(|_| Uuid::new_v4()),
-                    role: msg.role,
-                    content: msg.content,
-                    timestamp: DateTime::from_timestamp(msg.created_at, 0).unwrap_or_else(Utc::now),
-                }
-            }).collect();
+    /// The newest `limit` messages before the message `before_id`, or the newest of the conversation
+    pub fn latest_messages(&self, id: &Uuid, before_id: Option<i64>, limit: usize) -> SqliteResult<MessagePage> {
+        let id_str = id.to_string();
+        let messages = self.sqlite.load_messages_before(&id_str, before_id, limit)?;
+        self.message_page(&id_str, messages)
+    }
 
-            let conversation = FileConversation {
-                id,
-                title: db_conv.title,
-                created_at: DateTime::from_timestamp(db_conv.created_at, 0).unwrap_or_else(Utc::now),
-                updated_at: DateTime::from_timestamp(db_conv.created_at, 0).unwrap_or_else(Utc::now),
-                messages: stored_messages,
-                turns: Vec::new(), // Turns are not yet migrated to SQLite
-            };
+    /// Up to `limit` messages from the message `from_id` on
+    pub fn messages_from(&self, id: &Uuid, from_id: i64, limit: usize) -> SqliteResult<MessagePage> {
+        let id_str = id.to_string();
+        let messages = self.sqlite.load_messages_from(&id_str, from_id, limit)?;
+        self.message_page(&id_str, messages)
+    }
 
-            conversations.push(conversation);
-        }
+    fn message_page(&self, id_str: &str, messages: Vec<Message>) -> SqliteResult<MessagePage> {
+        let first_id = messages.first().map(|msg| msg.id);
+        let last_id = messages.last().map(|msg| msg.id);
+        let has_older = match first_id {
+            Some(first_id) => self.sqlite.has_messages_before(id_str, first_id)?,
+            None => false,
+        };
+        let has_newer = match last_id {
+            Some(last_id) => self.sqlite.has_messages_after(id_str, last_id)?,
+            None => false,
+        };
+        Ok(MessagePage {
+            messages: messages.into_iter().map(Self::stored_message).collect(),
+            first_id,
+            last_id,
+            has_older,
+            has_newer,
+        })
+    }
 
-        Ok(conversations)
+    fn stored_message(msg: Message) -> StoredMessage {
+        StoredMessage {
+            id: Uuid::parse_str(&msg.id.to_string()).unwrap_or_else(|_| Uuid::new_v4()),
+            role: msg.role,
+            content: msg.content,
+            timestamp: DateTime::from_timestamp(msg.created_at, 0).unwrap_or_else(Utc::now),
+        }
     }
 
     /// Update conversation title
     pub fn update_conversation_title(&self, id: &Uuid, title: String) -> SqliteResult<bool> {
         let id_str = id.to_string();
-        self.sqlite.update_title(&id_str, &title)
+        self.changed(id, self.sqlite.update_title(&id_str, &title))
+    }
+
+    /// Remember the model profile and parameter overrides of a conversation
+    pub fn update_conversation_settings(&self, id: &Uuid, settings: &ConversationSettings) -> SqliteResult<bool> {
+        let id_str = id.to_string();
+        self.changed(id, self.sqlite.update_conversation_settings(&id_str, settings))
+    }
+
+    /// Move a conversation into a folder; None leaves it unfiled
+    pub fn set_conversation_folder(&self, id: &Uuid, folder: Option<&str>) -> SqliteResult<bool> {
+        self.changed(id, self.sqlite.update_folder(&id.to_string(), folder))
+    }
+
+    /// Replace the tags of a conversation
+    pub fn set_conversation_tags(&self, id: &Uuid, tags: &[String]) -> SqliteResult<()> {
+        self.changed(id, self.sqlite.update_tags(&id.to_string(), tags))
+    }
+
+    /// Pin a conversation to the top of the history
+    pub fn set_conversation_pinned(&self, id: &Uuid, pinned: bool) -> SqliteResult<bool> {
+        self.changed(id, self.sqlite.update_pinned(&id.to_string(), pinned))
+    }
+
+    /// Move a conversation to or out of the archive
+    pub fn set_conversation_archived(&self, id: &Uuid, archived: bool) -> SqliteResult<bool> {
+        self.changed(id, self.sqlite.update_archived(&id.to_string(), archived))
     }
 
     /// Add a message to a conversation
     pub fn add_message_to_conversation(&self, conversation_id: &Uuid, role: String, content: String) -> SqliteResult<()> {
         let id_str = conversation_id.to_string();
-        self.sqlite.insert_message(&id_str, &role, &content, None)
+        self.changed(conversation_id, self.sqlite.insert_message(&id_str, &role, &content, None))
+    }
+
+    /// Add an assistant reply, remembering the model that wrote it
+    pub fn add_reply_to_conversation(&self, conversation_id: &Uuid, content: String, model: &str) -> SqliteResult<()> {
+        let id_str = conversation_id.to_string();
+        self.changed(conversation_id, self.sqlite.insert_reply(&id_str, &content, model))
     }
 
     /// Add a turn to a conversation (not yet implemented in SQLite)
@@ -130,7 +216,11 @@ impl Storage {
     /// Delete a conversation
     pub fn delete_conversation(&self, conversation_id: &Uuid) -> SqliteResult<bool> {
         let id_str = conversation_id.to_string();
-        self.sqlite.delete_conversation(&id_str)
+        let deleted = self.sqlite.delete_conversation(&id_str)?;
+        if deleted {
+            self.emit(StorageEvent::ConversationDeleted { id: *conversation_id });
+        }
+        Ok(deleted)
     }
 
     /// Search conversation history
@@ -138,24 +228,42 @@ impl Storage {
         self.sqlite.search_history(query, limit)
     }
 
-    /// List conversations from index (compatibility method)
+    /// One page of History search results for a parsed query; `prefix` lets the last word match as a prefix
+    pub fn search(&self, query: &super::search_query::SearchQuery, prefix: bool, limit: usize, offset: usize) -> SqliteResult<Vec<super::sqlite_storage_simple::Snippet>> {
+        self.sqlite.search(query, prefix, limit, offset)
+    }
+
+    /// Summaries of all conversations (title, activity and labels, no messages), most recently active first
     pub fn list_conversations_from_index(&self) -> SqliteResult<Vec<super::conversation_storage::ConversationIndex>> {
-        let db_conversations = self.sqlite.list_conversations()?;
-        let mut index = Vec::new();
-        
-        for db_conv in db_conversations {
-            let id = Uuid::parse_str(&db_conv.id)
-                .map_err(|e| rusqlite::Error::InvalidParameterName(format!("Invalid UUID: {}", e)))?;
-            
-            index.push(super::conversation_storage::ConversationIndex {
-                id,
-                title: db_conv.title,
-                created_at: DateTime::from_timestamp(db_conv.created_at, 0).unwrap_or_else(Utc::now),
-                updated_at: DateTime::from_timestamp(db_conv.created_at, 0).unwrap_or_else(Utc::now),
-            });
-        }
-        
-        Ok(index)
+        self.sqlite.list_conversations()?
+            .into_iter()
+            .map(Self::index_entry)
+            .collect()
+    }
+
+    /// Title, activity and labels of one conversation without loading its messages
+    pub fn conversation_index(&self, id: &Uuid) -> SqliteResult<Option<super::conversation_storage::ConversationIndex>> {
+        self.sqlite.get_conversation(&id.to_string())?
+            .map(Self::index_entry)
+            .transpose()
+    }
+
+    fn index_entry(db_conv: super::sqlite_storage_simple::Conversation) -> SqliteResult<super::conversation_storage::ConversationIndex> {
+        let id = Uuid::parse_str(&db_conv.id)
+            .map_err(|e| rusqlite::Error::InvalidParameterName(format!("Invalid UUID: {}", e)))?;
+        Ok(super::conversation_storage::ConversationIndex {
+            id,
+            title: db_conv.title,
+            created_at: DateTime::from_timestamp(db_conv.created_at, 0).unwrap_or_else(Utc::now),
+            updated_at: DateTime::from_timestamp(db_conv.updated_at, 0).unwrap_or_else(Utc::now),
+            folder: db_conv.folder,
+            tags: db_conv.tags,
+            pinned: db_conv.pinned,
+            archived: db_conv.archived,
+            message_count: db_conv.message_count,
+            last_model: db_conv.last_model,
+            last_snippet: db_conv.last_snippet,
+        })
     }
 }
 
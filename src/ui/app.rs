@@ -10,13 +10,13 @@ use tokio::sync::RwLock;
 use uuid::Uuid;
 
 use crate::{
-    config::{AppConfig, LlmProfile},
-    storage::Storage,
+    config::{AppConfig, LlmProfile, Persona, ThemePreference},
+    storage::{conversation_storage::{ConversationIndex, ConversationSettings}, search_query::SearchQuery, MessagePage, Storage, StorageEvent},
     llm::LlmClient,
     mcp::MCPServerRegistry,
     prompts::PromptManager,
     ui::context::ContextPage,
-    ui::pages::settings::{SimpleSettingsPage, SimpleSettingsMessage},
+    ui::pages::settings::{ConnectionStatus, SimpleSettingsPage, SimpleSettingsMessage},
     ui::widgets::{ToolCallWidget, ToolCallMessage},
     ui::dialogs::{DialogAction, DialogPage},
 };
@@ -34,24 +34,40 @@ pub enum Message {
     RemoveFile(String), // file path
     FileChooserCancelled,
     FileChooserError(Arc<file_chooser::Error>),
+    // Prompt templates
+    ShowPromptTemplates,
+    SelectPromptTemplate(usize),
+    BackToPromptTemplates,
+    PromptTemplateFieldChanged(usize, String),
+    InsertPromptTemplate,
     NavigateTo(NavigationPage),
     SelectConversation(Uuid),
     DeleteConversation(Uuid),
     NewConversation,
     AgentUpdate(AgentUpdate),
+    GenerationPrepared(Uuid, Option<Uuid>, Vec<crate::llm::Message>), // request, conversation, messages with the system prompt rendered
     ToolCallStarted(String, String), // tool_name, parameters
     ToolCallCompleted(String, String), // tool_name, result
     ToolCallError(String, String), // tool_name, error
     ToolCallWidgetMessage(usize, ToolCallMessage), // index, message
+    ToggleReasoning(usize), // message index
     ScrollToBottom,
+    ChatScrolled(bool), // whether the chat view now ends at the last message
     // Menu actions
     ShowAbout,
     OpenSettings,
     Quit,
     CloseAbout,
     OpenUrl(String),
+    // Conversation settings
+    SelectPersona(usize), // 0 = no persona, then personas by name
+    ChangeConversationProfile(usize),
+    ShowConversationSettings,
+    ConversationTemperatureChanged(String),
+    ConversationMaxTokensChanged(String),
+    ConversationSystemPromptChanged(String),
+    ApplyConversationSettings,
     // Settings actions
-    ChangeDefaultProfile(usize),
     SaveSettings,
     ResetSettings,
     // New Settings page messages
@@ -60,11 +76,29 @@ pub enum Message {
     DialogAction(DialogAction),
     ShowMessageDialog(String),
     // MCP actions
-    MCPToolsUpdated(Vec<crate::llm::ToolDefinition>),
+    MCPToolsUpdated(crate::mcp::registry::MCPRegistrySnapshot),
     RefreshMCPTools,
     // Tool toggle actions
     ToggleAllTools(bool), // true = enable all, false = disable all
     ToggleTool(String, bool), // tool_name, enabled
+    ToggleServer(String, bool), // server_name, enabled
+    // Hot reload of configuration files
+    ConfigReloaded(AppConfig),
+    ApplyPendingConfig,
+    PromptsReloaded(PromptManager),
+    ConfigFileStatus(String, Option<String>), // file, parse/validation error
+    WindowFocusChanged(bool),
+    NotificationClicked(Uuid), // conversation the notification was about
+    // Keyboard shortcuts and command palette
+    Key(keyboard::Modifiers, keyboard::Key),
+    OpenCommandPalette,
+    CloseCommandPalette,
+    CommandPaletteQueryChanged(String),
+    RunPaletteCommand(usize), // index into the filtered entries
+    RunSelectedPaletteCommand,
+    StartPersonaChat(String),
+    ExportConversation,
+    ConversationExported(Result<String, String>), // written path or error
     ShowToolsContext,
     HideToolsContext,
     // Markdown link handling
@@ -72,6 +106,27 @@ pub enum Message {
     // Search functionality
     SearchChanged(String),
     SearchResults(Vec<crate::storage::sqlite_storage_simple::Snippet>),
+    LoadMoreSearchResults,
+    OpenSearchResult(Uuid, i64), // conversation and id of the matched message
+    LoadOlderMessages,
+    LoadNewerMessages,
+    StorageChanged(Option<StorageEvent>), // None: events were missed, reload everything
+    // History organization
+    HistoryFolderFilterSelected(usize), // 0 = all folders, 1 = unfiled, then folders by name
+    HistoryTagFilterSelected(usize), // 0 = all tags, then tags by name
+    HistoryShowArchivedToggled(bool),
+    ToggleConversationSelected(Uuid, bool),
+    SelectAllConversations,
+    ClearConversationSelection,
+    PinConversation(Uuid, bool),
+    EditConversationLabels(Uuid),
+    ConversationFolderInput(String),
+    ConversationTagsInput(String),
+    SaveConversationLabels,
+    CancelConversationLabels,
+    ArchiveSelectedConversations(bool),
+    DeleteSelectedConversations,
+    ExportSelectedConversations,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -84,6 +139,7 @@ pub enum NavigationPage {
 
 // ContextPage moved to ui::context module for better organization
 
+/// Actions reachable from menus, shortcuts and the command palette; see `ui::commands::COMMANDS`
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum MenuAction {
     About,
@@ -91,6 +147,18 @@ pub enum MenuAction {
     Settings,
     Quit,
     SendMessage,
+    StopMessage,
+    AttachFile,
+    PromptTemplates,
+    ConversationSettings,
+    ExportConversation,
+    CommandPalette,
+    ShowChat,
+    ShowHistory,
+    ShowMcpConfig,
+    ShowTools,
+    EnableAllTools,
+    DisableAllTools,
 }
 
 impl menu::Action for MenuAction {
@@ -103,6 +171,18 @@ impl menu::Action for MenuAction {
             MenuAction::Settings => Message::OpenSettings,
             MenuAction::Quit => Message::Quit,
             MenuAction::SendMessage => Message::SendMessage,
+            MenuAction::StopMessage => Message::StopMessage,
+            MenuAction::AttachFile => Message::AttachFile,
+            MenuAction::PromptTemplates => Message::ShowPromptTemplates,
+            MenuAction::ConversationSettings => Message::ShowConversationSettings,
+            MenuAction::ExportConversation => Message::ExportConversation,
+            MenuAction::CommandPalette => Message::OpenCommandPalette,
+            MenuAction::ShowChat => Message::NavigateTo(NavigationPage::Chat),
+            MenuAction::ShowHistory => Message::NavigateTo(NavigationPage::History),
+            MenuAction::ShowMcpConfig => Message::NavigateTo(NavigationPage::MCPConfig),
+            MenuAction::ShowTools => Message::ShowToolsContext,
+            MenuAction::EnableAllTools => Message::ToggleAllTools(true),
+            MenuAction::DisableAllTools => Message::ToggleAllTools(false),
         }
     }
 }
@@ -149,18 +229,39 @@ pub struct CosmicLlmApp {
     input_id: cosmic::widget::Id,
     current_page: NavigationPage,
     current_conversation_id: Option<Uuid>,
+    // Profile and parameter overrides of the open conversation
+    conversation_settings: ConversationSettings,
+    // Edit buffers of the conversation settings drawer
+    temperature_input: String,
+    max_tokens_input: String,
+    system_prompt_input: String,
     mcp_registry: Arc<RwLock<MCPServerRegistry>>,
     llm_client: Arc<dyn LlmClient>,
     is_streaming: bool,
+    // Request whose system prompt is still rendering; sends wait until it streams
+    preparing_generation: Option<Uuid>,
     current_streaming_id: Option<Uuid>,
     active_tool_calls: Vec<ToolCallInfo>,
     // Anchors tool calls under the AI message that executed them
     current_ai_message_index: Option<usize>,
     archived_tool_calls: Vec<AnchoredToolCall>,
     expanded_tool_calls: std::collections::HashSet<usize>,
+    // Messages whose thinking section is open
+    expanded_reasoning: std::collections::HashSet<usize>,
     scrollable_id: cosmic::widget::Id,
+    // New output only follows the chat while the user has not scrolled up
+    chat_at_bottom: bool,
+    // The open conversation was started with history saving off and is never written to storage
+    off_the_record: bool,
+    // Notifications are only shown while the window is in the background
+    window_focused: bool,
     key_binds: std::collections::HashMap<menu::KeyBind, MenuAction>,
+    // Open command palette
+    command_palette: Option<CommandPalette>,
+    command_palette_input_id: widget::Id,
     settings_changed: bool,
+    // config.toml as changed on disk while there were unsaved settings
+    pending_config: Option<AppConfig>,
     title_sender: Option<tokio::sync::mpsc::UnboundedSender<(Uuid, String)>>,
     settings_page: SimpleSettingsPage,
     context_page: ContextPage,
@@ -178,19 +279,58 @@ pub struct CosmicLlmApp {
     available_mcp_tools: Vec<crate::llm::ToolDefinition>,
     // Tool enable/disable state (tool_name -> enabled)
     tool_states: std::collections::HashMap<String, bool>,
+    // Server enable/disable state (server_name -> enabled)
+    server_states: std::collections::HashMap<String, bool>,
+    // Qualified tool name -> owning MCP server
+    tool_servers: std::collections::HashMap<String, String>,
+    // Bare tool names exposed by more than one server
+    tool_conflicts: Vec<crate::mcp::registry::ToolConflict>,
     // Show tools context panel
     show_tools_context: bool,
     // Store last user message for retry functionality
     last_user_message: Option<String>,
     // Store attached files
     attached_files: Vec<String>,
+    // Template picked in the templates drawer and its argument values, in field order
+    selected_template: Option<usize>,
+    template_values: Vec<(String, String)>,
     // Store current error message
     current_error: Option<String>,
+    // Configuration files that failed to load (file -> error)
+    config_errors: std::collections::BTreeMap<String, String>,
     // Store prepared LLM messages with attachments for the current request
     pending_llm_messages: Option<Vec<crate::llm::Message>>,
+    // Cancels the agent loop, HTTP request and tool calls of the current generation
+    cancel_token: Option<tokio_util::sync::CancellationToken>,
     // Search functionality
     search_query: String,
     search_results: Vec<crate::storage::sqlite_storage_simple::Snippet>,
+    search_has_more: bool,
+    // Message opened from a search result
+    highlighted_message: Option<usize>,
+    // Only one page of a long conversation is loaded at a time
+    first_loaded_message_id: Option<i64>,
+    last_loaded_message_id: Option<i64>,
+    has_older_messages: bool,
+    has_newer_messages: bool,
+    // History rows, kept in sync with storage events instead of queried on every redraw
+    history_rows: Vec<ConversationIndex>,
+    // History filters and the conversations picked for bulk actions
+    history_folder_filter: FolderFilter,
+    history_tag_filter: Option<String>,
+    history_show_archived: bool,
+    history_selection: std::collections::HashSet<Uuid>,
+    // Conversation whose folder and comma-separated tags are being edited
+    history_labels_edit: Option<(Uuid, String, String)>,
+}
+
+/// Folder shown on the History page
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum FolderFilter {
+    #[default]
+    All,
+    Unfiled,
+    Folder(String),
 }
 
 #[derive(Debug, Clone)]
@@ -198,6 +338,10 @@ pub struct ChatMessage {
     pub content: String,
     pub is_user: bool,
     pub is_error: bool,
+    /// Event shown between messages, such as a model switch; never sent to the model
+    pub is_notice: bool,
+    /// Model thinking shown in a collapsible section above the answer
+    pub reasoning: String,
 }
 
 #[derive(Debug, Clone)]
@@ -223,6 +367,148 @@ pub struct AnchoredToolCall {
     pub tool_call: ToolCallInfo,
 }
 
+/// Command palette opened with Ctrl+K
+#[derive(Debug, Clone)]
+pub struct CommandPalette {
+    pub query: String,
+    /// Highlighted row among the filtered entries
+    pub selected: usize,
+    /// Everything the palette can run, collected when it opened
+    pub entries: Vec<crate::ui::commands::PaletteEntry>,
+}
+
+/// Download a model to an Ollama server, reporting progress to the profile editor
+fn pull_ollama_model(profile: LlmProfile) -> app::Task<Message> {
+    use cosmic::iced_futures::stream;
+    use cosmic::iced_futures::futures::SinkExt;
+    
+    let progress = stream::channel(16, move |mut output| async move {
+        let result = pull_with_progress(profile, &mut output).await;
+        let _ = output.send(Message::SettingsMessage(SimpleSettingsMessage::ModelPulled(result))).await;
+    });
+    cosmic::Task::run(progress, cosmic::Action::App)
+}
+
+#[cfg(feature = "ollama")]
+async fn pull_with_progress(
+    profile: LlmProfile,
+    output: &mut cosmic::iced_futures::futures::channel::mpsc::Sender<Message>,
+) -> Result<(), String> {
+    use cosmic::iced_futures::futures::{SinkExt, StreamExt};
+    
+    let model = profile.model.clone();
+    let client = crate::llm::ollama::OllamaClient::new(profile);
+    let mut progress = client.pull_model(&model).await.map_err(|e| e.to_string())?;
+    while let Some(line) = progress.next().await {
+        let line = line.map_err(|e| e.to_string())?;
+        let _ = output.send(Message::SettingsMessage(SimpleSettingsMessage::PullProgress(line.summary()))).await;
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "ollama"))]
+async fn pull_with_progress(
+    _profile: LlmProfile,
+    _output: &mut cosmic::iced_futures::futures::channel::mpsc::Sender<Message>,
+) -> Result<(), String> {
+    Err("This build has no Ollama support".to_string())
+}
+
+/// History search results loaded at a time
+const SEARCH_PAGE_SIZE: usize = 20;
+
+/// Messages of a stored conversation loaded at a time, newest first
+const MESSAGE_PAGE_SIZE: usize = 100;
+
+fn chat_message(msg: &crate::storage::conversation_storage::StoredMessage) -> ChatMessage {
+    ChatMessage {
+        content: msg.content.clone(),
+        is_user: msg.role == "user",
+        is_error: false,
+        is_notice: msg.role == "notice",
+        reasoning: String::new(),
+    }
+}
+
+/// Ask where to save a Markdown export and write it there
+fn save_markdown(title: &str, markdown: String) -> app::Task<Message> {
+    let file_name: String = title
+        .chars()
+        .map(|c| if c == '/' || c == '\\' { '-' } else { c })
+        .collect();
+    cosmic::task::future(async move {
+        let dialog = file_chooser::save::Dialog::new()
+            .title("Export Conversation")
+            .file_name(format!("{}.md", file_name));
+        match dialog.save_file().await {
+            Ok(response) => {
+                let Some(path) = response.url().and_then(|url| url.to_file_path().ok()) else {
+                    return Message::FileChooserError(Arc::new(file_chooser::Error::UrlAbsolute));
+                };
+                let result = tokio::fs::write(&path, markdown).await
+                    .map(|_| path.display().to_string())
+                    .map_err(|e| format!("Failed to export conversation: {}", e));
+                Message::ConversationExported(result)
+            }
+            Err(file_chooser::Error::Cancelled) => Message::FileChooserCancelled,
+            Err(why) => Message::FileChooserError(Arc::new(why)),
+        }
+    })
+}
+
+/// Comma-separated tags, trimmed, lowercased and without duplicates
+fn parse_tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = text
+        .split(',')
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+/// Folders in use, by name
+fn history_folders(conversations: &[ConversationIndex]) -> Vec<String> {
+    let folders: std::collections::BTreeSet<String> = conversations.iter().filter_map(|conv| conv.folder.clone()).collect();
+    folders.into_iter().collect()
+}
+
+/// Tags in use, by name
+fn history_tags(conversations: &[ConversationIndex]) -> Vec<String> {
+    let tags: std::collections::BTreeSet<String> = conversations.iter().flat_map(|conv| conv.tags.iter().cloned()).collect();
+    tags.into_iter().collect()
+}
+
+/// Heading of the History group a conversation last active at `updated_at` falls under
+fn history_group(updated_at: chrono::DateTime<chrono::Utc>, today: chrono::NaiveDate) -> &'static str {
+    let days = (today - updated_at.with_timezone(&chrono::Local).date_naive()).num_days();
+    match days {
+        i64::MIN..=0 => "Today",
+        1 => "Yesterday",
+        2..=7 => "Last week",
+        8..=30 => "Last month",
+        _ => "Older",
+    }
+}
+
+/// The libcosmic theme for a theme preference
+fn app_theme(preference: ThemePreference) -> cosmic::Theme {
+    match preference {
+        ThemePreference::System => cosmic::theme::system_preference(),
+        ThemePreference::Dark => {
+            let mut theme = cosmic::theme::system_dark();
+            theme.theme_type.prefer_dark(Some(true));
+            theme
+        }
+        ThemePreference::Light => {
+            let mut theme = cosmic::theme::system_light();
+            theme.theme_type.prefer_dark(Some(false));
+            theme
+        }
+    }
+}
+
 impl CosmicLlmApp {
     pub fn new(core: Core, config: AppConfig, storage: Storage, prompt_manager: PromptManager, mcp_registry: Arc<RwLock<MCPServerRegistry>>, llm_client: Arc<dyn LlmClient>) -> Self {
         // Create title sender channel
@@ -262,17 +548,29 @@ impl CosmicLlmApp {
             input_id: cosmic::widget::Id::unique(),
             current_page: NavigationPage::Chat,
             current_conversation_id: None,
+            conversation_settings: ConversationSettings::default(),
+            temperature_input: String::new(),
+            max_tokens_input: String::new(),
+            system_prompt_input: String::new(),
             mcp_registry,
             llm_client,
             is_streaming: false,
+            preparing_generation: None,
             current_streaming_id: None,
             active_tool_calls: Vec::new(),
             current_ai_message_index: None,
             archived_tool_calls: Vec::new(),
             expanded_tool_calls: std::collections::HashSet::new(),
+            expanded_reasoning: std::collections::HashSet::new(),
             scrollable_id: cosmic::widget::Id::unique(),
-            key_binds: Self::create_key_binds(),
+            chat_at_bottom: true,
+            off_the_record: false,
+            window_focused: true,
+            key_binds: std::collections::HashMap::new(),
+            command_palette: None,
+            command_palette_input_id: widget::Id::unique(),
             settings_changed: false,
+            pending_config: None,
             title_sender: Some(title_sender),
             settings_page: SimpleSettingsPage::new(),
             context_page: ContextPage::About,
@@ -315,60 +613,36 @@ impl CosmicLlmApp {
             dialog_text_input_id: widget::Id::unique(),
             available_mcp_tools: Vec::new(),
             tool_states: std::collections::HashMap::new(),
+            server_states: std::collections::HashMap::new(),
+            tool_servers: std::collections::HashMap::new(),
+            tool_conflicts: Vec::new(),
             show_tools_context: false,
             last_user_message: None,
             attached_files: Vec::new(),
+            selected_template: None,
+            template_values: Vec::new(),
             current_error: None,
+            config_errors: std::collections::BTreeMap::new(),
             pending_llm_messages: None,
+            cancel_token: None,
             search_query: String::new(),
             search_results: Vec::new(),
+            search_has_more: false,
+            highlighted_message: None,
+            first_loaded_message_id: None,
+            last_loaded_message_id: None,
+            has_older_messages: false,
+            has_newer_messages: false,
+            history_rows: Vec::new(),
+            history_folder_filter: FolderFilter::All,
+            history_tag_filter: None,
+            history_show_archived: false,
+            history_selection: std::collections::HashSet::new(),
+            history_labels_edit: None,
         }
     }
     
 
-    fn create_key_binds() -> std::collections::HashMap<menu::KeyBind, MenuAction> {
-        use cosmic::iced::keyboard::Key;
-        use cosmic::widget::menu::key_bind::{KeyBind, Modifier};
-        
-        let mut key_binds = std::collections::HashMap::new();
-        
-        // File menu shortcuts
-        key_binds.insert(
-            KeyBind {
-                modifiers: vec![Modifier::Ctrl],
-                key: Key::Character("n".into()),
-            },
-            MenuAction::NewConversation,
-        );
-        key_binds.insert(
-            KeyBind {
-                modifiers: vec![Modifier::Ctrl],
-                key: Key::Character("q".into()),
-            },
-            MenuAction::Quit,
-        );
-        
-        // View menu shortcuts
-        key_binds.insert(
-            KeyBind {
-                modifiers: vec![Modifier::Ctrl],
-                key: Key::Character(",".into()),
-            },
-            MenuAction::Settings,
-        );
-        
-        // Send message shortcut
-        key_binds.insert(
-            KeyBind {
-                modifiers: vec![Modifier::Ctrl],
-                key: Key::Named(cosmic::iced::keyboard::key::Named::Enter),
-            },
-            MenuAction::SendMessage,
-        );
-        
-        key_binds
-    }
-    
     fn create_streaming_subscription(&self, streaming_id: Option<Uuid>) -> Subscription<Message> {
         use cosmic::iced_futures::stream;
         use cosmic::iced_futures::futures::SinkExt;
@@ -377,50 +651,14 @@ impl CosmicLlmApp {
         // Create a streaming subscription using the channel pattern
         let id = streaming_id.unwrap_or_else(|| uuid::Uuid::new_v4());
         let llm_client = self.llm_client.clone();
-        let prompt_manager = self.prompt_manager.clone();
-        let messages = self.messages.clone();
         let mcp_registry = self.mcp_registry.clone();
-        let pending_messages = self.pending_llm_messages.clone();
+        // Prepared once when the message was sent, system prompt included
+        let llm_messages = self.pending_llm_messages.clone().unwrap_or_default();
+        let agent_policy = self.config.agent_policy(&self.conversation_profile_name());
+        let persona = self.active_persona().cloned();
+        let cancel = self.cancel_token.clone().unwrap_or_default();
         
         Subscription::run_with_id(id, stream::channel(100, move |mut output| async move {
-            // Use prepared messages if available (which includes attachments), otherwise rebuild
-            let llm_messages = if let Some(prepared_messages) = pending_messages {
-                println!("🔍 DEBUG: Using prepared messages with attachments");
-                prepared_messages
-            } else {
-                println!("🔍 DEBUG: Rebuilding messages from history");
-                // Build LLM messages with system prompt
-                let mut llm_messages = Vec::new();
-                
-                // Add system prompt if available
-                if let Some(system_prompt) = prompt_manager.get_system_prompt() {
-                    llm_messages.push(crate::llm::Message::new(
-                        crate::llm::Role::System,
-                        system_prompt.to_string()
-                    ));
-                }
-                
-                // Add conversation history, filtering out placeholder assistant messages
-                for msg in &messages {
-                    let content_trimmed = msg.content.trim();
-                    if !msg.is_user {
-                        // Skip placeholder or empty assistant messages
-                        if content_trimmed.is_empty() || content_trimmed == "🤔 Thinking..." {
-                            continue;
-                        }
-                    }
-
-                    let role = if msg.is_user {
-                        crate::llm::Role::User
-                    } else {
-                        crate::llm::Role::Assistant
-                    };
-                    llm_messages.push(crate::llm::Message::new(role, msg.content.clone()));
-                }
-                
-                llm_messages
-            };
-            
             // Create channel for agent updates
             let (tx_agent, mut rx_agent) = mpsc::unbounded_channel::<AgentUpdate>();
             
@@ -430,12 +668,16 @@ impl CosmicLlmApp {
             let llm_messages_clone = llm_messages.clone();
             
             tokio::spawn(async move {
-                let mut agentic_loop = crate::agentic::loop_engine::AgenticLoop::new(mcp_registry_clone, llm_client_clone);
+                let mut agentic_loop = crate::agentic::loop_engine::AgenticLoop::new(mcp_registry_clone, llm_client_clone, agent_policy, cancel.clone());
+                agentic_loop.persona = persona;
                 
                 match agentic_loop.process_message(llm_messages_clone, Some(tx_agent.clone()), Some(id)).await {
                     Ok(_final_response) => {
                         // Final response is sent via AgentUpdate::EndConversation
                     }
+                    Err(_) if cancel.is_cancelled() => {
+                        // Stopped by the user; the UI already recorded the partial turn
+                    }
                     Err(e) => {
                         // Send error via AgentUpdate - this handles cases where the loop fails completely
                         let _ = tx_agent.send(AgentUpdate::ModelError { 
@@ -452,6 +694,126 @@ impl CosmicLlmApp {
             }
         }))
     }
+    
+    /// Push a fresh registry snapshot to the UI whenever servers or tools change
+    fn create_mcp_events_subscription(&self) -> Subscription<Message> {
+        use cosmic::iced_futures::stream;
+        use cosmic::iced_futures::futures::SinkExt;
+        use tokio::sync::broadcast::error::RecvError;
+        
+        let mcp_registry = self.mcp_registry.clone();
+        
+        Subscription::run_with_id("mcp-registry-events", stream::channel(16, move |mut output| async move {
+            let mut events = mcp_registry.read().await.subscribe();
+            // Pick up whatever was registered before we subscribed
+            let snapshot = mcp_registry.read().await.snapshot();
+            let _ = output.send(Message::MCPToolsUpdated(snapshot)).await;
+            
+            loop {
+                match events.recv().await {
+                    Ok(crate::mcp::registry::MCPRegistryEvent::ConfigReloaded { error }) => {
+                        let _ = output.send(Message::ConfigFileStatus("mcp_config.json".to_string(), error)).await;
+                    }
+                    Ok(event) => {
+                        log::debug!("MCP registry event: {:?}", event);
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        log::debug!("Skipped {} MCP registry events", skipped);
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+                let snapshot = mcp_registry.read().await.snapshot();
+                let _ = output.send(Message::MCPToolsUpdated(snapshot)).await;
+            }
+        }))
+    }
+    
+    /// Keep the cached History rows in step with writes to storage
+    fn create_storage_events_subscription(&self) -> Subscription<Message> {
+        use cosmic::iced_futures::stream;
+        use cosmic::iced_futures::futures::SinkExt;
+        use tokio::sync::broadcast::error::RecvError;
+        
+        let mut events = self.storage.subscribe();
+        
+        Subscription::run_with_id("storage-events", stream::channel(16, move |mut output| async move {
+            // Pick up whatever changed before we subscribed
+            let _ = output.send(Message::StorageChanged(None)).await;
+            
+            loop {
+                match events.recv().await {
+                    Ok(event) => {
+                        let _ = output.send(Message::StorageChanged(Some(event))).await;
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        log::debug!("Skipped {} storage events", skipped);
+                        let _ = output.send(Message::StorageChanged(None)).await;
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        }))
+    }
+    
+    /// Watch config.toml and the prompt files, reloading them when they change on disk
+    fn create_config_watch_subscription(&self) -> Subscription<Message> {
+        use cosmic::iced_futures::stream;
+        use cosmic::iced_futures::futures::SinkExt;
+        use crate::config::watcher::{self, FileWatcher};
+        
+        let mut prompts = self.config.prompts.clone();
+        
+        Subscription::run_with_id("config-file-watcher", stream::channel(16, move |mut output| async move {
+            let config_path = AppConfig::config_file_path();
+            let mut files = FileWatcher::new(std::iter::once(config_path.clone()).chain(prompts.paths()));
+            let mut poll = tokio::time::interval(watcher::POLL_INTERVAL);
+            
+            loop {
+                poll.tick().await;
+                let changed = files.poll_changed();
+                if changed.is_empty() {
+                    continue;
+                }
+                
+                let mut reload_prompts = changed.iter().any(|path| *path != config_path);
+                if changed.contains(&config_path) {
+                    match AppConfig::reload() {
+                        Ok(config) => {
+                            // Prompt files may have moved; follow the new paths
+                            if config.prompts != prompts {
+                                prompts = config.prompts.clone();
+                                for path in prompts.paths() {
+                                    files.watch(path);
+                                }
+                                reload_prompts = true;
+                            }
+                            let _ = output.send(Message::ConfigFileStatus("config.toml".to_string(), None)).await;
+                            let _ = output.send(Message::ConfigReloaded(config)).await;
+                        }
+                        Err(e) => {
+                            let _ = output.send(Message::ConfigFileStatus("config.toml".to_string(), Some(e))).await;
+                        }
+                    }
+                }
+                
+                if reload_prompts {
+                    // Templates added to the directory since the last poll
+                    for path in prompts.paths() {
+                        files.watch(path);
+                    }
+                    match PromptManager::load_from_config(&prompts) {
+                        Ok(prompt_manager) => {
+                            let _ = output.send(Message::ConfigFileStatus("prompts".to_string(), None)).await;
+                            let _ = output.send(Message::PromptsReloaded(prompt_manager)).await;
+                        }
+                        Err(e) => {
+                            let _ = output.send(Message::ConfigFileStatus("prompts".to_string(), Some(e.to_string()))).await;
+                        }
+                    }
+                }
+            }
+        }))
+    }
 }
 
 impl Application for CosmicLlmApp {
@@ -470,7 +832,18 @@ impl Application for CosmicLlmApp {
 
     fn init(core: Core, _flags: Self::Flags) -> (Self, app::Task<Self::Message>) {
         // Initialize config and storage
-        let config = AppConfig::load().unwrap_or_else(|_| AppConfig::default());
+        let mut config_errors = std::collections::BTreeMap::new();
+        let config = match AppConfig::reload() {
+            Ok(config) => config,
+            Err(e) => {
+                // A missing config.toml is a first run; anything else is worth reporting
+                if AppConfig::config_file_path().exists() {
+                    eprintln!("Failed to load config.toml, using defaults: {}", e);
+                    config_errors.insert("config.toml".to_string(), e);
+                }
+                AppConfig::default()
+            }
+        };
         if let Ok(cwd) = std::env::current_dir() {
             println!("🗂️ Config load cwd: {}", cwd.display());
         }
@@ -492,7 +865,9 @@ impl Application for CosmicLlmApp {
         let prompt_manager = crate::prompts::PromptManager::load_from_config(&config.prompts)
             .unwrap_or_else(|e| {
                 eprintln!("Failed to load prompts: {}", e);
-                crate::prompts::PromptManager::load_from_config(&crate::prompts::PromptConfig::default()).unwrap()
+                config_errors.insert("prompts".to_string(), e.to_string());
+                let no_prompts = crate::prompts::PromptConfig { system_prompt_file: None, user_prompt_file: None, templates_dir: None };
+                crate::prompts::PromptManager::load_from_config(&no_prompts).unwrap()
             });
         
         // Initialize MCP registry (non-blocking)
@@ -504,6 +879,7 @@ impl Application for CosmicLlmApp {
         let mcp_config = crate::config::MCPConfig::load_from_json()
             .unwrap_or_else(|e| {
                 println!("📝 No mcp_config.json found (or error loading): {}", e);
+                config_errors.insert("mcp_config.json".to_string(), e.to_string());
                 println!("📝 Falling back to embedded TOML config");
                 config.mcp.clone()
             });
@@ -514,31 +890,31 @@ impl Application for CosmicLlmApp {
         }
         
         tokio::spawn(async move {
-            let mut registry = mcp_registry_clone.write().await;
-            if let Err(e) = registry.initialize_from_config(&mcp_config).await {
-                eprintln!("Failed to initialize MCP registry: {}", e);
+            {
+                let mut registry = mcp_registry_clone.write().await;
+                if let Err(e) = registry.initialize_from_config(&mcp_config).await {
+                    eprintln!("Failed to initialize MCP registry: {}", e);
+                }
             }
+            // Follow tools/list_changed notifications and mcp_config.json edits
+            MCPServerRegistry::watch(mcp_registry_clone).await;
         });
         
-        // Initialize LLM client based on default profile's backend
-        let llm_client: Arc<dyn LlmClient> = {
-            let profile = config.get_default_profile().unwrap_or(&crate::config::LlmProfile::default()).clone();
-            match profile.backend.as_str() {
-                "anthropic" => Arc::new(crate::llm::anthropic::AnthropicClient::new(profile)),
-                "deepseek" | "openai" => Arc::new(crate::llm::openai::OpenAIClient::new(profile)),
-                "ollama" => Arc::new(crate::llm::ollama::OllamaClient::new(profile)),
-                "gemini" => Arc::new(crate::llm::gemini::GeminiClient::new(profile)),
-                _ => Arc::new(crate::llm::openai::OpenAIClient::new(profile)),
-            }
-        };
+        let profile = config.get_default_profile().unwrap_or(&crate::config::LlmProfile::default()).clone();
+        let llm_client: Arc<dyn LlmClient> = Arc::new(crate::llm::UnavailableClient::new("No LLM backend configured"));
         
         let mut app = Self::new(core, config, storage, prompt_manager, mcp_registry, llm_client);
+        app.config_errors = config_errors;
+        app.settings_page.load_mcp_config(&app.config);
+        app.apply_key_binds();
+        // Initialize LLM client based on default profile's backend
+        app.set_llm_client(profile);
         
         // Check for conversations with "Generating title..." and retry title generation
         // Note: We'll handle this in the main thread instead of async task
         // since Storage is not cloneable
         println!("🔍 Checking for conversations with 'Generating title...'");
-        let conversations = app.storage.list_conversations().unwrap_or_else(|e| {
+        let conversations = app.storage.list_conversations_from_index().unwrap_or_else(|e| {
             eprintln!("Failed to list conversations: {}", e);
             Vec::new()
         });
@@ -571,37 +947,41 @@ impl Application for CosmicLlmApp {
             }
         }
         println!("✅ Finished checking for conversations with 'Generating title...'");
+        app.reload_history_rows();
         
         // Add welcome message
         app.messages.push(ChatMessage {
             content: "Welcome to Cosmic AI".to_string(),
             is_user: false,
             is_error: false,
+            is_notice: false,
+            reasoning: String::new(),
         });
         
-        // Load MCP tools on startup (same as refresh button)
-        let load_tools_task = cosmic::Task::perform(
-            async move {
-                // Wait for MCP servers to initialize (give them more time)
-                tokio::time::sleep(tokio::time::Duration::from_millis(5000)).await;
-                println!("🔄 Startup: Attempting to refresh MCP tools...");
-                cosmic::Action::App(Message::RefreshMCPTools)
-            },
-            |msg| msg,
-        );
-        
-        let tasks = vec![load_tools_task];
-
-        (app, app::Task::batch(tasks))
+        // MCP tools are delivered by the registry events subscription
+        let theme = app_theme(app.config.preferences.theme);
+        (app, cosmic::command::set_theme(theme))
     }
 
     fn subscription(&self) -> Subscription<Self::Message> {
+        let mut subscriptions = vec![
+            self.create_mcp_events_subscription(),
+            self.create_storage_events_subscription(),
+            self.create_config_watch_subscription(),
+            cosmic::iced::event::listen_with(|event, status, _id| match event {
+                cosmic::iced::Event::Window(cosmic::iced::window::Event::Focused) => Some(Message::WindowFocusChanged(true)),
+                cosmic::iced::Event::Window(cosmic::iced::window::Event::Unfocused) => Some(Message::WindowFocusChanged(false)),
+                // Keys a focused widget already handled (typing, Enter in the input) are not shortcuts
+                cosmic::iced::Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. })
+                    if status == cosmic::iced::event::Status::Ignored => Some(Message::Key(modifiers, key)),
+                _ => None,
+            }),
+        ];
         // Create a subscription for streaming LLM responses
         if self.is_streaming {
-            self.create_streaming_subscription(self.current_streaming_id)
-        } else {
-            Subscription::none()
+            subscriptions.push(self.create_streaming_subscription(self.current_streaming_id));
         }
+        Subscription::batch(subscriptions)
     }
 
     fn update(&mut self, message: Self::Message) -> app::Task<Self::Message> {
@@ -614,18 +994,33 @@ impl Application for CosmicLlmApp {
                 self.input = self.input_content.text();
             }
             Message::SendMessage => {
+                // One request at a time; a blocked send leaves the typed text in the input
+                if self.is_streaming || self.preparing_generation.is_some() {
+                    return app::Task::none();
+                }
                 println!("🔍 DEBUG: SendMessage received. Input: '{}', Attachments: {}", 
                     self.input, self.attached_files.len());
                 // Allow sending if there's text OR if there are attachments
                 if !self.input.trim().is_empty() || !self.attached_files.is_empty() {
                     // Create new conversation if none exists
                     if self.current_conversation_id.is_none() {
-                        let conv_id = self.storage.create_conversation("Generating title...".to_string())
-                            .unwrap_or_else(|e| {
-                                eprintln!("Failed to create conversation: {}", e);
-                                Uuid::new_v4()
-                            });
+                        self.off_the_record = !self.config.preferences.save_history;
+                        let conv_id = if self.off_the_record {
+                            Uuid::new_v4()
+                        } else {
+                            self.storage.create_conversation("Generating title...".to_string())
+                                .unwrap_or_else(|e| {
+                                    eprintln!("Failed to create conversation: {}", e);
+                                    Uuid::new_v4()
+                                })
+                        };
                         self.current_conversation_id = Some(conv_id);
+                        // Pin the profile so reopening the conversation restores it, unless it
+                        // comes from the persona: then the persona's later profile changes apply
+                        if !self.active_persona().is_some_and(|persona| persona.profile.is_some()) {
+                            self.conversation_settings.profile = Some(self.conversation_profile_name());
+                        }
+                        self.save_conversation_settings();
                         
                         // Generate title synchronously
                         println!("🚀 Starting title generation for conversation {}", conv_id);
@@ -639,10 +1034,25 @@ impl Application for CosmicLlmApp {
                         };
                         
                         println!("🎯 Generated title: '{}'", fallback_title);
-                        if let Err(e) = self.storage.update_conversation_title(&conv_id, fallback_title.clone()) {
-                            eprintln!("Failed to update conversation title: {}", e);
+                        if !self.off_the_record {
+                            if let Err(e) = self.storage.update_conversation_title(&conv_id, fallback_title.clone()) {
+                                eprintln!("Failed to update conversation title: {}", e);
+                            }
+                            println!("💾 Saved title to storage for conversation {}: {}", conv_id, fallback_title);
+                        }
+                    }
+                    
+                    // The earlier turns go to the model as well, not only the page on screen
+                    let mut llm_messages = self.request_history();
+                    
+                    // The reply appears at the end of the conversation, so show the latest page
+                    if self.has_newer_messages {
+                        if let Some(conv_id) = self.current_conversation_id {
+                            match self.storage.latest_messages(&conv_id, None, MESSAGE_PAGE_SIZE) {
+                                Ok(page) => self.show_message_page(page),
+                                Err(e) => eprintln!("Failed to load conversation: {}", e),
+                            }
                         }
-                        println!("💾 Saved title to storage for conversation {}: {}", conv_id, fallback_title);
                     }
                     
                     // Create user message content
@@ -653,11 +1063,13 @@ impl Application for CosmicLlmApp {
                         content: message_content,
                         is_user: true,
                         is_error: false,
+                        is_notice: false,
+                        reasoning: String::new(),
                     };
                     self.messages.push(user_msg.clone());
                     
                     // Add to storage
-                    if let Some(conv_id) = self.current_conversation_id {
+                    if let Some(conv_id) = self.stored_conversation_id() {
                         if let Err(e) = self.storage.add_message_to_conversation(&conv_id, "user".to_string(), self.input.clone()) {
                             eprintln!("Failed to add message to conversation: {}", e);
                         }
@@ -697,26 +1109,6 @@ impl Application for CosmicLlmApp {
                     }
                     println!("🔍 DEBUG: Final attachments count: {}", attachments.len());
                     
-                    // Convert messages to LLM format
-                    let mut llm_messages = Vec::new();
-                    
-                    // Add system prompt if available
-                    if let Some(system_prompt) = self.prompt_manager.get_system_prompt() {
-                        llm_messages.push(crate::llm::Message::new(
-                            crate::llm::Role::System,
-                            system_prompt.to_string()
-                        ));
-                    }
-                    
-                    for msg in &self.messages {
-                        let role = if msg.is_user { 
-                            crate::llm::Role::User 
-                        } else { 
-                            crate::llm::Role::Assistant 
-                        };
-                        llm_messages.push(crate::llm::Message::new(role, msg.content.clone()));
-                    }
-                    
                     // Create the current user message with attachments
                     let current_user_message = if attachments.is_empty() {
                         crate::llm::Message::new(crate::llm::Role::User, input_text.clone())
@@ -739,41 +1131,62 @@ impl Application for CosmicLlmApp {
                             i, msg.role, msg.content, msg.attachments);
                     }
                     
-                    // Store the prepared messages for the subscription to use
-                    self.pending_llm_messages = Some(llm_messages);
-                    
-                    // Start streaming LLM response
-                    let streaming_id = uuid::Uuid::new_v4();
-                    self.current_streaming_id = Some(streaming_id);
-                    self.is_streaming = true;
+                    // The reply should be seen, wherever the user had scrolled to
+                    self.chat_at_bottom = true;
                     
                     // Store the last user message for retry functionality
                     self.last_user_message = Some(input_text.clone());
                     
-                    // The scrollable widget will automatically scroll to show new content
-                    // due to the spacer at the bottom
+                    // Streaming starts once the system prompt is rendered
+                    return self.prepare_generation(llm_messages);
+                }
+            }
+            Message::GenerationPrepared(request_id, conv_id, llm_messages) => {
+                // Stopped while preparing; a newer request owns the flag now
+                if self.preparing_generation != Some(request_id) {
+                    return app::Task::none();
                 }
+                self.preparing_generation = None;
+                // The user moved to another conversation meanwhile
+                if self.is_streaming || conv_id != self.current_conversation_id {
+                    return app::Task::none();
+                }
+                // Store the prepared messages for the subscription to use
+                self.pending_llm_messages = Some(llm_messages);
+                
+                // Start streaming LLM response
+                let streaming_id = uuid::Uuid::new_v4();
+                self.current_streaming_id = Some(streaming_id);
+                self.cancel_token = Some(tokio_util::sync::CancellationToken::new());
+                self.is_streaming = true;
+                
+                // The scrollable widget will automatically scroll to show new content
+                // due to the spacer at the bottom
             }
             Message::StopMessage => {
+                // The request never started streaming; drop it once its prompt is rendered
+                self.preparing_generation = None;
                 if self.is_streaming {
-                    // Stop the current streaming
+                    // Abort the agent loop together with its HTTP request and tool calls
+                    if let Some(cancel_token) = self.cancel_token.take() {
+                        cancel_token.cancel();
+                    }
                     self.is_streaming = false;
                     self.current_streaming_id = None;
                     self.pending_llm_messages = None; // Clear prepared messages
                     
-                    // Remove any incomplete assistant message
-                    if let Some(index) = self.current_ai_message_index {
-                        if index < self.messages.len() && !self.messages[index].is_user {
-                            self.messages.remove(index);
-                        }
-                    }
-                    self.current_ai_message_index = None;
+                    // Keep what the turn produced so far, marked as cancelled
+                    self.finish_current_turn(true);
                 }
             }
             Message::RetryMessage => {
                 if let Some(last_msg) = &self.last_user_message {
                     // Stop current streaming if any
+                    self.preparing_generation = None;
                     if self.is_streaming {
+                        if let Some(cancel_token) = self.cancel_token.take() {
+                            cancel_token.cancel();
+                        }
                         self.is_streaming = false;
                         self.current_streaming_id = None;
                     }
@@ -870,59 +1283,176 @@ impl Application for CosmicLlmApp {
                     self.current_error = Some(format!("File selection error: {}", error));
                 }
             }
+            Message::ShowPromptTemplates => {
+                if self.context_page == ContextPage::PromptTemplates && self.core.window.show_context && !self.show_tools_context {
+                    self.core.window.show_context = false;
+                } else {
+                    self.show_tools_context = false;
+                    self.selected_template = None;
+                    self.template_values.clear();
+                    self.context_page = ContextPage::PromptTemplates;
+                    self.core.window.show_context = true;
+                }
+            }
+            Message::SelectPromptTemplate(index) => {
+                if let Some(template) = self.prompt_manager.templates().get(index) {
+                    self.template_values = template.fields().into_iter().map(|field| (field, String::new())).collect();
+                    self.selected_template = Some(index);
+                    // Nothing to ask for: insert right away
+                    if self.template_values.is_empty() {
+                        return self.update(Message::InsertPromptTemplate);
+                    }
+                }
+            }
+            Message::BackToPromptTemplates => {
+                self.selected_template = None;
+                self.template_values.clear();
+            }
+            Message::PromptTemplateFieldChanged(index, value) => {
+                if let Some((_, field_value)) = self.template_values.get_mut(index) {
+                    *field_value = value;
+                }
+            }
+            Message::InsertPromptTemplate => {
+                if let Some(template) = self.selected_template.and_then(|index| self.prompt_manager.templates().get(index)) {
+                    let mut values = crate::prompts::builtin_values(&self.attached_files);
+                    values.extend(self.template_values.iter().cloned());
+                    let rendered = template.render(&values);
+                    // Append to whatever was already typed
+                    let text = if self.input.trim().is_empty() {
+                        rendered
+                    } else {
+                        format!("{}\n{}", self.input.trim_end(), rendered)
+                    };
+                    self.input_content = text_editor::Content::with_text(&text);
+                    self.input = text;
+                }
+                self.selected_template = None;
+                self.template_values.clear();
+                self.core.window.show_context = false;
+            }
             Message::NavigateTo(page) => {
                 self.current_page = page;
+                // Keep the nav bar in step when the page changes from a shortcut or button
+                let entity = self.nav_model.iter().find(|entity| self.nav_model.data::<NavigationPage>(*entity) == Some(&page));
+                if let Some(entity) = entity {
+                    self.nav_model.activate(entity);
+                }
                 
                 // Refresh MCP tools when navigating to MCP config page or Chat page
                 if page == NavigationPage::MCPConfig || page == NavigationPage::Chat {
                     // Immediately try to get cached tools
-                    if let Ok(registry) = self.mcp_registry.try_read() {
-                        self.available_mcp_tools = registry.get_available_tools();
-                        self.tool_states = registry.get_tool_states();
+                    let snapshot = self.mcp_registry.try_read().ok().map(|registry| registry.snapshot());
+                    if let Some(snapshot) = snapshot {
+                        self.apply_mcp_snapshot(snapshot);
                     }
                 }
             }
             Message::SelectConversation(id) => {
                 self.current_conversation_id = Some(id);
+                self.off_the_record = false;
+                self.highlighted_message = None;
                 self.current_page = NavigationPage::Chat;
-                // Load conversation messages
-                if let Ok(Some(conv)) = self.storage.get_conversation(&id) {
-                    self.messages = conv.messages.iter().map(|msg| {
-                        ChatMessage {
-                            content: msg.content.clone(),
-                            is_user: msg.role == "user",
-                            is_error: false,
-                        }
-                    }).collect();
+                // Load the newest messages; older ones are paged in on request
+                if let Ok(Some(settings)) = self.storage.get_conversation_settings(&id) {
+                    match self.storage.latest_messages(&id, None, MESSAGE_PAGE_SIZE) {
+                        Ok(page) => self.show_message_page(page),
+                        Err(e) => eprintln!("Failed to load conversation: {}", e),
+                    }
+                    // Talk to the model this conversation was using
+                    self.load_conversation_settings(settings);
+                }
+            }
+            Message::LoadOlderMessages => {
+                let (Some(id), Some(first_id)) = (self.current_conversation_id, self.first_loaded_message_id) else {
+                    return app::Task::none();
+                };
+                let page = match self.storage.latest_messages(&id, Some(first_id), MESSAGE_PAGE_SIZE) {
+                    Ok(page) => page,
+                    Err(e) => {
+                        eprintln!("Failed to load earlier messages: {}", e);
+                        return app::Task::none();
+                    }
+                };
+                // Everything indexed by message position moves down by the prepended count
+                let count = page.messages.len();
+                self.expanded_reasoning = self.expanded_reasoning.iter().map(|i| i + count).collect();
+                for anchored in &mut self.archived_tool_calls {
+                    anchored.anchor_index += count;
                 }
+                self.current_ai_message_index = self.current_ai_message_index.map(|i| i + count);
+                self.highlighted_message = self.highlighted_message.map(|i| i + count);
+                self.messages.splice(0..0, page.messages.iter().map(chat_message));
+                self.first_loaded_message_id = page.first_id.or(self.first_loaded_message_id);
+                self.has_older_messages = page.has_older;
+            }
+            Message::LoadNewerMessages => {
+                let (Some(id), Some(last_id)) = (self.current_conversation_id, self.last_loaded_message_id) else {
+                    return app::Task::none();
+                };
+                let page = match self.storage.messages_from(&id, last_id + 1, MESSAGE_PAGE_SIZE) {
+                    Ok(page) => page,
+                    Err(e) => {
+                        eprintln!("Failed to load later messages: {}", e);
+                        return app::Task::none();
+                    }
+                };
+                self.messages.extend(page.messages.iter().map(chat_message));
+                self.last_loaded_message_id = page.last_id.or(self.last_loaded_message_id);
+                self.has_newer_messages = page.has_newer;
             }
+            Message::StorageChanged(event) => match event {
+                None => self.reload_history_rows(),
+                Some(StorageEvent::ConversationCreated { id } | StorageEvent::ConversationChanged { id }) => {
+                    self.refresh_history_row(&id);
+                }
+                Some(StorageEvent::ConversationDeleted { id }) => {
+                    self.history_rows.retain(|conv| conv.id != id);
+                }
+            },
             Message::DeleteConversation(id) => {
                 // If deleting the active conversation, clear the chat
                 if self.current_conversation_id == Some(id) {
                     self.current_conversation_id = None;
+                    self.first_loaded_message_id = None;
+                    self.last_loaded_message_id = None;
+                    self.has_older_messages = false;
+                    self.has_newer_messages = false;
+                    self.expanded_reasoning.clear();
                     self.messages.clear();
                     self.input.clear();
+                    self.load_conversation_settings(ConversationSettings::default());
                 }
                 let _ = self.storage.delete_conversation(&id);
+                self.history_selection.remove(&id);
                 // Stay on History page to reflect changes
                 self.current_page = NavigationPage::History;
             }
             Message::NewConversation => {
                 self.current_conversation_id = None;
+                self.off_the_record = false;
+                self.highlighted_message = None;
+                self.first_loaded_message_id = None;
+                self.last_loaded_message_id = None;
+                self.has_older_messages = false;
+                self.has_newer_messages = false;
+                self.expanded_reasoning.clear();
                 self.messages.clear();
                 self.input.clear();
+                self.load_conversation_settings(ConversationSettings::default());
                 self.current_page = NavigationPage::Chat;
                 self.active_tool_calls.clear();
                 self.archived_tool_calls.clear();
                 self.current_ai_message_index = None;
             }
             Message::AgentUpdate(u) => {
+                let mut notification = app::Task::none();
                 match u {
                     AgentUpdate::BeginTurn { conversation_id: _, turn_id, iteration, plan_summary } => {
                         // Start a new turn bubble
                         self.turns.push(Turn { id: turn_id, iteration, text: plan_summary.unwrap_or_default(), complete: false, tools: Vec::new() });
                         // Always create a fresh assistant message bubble for this turn
-                        self.messages.push(ChatMessage { content: String::from(""), is_user: false, is_error: false });
+                        self.messages.push(ChatMessage { content: String::from(""), is_user: false, is_error: false, is_notice: false, reasoning: String::new() });
                         self.current_ai_message_index = Some(self.messages.len() - 1);
                     }
                     AgentUpdate::AssistantDelta { turn_id: _, text_chunk, seq: _ } => {
@@ -948,17 +1478,25 @@ impl Application for CosmicLlmApp {
                             }
                         }
                         if !wrote {
-                            self.messages.push(ChatMessage { content: full_text.clone(), is_user: false, is_error: false });
+                            self.messages.push(ChatMessage { content: full_text.clone(), is_user: false, is_error: false, is_notice: false, reasoning: String::new() });
                             self.current_ai_message_index = Some(self.messages.len() - 1);
                         }
                         if !full_text.trim().is_empty() {
-                            if let Some(conv_id) = self.current_conversation_id {
-                                if let Err(e) = self.storage.add_message_to_conversation(&conv_id, "assistant".to_string(), full_text) {
+                            if let Some(conv_id) = self.stored_conversation_id() {
+                                let model = self.conversation_profile().map(|profile| profile.model).unwrap_or_default();
+                                if let Err(e) = self.storage.add_reply_to_conversation(&conv_id, full_text, &model) {
                                     eprintln!("Failed to add message to conversation: {}", e);
                                 }
                             }
                         }
                     }
+                    AgentUpdate::ReasoningDelta { turn_id: _, text_chunk } => {
+                        if let Some(idx) = self.current_ai_message_index {
+                            if let Some(msg) = self.messages.get_mut(idx) {
+                                msg.reasoning.push_str(&text_chunk);
+                            }
+                        }
+                    }
                     AgentUpdate::ToolPlanned { turn_id: _, plan_items: _ } => {
                         // Do not create placeholder rows; spinner covers planned state
                     }
@@ -987,58 +1525,15 @@ impl Application for CosmicLlmApp {
                         }
                     }
                     AgentUpdate::EndTurn { turn_id: _ } => {
-                        // Archive active tools under current AI bubble
-                        if let Some(anchor) = self.current_ai_message_index {
-                            for tc in self.active_tool_calls.drain(..) {
-                                self.archived_tool_calls.push(AnchoredToolCall { anchor_index: anchor, tool_call: tc });
-                            }
-                            // If the assistant bubble has no text, remove it and shift anchors
-                            let should_remove = self.messages.get(anchor).map(|m| !m.is_user && m.content.trim().is_empty()).unwrap_or(false);
-                            if should_remove {
-                                self.messages.remove(anchor);
-                                for anchored in &mut self.archived_tool_calls {
-                                    if anchored.anchor_index > anchor {
-                                        anchored.anchor_index -= 1;
-                                    } else if anchored.anchor_index == anchor {
-                                        anchored.anchor_index = anchor.saturating_sub(1);
-                                    }
-                                }
-                            }
-                        } else {
-                            self.active_tool_calls.clear();
-                        }
-                        if let Some(turn) = self.turns.last_mut() { 
-                            turn.complete = true;
-                            // Persist turn to storage
-                            if let Some(conv_id) = self.current_conversation_id {
-                                let storage_tools: Vec<crate::storage::conversation_storage::ToolCallInfo> = turn.tools.iter().map(|tc| {
-                                    crate::storage::conversation_storage::ToolCallInfo {
-                                        id: tc.id.clone(),
-                                        tool_name: tc.tool_name.clone(),
-                                        parameters: tc.parameters.clone(),
-                                        status: match tc.status {
-                                            ToolCallStatus::Started => crate::storage::conversation_storage::ToolCallStatus::Started,
-                                            ToolCallStatus::Completed => crate::storage::conversation_storage::ToolCallStatus::Completed,
-                                            ToolCallStatus::Error => crate::storage::conversation_storage::ToolCallStatus::Error,
-                                        },
-                                        result: tc.result.clone(),
-                                        error: tc.error.clone(),
-                                    }
-                                }).collect();
-                                
-                                let storage_turn = crate::storage::conversation_storage::Turn {
-                                    id: turn.id,
-                                    iteration: turn.iteration,
-                                    text: turn.text.clone(),
-                                    complete: turn.complete,
-                                    tools: storage_tools,
-                                };
-                                let _ = self.storage.add_turn_to_conversation(&conv_id, storage_turn);
-                            }
-                        }
-                        self.current_ai_message_index = None;
+                        self.finish_current_turn(false);
                     }
-                    AgentUpdate::EndConversation { final_text: _ } => {
+                    AgentUpdate::EndConversation { final_text } => {
+                        let body = match crate::ui::notifications::preview(&final_text) {
+                            preview if preview.is_empty() => "The assistant finished its run".to_string(),
+                            preview => preview,
+                        };
+                        notification = self.notify_in_background("Reply ready", body);
+                        self.cancel_token = None;
                         self.is_streaming = false;
                         self.current_streaming_id = None;
                         self.current_ai_message_index = None;
@@ -1048,21 +1543,32 @@ impl Application for CosmicLlmApp {
                     }
                     AgentUpdate::ModelError { turn_id: _, error } => {
                         // Stop streaming and show error message
+                        self.cancel_token = None;
                         self.is_streaming = false;
                         self.current_streaming_id = None;
                         self.current_ai_message_index = None;
                         self.pending_llm_messages = None;
                         self.active_tool_calls.clear();
                         
+                        notification = self.notify_in_background("Reply failed", crate::ui::notifications::preview(&error));
+                        
                         // Add error message as a separate chat bubble
                         self.messages.push(ChatMessage { 
                             content: format!("❌ **Model Communication Error**\n\n{}", error), 
                             is_user: false,
-                            is_error: true
+                            is_error: true,
+                            is_notice: false,
+                            reasoning: String::new(),
                         });
                     }
-                    AgentUpdate::Heartbeat { turn_id: _, ts_ms: _ } => {}
+                    AgentUpdate::Heartbeat { turn_id: _, ts_ms: _ } => {
+                        return app::Task::none();
+                    }
+                }
+                if !self.chat_at_bottom {
+                    return notification;
                 }
+                return app::Task::batch([notification, self.update(Message::ScrollToBottom)]);
             }
             Message::ToolCallStarted(tool_name, parameters) => {
                 // Add tool call to active list
@@ -1101,10 +1607,21 @@ impl Application for CosmicLlmApp {
                     }
                 }
             }
+            Message::ToggleReasoning(index) => {
+                if !self.expanded_reasoning.remove(&index) {
+                    self.expanded_reasoning.insert(index);
+                }
+            }
             Message::ScrollToBottom => {
-                // For now, we'll rely on the spacer at the bottom to force scroll
-                // The scrollable widget should automatically scroll to show new content
-                // This is a placeholder for future scroll-to-bottom implementation
+                if self.config.preferences.auto_scroll {
+                    return cosmic::iced::widget::scrollable::snap_to(
+                        self.scrollable_id.clone(),
+                        cosmic::iced::widget::scrollable::RelativeOffset::END,
+                    );
+                }
+            }
+            Message::ChatScrolled(at_bottom) => {
+                self.chat_at_bottom = at_bottom;
             }
             Message::ShowAbout => {
                 // Toggle behavior: if About is already shown, hide it; otherwise show it
@@ -1123,44 +1640,126 @@ impl Application for CosmicLlmApp {
                 let _ = webbrowser::open(&url);
             }
             Message::OpenSettings => {
-                self.current_page = NavigationPage::Settings;
+                return self.update(Message::NavigateTo(NavigationPage::Settings));
             }
             Message::Quit => {
                 // TODO: Implement proper quit
                 std::process::exit(0);
             }
-            Message::ChangeDefaultProfile(profile_index) => {
+            Message::SelectPersona(index) => {
+                // Personas are picked when a conversation starts
+                if self.current_conversation_id.is_some() {
+                    return app::Task::none();
+                }
+                let mut persona_names: Vec<String> = self.config.personas.keys().cloned().collect();
+                persona_names.sort();
+                let persona = index.checked_sub(1).and_then(|i| persona_names.get(i).cloned());
+                // Start from the persona's defaults instead of earlier overrides
+                self.load_conversation_settings(ConversationSettings { persona, ..ConversationSettings::default() });
+            }
+            Message::ChangeConversationProfile(profile_index) => {
                 // Must sort the same way as in the view to maintain index consistency
                 let mut profile_names: Vec<String> = self.config.profiles.keys().cloned().collect();
                 profile_names.sort();
-                if let Some(profile_name) = profile_names.get(profile_index) {
-                    self.config.default = profile_name.clone();
-                    self.settings_changed = true;
-                    // Recreate LLM client for new default provider
-                    if let Some(profile) = self.config.get_default_profile().cloned() {
-                        let masked = if profile.api_key.len() > 6 { format!("{}...{}", &profile.api_key[..3], &profile.api_key[profile.api_key.len().saturating_sub(3)..]) } else { "***".to_string() };
-                        println!("🔄 Switching default profile to '{}' model='{}' endpoint='{}' api_key='{}'", self.config.default, profile.model, profile.endpoint, masked);
-                        self.llm_client = match profile.backend.as_str() {
-                            "anthropic" => Arc::new(crate::llm::anthropic::AnthropicClient::new(profile)),
-                            "deepseek" | "openai" => Arc::new(crate::llm::openai::OpenAIClient::new(profile)),
-                            "ollama" => Arc::new(crate::llm::ollama::OllamaClient::new(profile)),
-                            "gemini" => Arc::new(crate::llm::gemini::GeminiClient::new(profile)),
-                            _ => Arc::new(crate::llm::openai::OpenAIClient::new(profile)),
-                        };
+                if let Some(profile_name) = profile_names.get(profile_index).cloned() {
+                    if profile_name == self.conversation_profile_name() {
+                        return app::Task::none();
+                    }
+                    self.conversation_settings.profile = Some(profile_name.clone());
+                    self.save_conversation_settings();
+                    self.apply_conversation_profile();
+                    if let Some(profile) = self.config.get_profile(&profile_name) {
+                        println!("🔄 Switching conversation to profile '{}' model='{}' endpoint='{}'", profile_name, profile.model, profile.endpoint);
+                    }
+                    
+                    // Record the switch in the history of a conversation that already started
+                    if self.current_conversation_id.is_some() {
+                        let model = self.config.get_profile(&profile_name).map(|p| p.model.clone()).unwrap_or_default();
+                        let notice = format!("Switched to {} ({})", profile_name, model);
+                        if let Some(conv_id) = self.stored_conversation_id() {
+                            if let Err(e) = self.storage.add_message_to_conversation(&conv_id, "notice".to_string(), notice.clone()) {
+                                eprintln!("Failed to add message to conversation: {}", e);
+                            }
+                        }
+                        self.messages.push(ChatMessage {
+                            content: notice,
+                            is_user: false,
+                            is_error: false,
+                            is_notice: true,
+                            reasoning: String::new(),
+                        });
                     }
                 }
             }
+            Message::ShowConversationSettings => {
+                if self.context_page == ContextPage::ConversationSettings && self.core.window.show_context && !self.show_tools_context {
+                    self.core.window.show_context = false;
+                } else {
+                    self.show_tools_context = false;
+                    self.context_page = ContextPage::ConversationSettings;
+                    self.core.window.show_context = true;
+                }
+            }
+            Message::ConversationTemperatureChanged(value) => {
+                self.temperature_input = value;
+            }
+            Message::ConversationMaxTokensChanged(value) => {
+                self.max_tokens_input = value;
+            }
+            Message::ConversationSystemPromptChanged(value) => {
+                self.system_prompt_input = value;
+            }
+            Message::ApplyConversationSettings => {
+                let temperature = self.temperature_input.trim();
+                let temperature = if temperature.is_empty() {
+                    None
+                } else {
+                    match temperature.parse::<f32>() {
+                        Ok(value) if (0.0..=2.0).contains(&value) => Some(value),
+                        _ => {
+                            self.current_error = Some(format!("Temperature must be between 0 and 2, got '{}'", temperature));
+                            return app::Task::none();
+                        }
+                    }
+                };
+                let max_tokens = self.max_tokens_input.trim();
+                let max_tokens = if max_tokens.is_empty() {
+                    None
+                } else {
+                    match max_tokens.parse::<u32>() {
+                        Ok(value) if value > 0 => Some(value),
+                        _ => {
+                            self.current_error = Some(format!("Max tokens must be a positive number, got '{}'", max_tokens));
+                            return app::Task::none();
+                        }
+                    }
+                };
+                let system_prompt = self.system_prompt_input.trim();
+                
+                self.conversation_settings.temperature = temperature;
+                self.conversation_settings.max_tokens = max_tokens;
+                self.conversation_settings.system_prompt = (!system_prompt.is_empty()).then(|| system_prompt.to_string());
+                self.current_error = None;
+                self.save_conversation_settings();
+                self.apply_conversation_profile();
+                self.core.window.show_context = false;
+            }
             Message::SaveSettings => {
                 if let Err(e) = self.config.save() {
                     eprintln!("Failed to save settings: {}", e);
                 } else {
                     self.settings_changed = false;
+                    // Saving overwrote the version changed on disk
+                    if self.pending_config.take().is_some() {
+                        self.config_errors.remove("config.toml");
+                    }
                     println!("Settings saved successfully");
                 }
             }
             Message::ResetSettings => {
                 self.config = AppConfig::default();
                 self.settings_changed = true;
+                return cosmic::command::set_theme(app_theme(self.config.preferences.theme));
             }
             Message::SettingsMessage(settings_msg) => {
                 match settings_msg {
@@ -1171,48 +1770,361 @@ impl Application for CosmicLlmApp {
                         if self.config.profiles.contains_key(&name) {
                             self.config.default = name;
                             self.settings_changed = true;
-                            if let Some(profile) = self.config.get_default_profile().cloned() {
-                                self.llm_client = match profile.backend.as_str() {
-                                    "anthropic" => Arc::new(crate::llm::anthropic::AnthropicClient::new(profile)),
-                                    "deepseek" | "openai" => Arc::new(crate::llm::openai::OpenAIClient::new(profile)),
-                                    "ollama" => Arc::new(crate::llm::ollama::OllamaClient::new(profile)),
-                                    "gemini" => Arc::new(crate::llm::gemini::GeminiClient::new(profile)),
-                                    _ => Arc::new(crate::llm::openai::OpenAIClient::new(profile)),
-                                };
-                            }
+                            // Conversations pinned to another profile keep it
+                            self.apply_conversation_profile();
                         }
                     }
                     SimpleSettingsMessage::NewProfileNameChanged(val) => {
                         self.settings_page.new_profile_name = val;
                     }
+                    SimpleSettingsMessage::NewProfileBackendChanged(idx) => {
+                        if let Some(backend) = self.settings_page.backends.get(idx).cloned() {
+                            self.settings_page.new_profile_backend = backend;
+                            self.settings_page.invalidate_discovery();
+                        }
+                    }
                     SimpleSettingsMessage::NewProfileModelChanged(val) => {
                         self.settings_page.new_profile_model = val;
                     }
+                    SimpleSettingsMessage::NewProfileModelSelected(idx) => {
+                        if let Some(model) = self.settings_page.available_models.get(idx).cloned() {
+                            self.settings_page.new_profile_model = model;
+                        }
+                    }
                     SimpleSettingsMessage::NewProfileEndpointChanged(val) => {
                         self.settings_page.new_profile_endpoint = val;
+                        self.settings_page.invalidate_discovery();
                     }
-                    SimpleSettingsMessage::AddNewProfile => {
-                        let name = self.settings_page.new_profile_name.trim().to_string();
-                        let model = self.settings_page.new_profile_model.trim().to_string();
-                        let endpoint = self.settings_page.new_profile_endpoint.trim().to_string();
-                        if !name.is_empty() && !model.is_empty() {
-                            let profile = LlmProfile {
-                                backend: "openai".to_string(), // Default backend
-                                api_key: String::new(),
-                                model,
-                                endpoint,
-                                temperature: Some(0.7),
-                                max_tokens: Some(1000),
-                            };
-                            self.config.profiles.insert(name.clone(), profile);
-                            if self.config.default.is_empty() {
-                                self.config.default = name.clone();
+                    SimpleSettingsMessage::NewProfileApiKeyChanged(val) => {
+                        self.settings_page.new_profile_api_key = val;
+                        self.settings_page.invalidate_discovery();
+                    }
+                    SimpleSettingsMessage::FetchModels => {
+                        self.settings_page.connection_status = ConnectionStatus::Checking;
+                        let profile = self.settings_page.draft_profile();
+                        let generation = self.settings_page.discovery_generation;
+                        return cosmic::Task::perform(
+                            async move {
+                                let result = match crate::llm::create_client(profile) {
+                                    Ok(client) => client.list_models().await.map_err(|e| e.to_string()),
+                                    Err(e) => Err(e.to_string()),
+                                };
+                                cosmic::Action::App(Message::SettingsMessage(SimpleSettingsMessage::ModelsFetched(generation, result)))
+                            },
+                            |msg| msg,
+                        );
+                    }
+                    SimpleSettingsMessage::ModelsFetched(generation, result) => {
+                        // Backend, endpoint or key changed while the request was out
+                        if generation != self.settings_page.discovery_generation {
+                            return app::Task::none();
+                        }
+                        match result {
+                            Ok(models) => {
+                                self.settings_page.connection_status = if models.is_empty() {
+                                    ConnectionStatus::Failed("Connected, but the backend reported no models".to_string())
+                                } else {
+                                    ConnectionStatus::Ok(format!("Found {} models", models.len()))
+                                };
+                                if self.settings_page.new_profile_model.trim().is_empty() {
+                                    if let Some(first) = models.first() {
+                                        self.settings_page.new_profile_model = first.clone();
+                                    }
+                                }
+                                self.settings_page.available_models = models;
+                            }
+                            Err(e) => {
+                                self.settings_page.available_models.clear();
+                                self.settings_page.connection_status = ConnectionStatus::Failed(e);
+                            }
+                        }
+                    }
+                    SimpleSettingsMessage::TestConnection => {
+                        let profile = self.settings_page.draft_profile();
+                        if profile.model.is_empty() {
+                            self.settings_page.connection_status = ConnectionStatus::Failed("Choose a model first".to_string());
+                            return app::Task::none();
+                        }
+                        self.settings_page.connection_status = ConnectionStatus::Checking;
+                        let generation = self.settings_page.discovery_generation;
+                        return cosmic::Task::perform(
+                            async move {
+                                let result = match crate::llm::create_client(profile) {
+                                    Ok(client) => client.test_connection().await.map_err(|e| e.to_string()),
+                                    Err(e) => Err(e.to_string()),
+                                };
+                                cosmic::Action::App(Message::SettingsMessage(SimpleSettingsMessage::ConnectionTested(generation, result)))
+                            },
+                            |msg| msg,
+                        );
+                    }
+                    SimpleSettingsMessage::ConnectionTested(generation, result) => {
+                        if generation != self.settings_page.discovery_generation {
+                            return app::Task::none();
+                        }
+                        self.settings_page.connection_status = match result {
+                            Ok(()) => ConnectionStatus::Ok("Connection works".to_string()),
+                            Err(e) => ConnectionStatus::Failed(e),
+                        };
+                    }
+                    SimpleSettingsMessage::PullModel => {
+                        let profile = self.settings_page.draft_profile();
+                        if profile.model.is_empty() {
+                            self.settings_page.connection_status = ConnectionStatus::Failed("Enter the model to pull first".to_string());
+                            return app::Task::none();
+                        }
+                        self.settings_page.connection_status = ConnectionStatus::Pulling(format!("Pulling {}", profile.model));
+                        return pull_ollama_model(profile);
+                    }
+                    SimpleSettingsMessage::PullProgress(progress) => {
+                        // Ignore progress from a pull the form has moved on from
+                        if self.settings_page.connection_status.is_busy() {
+                            self.settings_page.connection_status = ConnectionStatus::Pulling(progress);
+                        }
+                    }
+                    SimpleSettingsMessage::ModelPulled(result) => {
+                        if !self.settings_page.connection_status.is_busy() {
+                            return app::Task::none();
+                        }
+                        self.settings_page.connection_status = match result {
+                            Ok(()) => {
+                                let model = self.settings_page.new_profile_model.trim().to_string();
+                                if !self.settings_page.available_models.contains(&model) {
+                                    self.settings_page.available_models.push(model.clone());
+                                }
+                                ConnectionStatus::Ok(format!("Pulled {}", model))
+                            }
+                            Err(e) => ConnectionStatus::Failed(e),
+                        };
+                    }
+                    SimpleSettingsMessage::PersonaNameChanged(val) => {
+                        self.settings_page.persona_name = val;
+                    }
+                    SimpleSettingsMessage::PersonaProfileSelected(idx) => {
+                        // Index 0 is "Default profile"
+                        self.settings_page.persona_profile = SimpleSettingsPage::persona_profile_options(&self.config)
+                            .get(idx)
+                            .filter(|_| idx > 0)
+                            .cloned();
+                    }
+                    SimpleSettingsMessage::PersonaSystemPromptChanged(val) => {
+                        self.settings_page.persona_system_prompt = val;
+                    }
+                    SimpleSettingsMessage::PersonaToolsChanged(val) => {
+                        self.settings_page.persona_tools = val;
+                    }
+                    SimpleSettingsMessage::PersonaTemperatureChanged(val) => {
+                        self.settings_page.persona_temperature = val;
+                    }
+                    SimpleSettingsMessage::PersonaMaxTokensChanged(val) => {
+                        self.settings_page.persona_max_tokens = val;
+                    }
+                    SimpleSettingsMessage::EditPersona(name) => {
+                        if let Some(persona) = self.config.get_persona(&name).cloned() {
+                            self.settings_page.edit_persona(&name, &persona);
+                        }
+                    }
+                    SimpleSettingsMessage::DeletePersona(name) => {
+                        if self.config.personas.remove(&name).is_some() {
+                            self.settings_changed = true;
+                            if self.conversation_settings.persona.as_deref() == Some(name.as_str()) {
+                                self.apply_conversation_profile();
+                            }
+                        }
+                    }
+                    SimpleSettingsMessage::SavePersona => {
+                        let name = self.settings_page.persona_name.trim().to_string();
+                        if name.is_empty() {
+                            self.settings_page.persona_error = Some("The persona needs a name".to_string());
+                            return app::Task::none();
+                        }
+                        let renamed = self.settings_page.persona_editing.as_deref() != Some(name.as_str());
+                        if renamed && self.config.personas.contains_key(&name) {
+                            self.settings_page.persona_error = Some(format!("A persona named '{}' already exists", name));
+                            return app::Task::none();
+                        }
+                        match self.settings_page.draft_persona() {
+                            Ok(persona) => {
+                                if let Some(previous) = self.settings_page.persona_editing.take().filter(|previous| *previous != name) {
+                                    self.config.personas.remove(&previous);
+                                    // The open conversation follows the rename
+                                    if self.conversation_settings.persona.as_deref() == Some(previous.as_str()) {
+                                        self.conversation_settings.persona = Some(name.clone());
+                                        self.save_conversation_settings();
+                                    }
+                                }
+                                self.config.personas.insert(name.clone(), persona);
+                                self.settings_changed = true;
+                                self.settings_page.reset_persona();
+                                // Conversations with this persona pick up the new profile and parameters
+                                if self.conversation_settings.persona.as_deref() == Some(name.as_str()) {
+                                    self.apply_conversation_profile();
+                                }
+                            }
+                            Err(e) => {
+                                self.settings_page.persona_error = Some(e);
+                            }
+                        }
+                    }
+                    SimpleSettingsMessage::ThemeSelected(idx) => {
+                        if let Some(theme) = ThemePreference::ALL.get(idx).copied() {
+                            self.config.preferences.theme = theme;
+                            self.settings_changed = true;
+                            return cosmic::command::set_theme(app_theme(theme));
+                        }
+                    }
+                    SimpleSettingsMessage::SaveHistoryToggled(enabled) => {
+                        // Applies from the next new conversation on
+                        self.config.preferences.save_history = enabled;
+                        self.settings_changed = true;
+                    }
+                    SimpleSettingsMessage::NotificationsToggled(enabled) => {
+                        self.config.preferences.notifications = enabled;
+                        self.settings_changed = true;
+                    }
+                    SimpleSettingsMessage::AutoScrollToggled(enabled) => {
+                        self.config.preferences.auto_scroll = enabled;
+                        self.settings_changed = true;
+                    }
+                    SimpleSettingsMessage::SaveSettings => {
+                        return self.update(Message::SaveSettings);
+                    }
+                    SimpleSettingsMessage::McpServerNameChanged(val) => {
+                        self.settings_page.mcp_name = val;
+                    }
+                    SimpleSettingsMessage::McpServerCommandChanged(val) => {
+                        self.settings_page.mcp_command = val;
+                    }
+                    SimpleSettingsMessage::McpServerArgChanged(index, val) => {
+                        if let Some(arg) = self.settings_page.mcp_args.get_mut(index) {
+                            *arg = val;
+                        }
+                    }
+                    SimpleSettingsMessage::AddMcpServerArg => {
+                        self.settings_page.mcp_args.push(String::new());
+                    }
+                    SimpleSettingsMessage::RemoveMcpServerArg(index) => {
+                        if index < self.settings_page.mcp_args.len() {
+                            self.settings_page.mcp_args.remove(index);
+                        }
+                    }
+                    SimpleSettingsMessage::McpServerEnvKeyChanged(index, val) => {
+                        if let Some((key, _)) = self.settings_page.mcp_env.get_mut(index) {
+                            *key = val;
+                        }
+                    }
+                    SimpleSettingsMessage::McpServerEnvValueChanged(index, val) => {
+                        if let Some((_, value)) = self.settings_page.mcp_env.get_mut(index) {
+                            *value = val;
+                        }
+                    }
+                    SimpleSettingsMessage::ToggleMcpServerEnvVisible(index) => {
+                        if !self.settings_page.mcp_env_revealed.remove(&index) {
+                            self.settings_page.mcp_env_revealed.insert(index);
+                        }
+                    }
+                    SimpleSettingsMessage::AddMcpServerEnv => {
+                        self.settings_page.mcp_env.push((String::new(), String::new()));
+                    }
+                    SimpleSettingsMessage::RemoveMcpServerEnv(index) => {
+                        if index < self.settings_page.mcp_env.len() {
+                            self.settings_page.mcp_env.remove(index);
+                            // Rows below moved up; hide everything again
+                            self.settings_page.mcp_env_revealed.clear();
+                        }
+                    }
+                    SimpleSettingsMessage::McpServerEnabledToggled(enabled) => {
+                        self.settings_page.mcp_enabled = enabled;
+                    }
+                    SimpleSettingsMessage::ToggleMcpServer(name, enabled) => {
+                        return self.update(Message::ToggleServer(name, enabled));
+                    }
+                    SimpleSettingsMessage::EditMcpServer(name) => {
+                        self.settings_page.edit_mcp_server(&name);
+                    }
+                    SimpleSettingsMessage::DeleteMcpServer(name) => {
+                        if self.settings_page.mcp_config.servers.remove(&name).is_some() {
+                            if self.settings_page.mcp_editing.as_deref() == Some(name.as_str()) {
+                                self.settings_page.reset_mcp_server();
+                            }
+                            return self.save_mcp_config();
+                        }
+                    }
+                    SimpleSettingsMessage::TestMcpServer => {
+                        let mut server = match self.settings_page.draft_mcp_server() {
+                            Ok(server) => server,
+                            Err(e) => {
+                                self.settings_page.mcp_test_status = ConnectionStatus::Failed(e);
+                                return app::Task::none();
+                            }
+                        };
+                        server.expand_env_vars();
+                        self.settings_page.mcp_test_status = ConnectionStatus::Checking;
+                        self.settings_page.mcp_discovered_tools.clear();
+                        let generation = self.settings_page.mcp_test_generation;
+                        return cosmic::Task::perform(
+                            async move {
+                                let result = MCPServerRegistry::test_server(&server).await
+                                    .map(|tools| tools.into_iter().map(|tool| tool.name).collect())
+                                    .map_err(|e| e.to_string());
+                                cosmic::Action::App(Message::SettingsMessage(SimpleSettingsMessage::McpServerTested(generation, result)))
+                            },
+                            |msg| msg,
+                        );
+                    }
+                    SimpleSettingsMessage::McpServerTested(generation, result) => {
+                        if generation != self.settings_page.mcp_test_generation {
+                            return app::Task::none();
+                        }
+                        match result {
+                            Ok(tools) => {
+                                self.settings_page.mcp_test_status = ConnectionStatus::Ok(format!("Connected, {} tools", tools.len()));
+                                self.settings_page.mcp_discovered_tools = tools;
+                            }
+                            Err(e) => {
+                                self.settings_page.mcp_test_status = ConnectionStatus::Failed(e);
+                            }
+                        }
+                    }
+                    SimpleSettingsMessage::SaveMcpServer => {
+                        let name = self.settings_page.mcp_name.trim().to_string();
+                        if name.is_empty() {
+                            self.settings_page.mcp_error = Some("The server needs a name".to_string());
+                            return app::Task::none();
+                        }
+                        let renamed = self.settings_page.mcp_editing.as_deref() != Some(name.as_str());
+                        if renamed && self.settings_page.mcp_config.servers.contains_key(&name) {
+                            self.settings_page.mcp_error = Some(format!("A server named '{}' already exists", name));
+                            return app::Task::none();
+                        }
+                        match self.settings_page.draft_mcp_server() {
+                            Ok(server) => {
+                                if let Some(previous) = self.settings_page.mcp_editing.take() {
+                                    self.settings_page.mcp_config.servers.remove(&previous);
+                                }
+                                self.settings_page.mcp_config.servers.insert(name, server);
+                                self.settings_page.reset_mcp_server();
+                                return self.save_mcp_config();
+                            }
+                            Err(e) => {
+                                self.settings_page.mcp_error = Some(e);
+                            }
+                        }
+                    }
+                    SimpleSettingsMessage::ResetMcpServer => {
+                        self.settings_page.reset_mcp_server();
+                    }
+                    SimpleSettingsMessage::AddNewProfile => {
+                        let name = self.settings_page.new_profile_name.trim().to_string();
+                        let profile = self.settings_page.draft_profile();
+                        if !name.is_empty() && !profile.model.is_empty() {
+                            self.config.profiles.insert(name.clone(), profile);
+                            if self.config.default.is_empty() {
+                                self.config.default = name.clone();
                             }
                             self.settings_changed = true;
                             // Clear inputs
-                            self.settings_page.new_profile_name.clear();
-                            self.settings_page.new_profile_model.clear();
-                            self.settings_page.new_profile_endpoint.clear();
+                            self.settings_page.reset_new_profile();
                         }
                     }
                 }
@@ -1240,21 +2152,15 @@ impl Application for CosmicLlmApp {
             Message::ShowMessageDialog(content) => {
                 self.dialog = Some(DialogPage::MessageText(text_editor::Content::with_text(&content)));
             }
-            Message::MCPToolsUpdated(tools) => {
-                self.available_mcp_tools = tools;
-                // Sync tool states from registry
-                if let Ok(registry) = self.mcp_registry.try_read() {
-                    self.tool_states = registry.get_tool_states();
-                }
+            Message::MCPToolsUpdated(snapshot) => {
+                self.apply_mcp_snapshot(snapshot);
             }
             Message::RefreshMCPTools => {
                 // Try to get tools synchronously from registry
-                if let Ok(registry) = self.mcp_registry.try_read() {
-                    let tools = registry.get_available_tools();
-                    println!("🔄 RefreshMCPTools: Found {} tools", tools.len());
-                    self.available_mcp_tools = tools;
-                    // Also sync tool states
-                    self.tool_states = registry.get_tool_states();
+                let snapshot = self.mcp_registry.try_read().ok().map(|registry| registry.snapshot());
+                if let Some(snapshot) = snapshot {
+                    println!("🔄 RefreshMCPTools: Found {} tools", snapshot.tools.len());
+                    self.apply_mcp_snapshot(snapshot);
                 } else {
                     println!("🔄 RefreshMCPTools: Failed to get registry read lock");
                 }
@@ -1293,6 +2199,125 @@ impl Application for CosmicLlmApp {
                     |msg| msg,
                 );
             }
+            Message::ToggleServer(server_name, enabled) => {
+                // Update local state
+                self.server_states.insert(server_name.clone(), enabled);
+                // Remember the choice in mcp_config.json
+                if let Some(server) = self.settings_page.mcp_config.servers.get_mut(&server_name) {
+                    server.disabled = !enabled;
+                    return self.save_mcp_config();
+                }
+                // Update registry asynchronously
+                let mcp_registry = self.mcp_registry.clone();
+                return cosmic::Task::perform(
+                    async move {
+                        let mut registry = mcp_registry.write().await;
+                        registry.set_server_enabled(&server_name, enabled);
+                        cosmic::Action::App(Message::RefreshMCPTools)
+                    },
+                    |msg| msg,
+                );
+            }
+            Message::ConfigReloaded(config) => {
+                if self.settings_changed {
+                    // Don't clobber edits that have not been saved yet; let the user pick
+                    println!("⚠️ config.toml changed on disk; keeping unsaved settings");
+                    self.pending_config = Some(config);
+                    self.config_errors.insert(
+                        "config.toml".to_string(),
+                        "changed on disk while settings had unsaved changes; saving keeps yours".to_string(),
+                    );
+                    return app::Task::none();
+                }
+                self.pending_config = None;
+                println!("🔄 Reloaded config.toml");
+                let fallback_profile = |config: &AppConfig, profile: Option<&LlmProfile>| {
+                    profile
+                        .and_then(|profile| profile.fallback.as_deref())
+                        .and_then(|name| config.get_profile(name))
+                        .cloned()
+                };
+                let previous_profile = self.conversation_profile();
+                let previous_fallback = fallback_profile(&self.config, previous_profile.as_ref());
+                let retry_changed = config.retry != self.config.retry;
+                let theme_changed = config.preferences.theme != self.config.preferences.theme;
+                // The embedded [mcp] section only applies when there is no mcp_config.json
+                let mcp_changed = config.mcp.servers != self.config.mcp.servers;
+                self.config = config;
+                self.apply_key_binds();
+                let profile = self.conversation_profile();
+                let client_changed = profile != previous_profile
+                    || fallback_profile(&self.config, profile.as_ref()) != previous_fallback
+                    || retry_changed;
+                if client_changed {
+                    if let Some(profile) = profile {
+                        println!("🔄 Profile settings changed, rebuilding LLM client for '{}'", self.conversation_profile_name());
+                        self.set_llm_client(profile);
+                    }
+                }
+                let theme_task = if theme_changed {
+                    cosmic::command::set_theme(app_theme(self.config.preferences.theme))
+                } else {
+                    app::Task::none()
+                };
+                if mcp_changed && !crate::config::MCPConfig::mcp_config_path().exists() {
+                    let mcp_config = self.config.mcp.clone();
+                    let mcp_registry = self.mcp_registry.clone();
+                    return cosmic::Task::batch([
+                        theme_task,
+                        cosmic::Task::perform(
+                            async move {
+                                mcp_registry.write().await.apply_config(&mcp_config).await;
+                                cosmic::Action::App(Message::RefreshMCPTools)
+                            },
+                            |msg| msg,
+                        ),
+                    ]);
+                }
+                return theme_task;
+            }
+            Message::ApplyPendingConfig => {
+                self.config_errors.remove("config.toml");
+                if let Some(config) = self.pending_config.take() {
+                    self.settings_changed = false;
+                    return self.update(Message::ConfigReloaded(config));
+                }
+            }
+            Message::PromptsReloaded(prompt_manager) => {
+                println!("🔄 Reloaded prompt files");
+                self.prompt_manager = prompt_manager;
+                // Template indices may have shifted
+                self.selected_template = None;
+                self.template_values.clear();
+            }
+            Message::WindowFocusChanged(focused) => {
+                self.window_focused = focused;
+            }
+            Message::NotificationClicked(conv_id) => {
+                let mut tasks = Vec::new();
+                // A conversation kept out of the history cannot be reopened once left
+                if self.current_conversation_id != Some(conv_id) && self.history_row(&conv_id).is_some() {
+                    tasks.push(self.update(Message::SelectConversation(conv_id)));
+                }
+                if let Some(window_id) = self.core.main_window_id() {
+                    tasks.push(cosmic::iced::window::gain_focus(window_id));
+                }
+                return app::Task::batch(tasks);
+            }
+            Message::ConfigFileStatus(file, error) => {
+                match error {
+                    Some(error) => {
+                        eprintln!("❌ Failed to reload {}: {}", file, error);
+                        self.config_errors.insert(file, error);
+                    }
+                    None => {
+                        if file == "mcp_config.json" {
+                            self.settings_page.load_mcp_config(&self.config);
+                        }
+                        self.config_errors.remove(&file);
+                    }
+                }
+            }
             Message::ShowToolsContext => {
                 self.show_tools_context = true;
                 self.core.window.show_context = true;
@@ -1305,26 +2330,211 @@ impl Application for CosmicLlmApp {
                 let _ = webbrowser::open(url.as_str());
             }
             Message::SearchChanged(query) => {
-                self.search_query = query.clone();
-                // Perform search if query is not empty
-                if !query.trim().is_empty() {
-                    // Perform search synchronously since Storage doesn't implement Clone
-                    match self.storage.search_history(&query, 50) {
-                        Ok(results) => {
-                            self.search_results = results;
+                self.search_query = query;
+                self.search_results.clear();
+                self.search_page();
+            }
+            Message::SearchResults(results) => {
+                self.search_results = results;
+            }
+            Message::LoadMoreSearchResults => {
+                self.search_page();
+            }
+            Message::OpenSearchResult(conv_id, message_id) => {
+                let select = self.update(Message::SelectConversation(conv_id));
+                if self.current_conversation_id != Some(conv_id) {
+                    return select;
+                }
+                // Start the chat at the match; earlier messages stay one click away
+                match self.storage.messages_from(&conv_id, message_id, MESSAGE_PAGE_SIZE) {
+                    Ok(page) if !page.messages.is_empty() => {
+                        self.show_message_page(page);
+                        self.highlighted_message = Some(0);
+                    }
+                    Ok(_) => return select,
+                    Err(e) => {
+                        eprintln!("Failed to load conversation: {}", e);
+                        return select;
+                    }
+                }
+                return app::Task::batch([
+                    select,
+                    cosmic::iced::widget::scrollable::snap_to(
+                        self.scrollable_id.clone(),
+                        cosmic::iced::widget::scrollable::RelativeOffset::START,
+                    ),
+                ]);
+            }
+            Message::Key(modifiers, key) => {
+                if let Some(palette) = self.command_palette.as_mut() {
+                    let count = crate::ui::commands::filter(&palette.entries, &palette.query).len();
+                    match key {
+                        keyboard::Key::Named(keyboard::key::Named::ArrowDown) => {
+                            palette.selected = (palette.selected + 1).min(count.saturating_sub(1));
+                            return app::Task::none();
                         }
-                        Err(e) => {
-                            eprintln!("Search error: {}", e);
-                            self.search_results.clear();
+                        keyboard::Key::Named(keyboard::key::Named::ArrowUp) => {
+                            palette.selected = palette.selected.saturating_sub(1);
+                            return app::Task::none();
                         }
+                        _ => {}
+                    }
+                }
+                let action = self.key_binds.iter()
+                    .find(|(key_bind, _)| key_bind.matches(modifiers, &key))
+                    .map(|(_, action)| *action);
+                if let Some(action) = action {
+                    return self.update(menu::Action::message(&action));
+                }
+            }
+            Message::OpenCommandPalette => {
+                if self.command_palette.take().is_none() {
+                    self.command_palette = Some(CommandPalette {
+                        query: String::new(),
+                        selected: 0,
+                        entries: self.palette_entries(),
+                    });
+                    return widget::text_input::focus(self.command_palette_input_id.clone());
+                }
+            }
+            Message::CloseCommandPalette => {
+                self.command_palette = None;
+            }
+            Message::CommandPaletteQueryChanged(query) => {
+                if let Some(palette) = self.command_palette.as_mut() {
+                    palette.query = query;
+                    palette.selected = 0;
+                }
+            }
+            Message::RunPaletteCommand(index) => {
+                if let Some(palette) = self.command_palette.take() {
+                    let entry = crate::ui::commands::filter(&palette.entries, &palette.query).into_iter().nth(index);
+                    if let Some(entry) = entry {
+                        return self.update(entry.message);
                     }
+                }
+            }
+            Message::RunSelectedPaletteCommand => {
+                if let Some(selected) = self.command_palette.as_ref().map(|palette| palette.selected) {
+                    return self.update(Message::RunPaletteCommand(selected));
+                }
+            }
+            Message::StartPersonaChat(name) => {
+                let mut persona_names: Vec<String> = self.config.personas.keys().cloned().collect();
+                persona_names.sort();
+                if let Some(index) = persona_names.iter().position(|persona| *persona == name) {
+                    let new_conversation = self.update(Message::NewConversation);
+                    return app::Task::batch([new_conversation, self.update(Message::SelectPersona(index + 1))]);
+                }
+            }
+            Message::ExportConversation => {
+                let Some(conversation) = self.export_conversation() else {
+                    self.current_error = Some("Nothing to export yet".to_string());
+                    return app::Task::none();
+                };
+                return save_markdown(&conversation.title, conversation.to_markdown());
+            }
+            Message::HistoryFolderFilterSelected(index) => {
+                self.history_folder_filter = match index {
+                    0 => FolderFilter::All,
+                    1 => FolderFilter::Unfiled,
+                    i => history_folders(&self.history_rows).into_iter().nth(i - 2).map(FolderFilter::Folder).unwrap_or_default(),
+                };
+                self.history_selection.clear();
+            }
+            Message::HistoryTagFilterSelected(index) => {
+                self.history_tag_filter = index.checked_sub(1).and_then(|i| history_tags(&self.history_rows).into_iter().nth(i));
+                self.history_selection.clear();
+            }
+            Message::HistoryShowArchivedToggled(show) => {
+                self.history_show_archived = show;
+                self.history_selection.clear();
+            }
+            Message::ToggleConversationSelected(id, selected) => {
+                if selected {
+                    self.history_selection.insert(id);
                 } else {
-                    // Clear search results if query is empty
-                    self.search_results.clear();
+                    self.history_selection.remove(&id);
                 }
             }
-            Message::SearchResults(results) => {
-                self.search_results = results;
+            Message::SelectAllConversations => {
+                self.history_selection = self.visible_conversations().into_iter().map(|conv| conv.id).collect();
+            }
+            Message::ClearConversationSelection => {
+                self.history_selection.clear();
+            }
+            Message::PinConversation(id, pinned) => {
+                if let Err(e) = self.storage.set_conversation_pinned(&id, pinned) {
+                    eprintln!("Failed to pin conversation: {}", e);
+                }
+            }
+            Message::EditConversationLabels(id) => {
+                if let Some(conv) = self.history_row(&id) {
+                    self.history_labels_edit = Some((id, conv.folder.clone().unwrap_or_default(), conv.tags.join(", ")));
+                }
+            }
+            Message::ConversationFolderInput(folder) => {
+                if let Some((_, edited, _)) = self.history_labels_edit.as_mut() {
+                    *edited = folder;
+                }
+            }
+            Message::ConversationTagsInput(tags) => {
+                if let Some((_, _, edited)) = self.history_labels_edit.as_mut() {
+                    *edited = tags;
+                }
+            }
+            Message::SaveConversationLabels => {
+                if let Some((id, folder, tags)) = self.history_labels_edit.take() {
+                    let folder = folder.trim();
+                    if let Err(e) = self.storage.set_conversation_folder(&id, Some(folder).filter(|f| !f.is_empty())) {
+                        eprintln!("Failed to move conversation to folder: {}", e);
+                    }
+                    if let Err(e) = self.storage.set_conversation_tags(&id, &parse_tags(&tags)) {
+                        eprintln!("Failed to tag conversation: {}", e);
+                    }
+                }
+            }
+            Message::CancelConversationLabels => {
+                self.history_labels_edit = None;
+            }
+            Message::ArchiveSelectedConversations(archived) => {
+                for id in std::mem::take(&mut self.history_selection) {
+                    if let Err(e) = self.storage.set_conversation_archived(&id, archived) {
+                        eprintln!("Failed to archive conversation: {}", e);
+                    }
+                }
+            }
+            Message::DeleteSelectedConversations => {
+                for id in std::mem::take(&mut self.history_selection) {
+                    let _ = self.update(Message::DeleteConversation(id));
+                }
+            }
+            Message::ExportSelectedConversations => {
+                let mut documents = Vec::new();
+                for conv in self.visible_conversations().into_iter().filter(|conv| self.history_selection.contains(&conv.id)) {
+                    match self.storage.get_conversation(&conv.id) {
+                        Ok(Some(mut conversation)) => {
+                            // The index has the latest title
+                            conversation.title = conv.title.clone();
+                            documents.push(conversation.to_markdown());
+                        }
+                        Ok(None) => {}
+                        Err(e) => eprintln!("Failed to load conversation {}: {}", conv.id, e),
+                    }
+                }
+                if documents.is_empty() {
+                    return app::Task::none();
+                }
+                return save_markdown("Conversations", documents.join("\n---\n\n"));
+            }
+            Message::ConversationExported(result) => {
+                match result {
+                    Ok(path) => println!("💾 Exported conversation to {}", path),
+                    Err(e) => {
+                        eprintln!("❌ {}", e);
+                        self.current_error = Some(e);
+                    }
+                }
             }
         }
         
@@ -1332,28 +2542,61 @@ impl Application for CosmicLlmApp {
     }
 
     fn view(&self) -> Element<Self::Message> {
+        // Main content area
+        let page = match self.current_page {
+            NavigationPage::Chat => self.chat_view(),
+            NavigationPage::History => self.history_view(),
+            NavigationPage::MCPConfig => self.mcp_config_view(),
+            NavigationPage::Settings => self.settings_page.view(&self.config, self.settings_changed).map(Message::SettingsMessage),
+        };
+        
+        // Surface configuration files that failed to load instead of silently using defaults
+        let page: Element<Message> = if self.config_errors.is_empty() {
+            page
+        } else {
+            let mut errors = cosmic::widget::column::with_capacity(self.config_errors.len() + 1).spacing(4);
+            for (file, error) in &self.config_errors {
+                errors = errors.push(
+                    cosmic::widget::row::with_capacity(2)
+                        .push(
+                            cosmic::widget::text(format!("⚠️ {}: {}", file, error))
+                                .size(13)
+                                .class(cosmic::style::Text::Color(cosmic::iced::Color::from_rgb(0.8, 0.2, 0.2)))
+                        )
+                        .push_maybe((file == "config.toml" && self.pending_config.is_some()).then(|| {
+                            widget::button::text("Load from disk")
+                                .on_press(Message::ApplyPendingConfig)
+                        }))
+                        .spacing(8)
+                        .align_y(cosmic::iced::Alignment::Center)
+                );
+            }
+            cosmic::widget::column::with_capacity(2)
+                .push(widget::container(errors).padding(8))
+                .push(page)
+                .into()
+        };
+        
         // Main layout with side panel and content area
-        let mut content = cosmic::widget::row::with_capacity(1)
-            .push(
-                // Main content area
-                match self.current_page {
-                    NavigationPage::Chat => self.chat_view(),
-                    NavigationPage::History => self.history_view(),
-                    NavigationPage::MCPConfig => self.mcp_config_view(),
-                    NavigationPage::Settings => self.settings_page.view(&self.config).map(Message::SettingsMessage),
-                }
-            );
+        let mut content = cosmic::widget::row::with_capacity(1).push(page);
 
         // Add dialog overlay if dialog is open
         if let Some(dialog_page) = &self.dialog {
             content = content.push(
                 dialog_page.view(&self.dialog_text_input_id)
             );
+        } else if let Some(palette) = &self.command_palette {
+            content = content.push(self.command_palette_view(palette));
         }
 
         content.into()
     }
 
+    fn on_escape(&mut self) -> app::Task<Self::Message> {
+        self.command_palette = None;
+        app::Task::none()
+    }
+
     fn header_start(&self) -> Vec<Element<Self::Message>> {
         vec![self.create_menu_bar()]
     }
@@ -1420,6 +2663,16 @@ impl Application for CosmicLlmApp {
                     Message::CloseAbout,
                 )
                 .title(self.context_page.title()),  // Dynamic title from ContextPage (pattern from msToDO)
+                ContextPage::PromptTemplates => app::context_drawer::context_drawer(
+                    self.prompt_templates_view(),
+                    Message::CloseAbout,
+                )
+                .title(self.context_page.title()),
+                ContextPage::ConversationSettings => app::context_drawer::context_drawer(
+                    self.conversation_settings_view(),
+                    Message::CloseAbout,
+                )
+                .title(self.context_page.title()),
             })
         }
     }
@@ -1427,6 +2680,464 @@ impl Application for CosmicLlmApp {
 
 impl CosmicLlmApp {
 
+    /// Build the client for `profile`, with the configured retries and its fallback profile
+    fn set_llm_client(&mut self, profile: LlmProfile) {
+        match crate::llm::retry::RetryingClient::for_profile(profile, &self.config) {
+            Ok(client) => {
+                self.llm_client = client;
+                self.config_errors.remove("backend");
+            }
+            Err(e) => {
+                eprintln!("❌ Cannot create LLM client: {}", e);
+                self.llm_client = Arc::new(crate::llm::UnavailableClient::new(e.to_string()));
+                self.config_errors.insert("backend".to_string(), e.to_string());
+            }
+        }
+    }
+    
+    /// Persona the open conversation was started with
+    fn active_persona(&self) -> Option<&Persona> {
+        self.conversation_settings.persona.as_deref().and_then(|name| self.config.get_persona(name))
+    }
+    
+    /// Name of the profile the open conversation talks to
+    fn conversation_profile_name(&self) -> String {
+        self.conversation_settings.profile.clone()
+            .or_else(|| self.active_persona().and_then(|persona| persona.profile.clone()))
+            .filter(|name| self.config.profiles.contains_key(name))
+            .unwrap_or_else(|| self.config.default.clone())
+    }
+    
+    /// The conversation's profile with its own, then its persona's, temperature and max tokens applied
+    fn conversation_profile(&self) -> Option<LlmProfile> {
+        let mut profile = self.config.get_profile(&self.conversation_profile_name())?.clone();
+        let persona = self.active_persona();
+        if let Some(temperature) = self.conversation_settings.temperature.or(persona.and_then(|p| p.temperature)) {
+            profile.temperature = Some(temperature);
+        }
+        if let Some(max_tokens) = self.conversation_settings.max_tokens.or(persona.and_then(|p| p.max_tokens)) {
+            profile.max_tokens = Some(max_tokens);
+        }
+        Some(profile)
+    }
+    
+    /// Rebuild the client for the open conversation
+    fn apply_conversation_profile(&mut self) {
+        if let Some(profile) = self.conversation_profile() {
+            self.set_llm_client(profile);
+        }
+    }
+    
+    /// Replace the chat with a page of stored messages
+    fn show_message_page(&mut self, page: MessagePage) {
+        self.expanded_reasoning.clear();
+        self.highlighted_message = None;
+        self.messages = page.messages.iter().map(chat_message).collect();
+        self.first_loaded_message_id = page.first_id;
+        self.last_loaded_message_id = page.last_id;
+        self.has_older_messages = page.has_older;
+        self.has_newer_messages = page.has_newer;
+    }
+    
+    fn reload_history_rows(&mut self) {
+        self.history_rows = self.storage.list_conversations_from_index().unwrap_or_else(|e| {
+            eprintln!("Failed to list conversations: {}", e);
+            Vec::new()
+        });
+    }
+    
+    /// Re-read one History row after a change, keeping the most recently active first
+    fn refresh_history_row(&mut self, id: &Uuid) {
+        let row = match self.storage.conversation_index(id) {
+            Ok(row) => row,
+            Err(e) => {
+                eprintln!("Failed to load conversation {}: {}", id, e);
+                return;
+            }
+        };
+        self.history_rows.retain(|conv| conv.id != *id);
+        if let Some(row) = row {
+            self.history_rows.push(row);
+            self.history_rows.sort_by(|a, b| b.updated_at.cmp(&a.updated_at).then(b.created_at.cmp(&a.created_at)));
+        }
+    }
+    
+    fn history_row(&self, id: &Uuid) -> Option<&ConversationIndex> {
+        self.history_rows.iter().find(|conv| conv.id == *id)
+    }
+    
+    /// Switch to the settings of a conversation being opened (or of a new chat)
+    fn load_conversation_settings(&mut self, settings: ConversationSettings) {
+        self.temperature_input = settings.temperature.map(|t| t.to_string()).unwrap_or_default();
+        self.max_tokens_input = settings.max_tokens.map(|t| t.to_string()).unwrap_or_default();
+        self.system_prompt_input = settings.system_prompt.clone().unwrap_or_default();
+        self.conversation_settings = settings;
+        self.apply_conversation_profile();
+    }
+    
+    /// Notify about the open conversation when notifications are on and the window is in the background;
+    /// clicking the notification brings the conversation back
+    fn notify_in_background(&self, summary: &str, body: String) -> app::Task<Message> {
+        let Some(conv_id) = self.current_conversation_id else {
+            return app::Task::none();
+        };
+        if !self.config.preferences.notifications || self.window_focused {
+            return app::Task::none();
+        }
+        let summary = summary.to_string();
+        cosmic::Task::perform(
+            async move {
+                if crate::ui::notifications::notify(summary, body).await {
+                    cosmic::Action::App(Message::NotificationClicked(conv_id))
+                } else {
+                    cosmic::Action::None
+                }
+            },
+            |msg| msg,
+        )
+    }
+    
+    /// Write the servers edited on the Settings page to mcp_config.json and reconnect what changed
+    fn save_mcp_config(&mut self) -> app::Task<Message> {
+        if let Err(e) = self.settings_page.mcp_config.save() {
+            eprintln!("Failed to save mcp_config.json: {}", e);
+            self.settings_page.mcp_error = Some(format!("Failed to save mcp_config.json: {}", e));
+            return app::Task::none();
+        }
+        let mut mcp_config = self.settings_page.mcp_config.clone();
+        mcp_config.expand_env_vars();
+        let mcp_registry = self.mcp_registry.clone();
+        cosmic::Task::perform(
+            async move {
+                mcp_registry.write().await.apply_config(&mcp_config).await;
+                cosmic::Action::App(Message::RefreshMCPTools)
+            },
+            |msg| msg,
+        )
+    }
+    
+    /// Shortcuts from config.toml on top of the defaults; unusable entries are reported like other config errors
+    fn apply_key_binds(&mut self) {
+        let (key_binds, errors) = crate::ui::commands::key_binds(&self.config.keybindings);
+        self.key_binds = key_binds;
+        if errors.is_empty() {
+            self.config_errors.remove("keybindings");
+        } else {
+            self.config_errors.insert("keybindings".to_string(), errors.join("; "));
+        }
+    }
+    
+    /// Commands, profiles, personas, tools and conversations the command palette can run
+    fn palette_entries(&self) -> Vec<crate::ui::commands::PaletteEntry> {
+        use crate::ui::commands::{PaletteEntry, COMMANDS};
+        
+        let mut entries = Vec::new();
+        for command in COMMANDS.iter().filter(|command| command.action != MenuAction::CommandPalette) {
+            let shortcut = self.key_binds.iter()
+                .find(|(_, action)| **action == command.action)
+                .map(|(key_bind, _)| key_bind.to_string());
+            entries.push(PaletteEntry {
+                label: command.label.to_string(),
+                shortcut,
+                message: menu::Action::message(&command.action),
+            });
+        }
+        
+        // Same order as the profile dropdown
+        let mut profile_names: Vec<&String> = self.config.profiles.keys().collect();
+        profile_names.sort();
+        for (index, name) in profile_names.into_iter().enumerate() {
+            let model = self.config.profiles.get(name).map(|profile| profile.model.clone()).unwrap_or_default();
+            entries.push(PaletteEntry {
+                label: format!("Switch to profile: {} ({})", name, model),
+                shortcut: None,
+                message: Message::ChangeConversationProfile(index),
+            });
+        }
+        
+        let mut persona_names: Vec<&String> = self.config.personas.keys().collect();
+        persona_names.sort();
+        for name in persona_names {
+            entries.push(PaletteEntry {
+                label: format!("New chat with persona: {}", name),
+                shortcut: None,
+                message: Message::StartPersonaChat(name.clone()),
+            });
+        }
+        
+        for tool in &self.available_mcp_tools {
+            let enabled = self.tool_states.get(&tool.name).copied().unwrap_or(true);
+            entries.push(PaletteEntry {
+                label: format!("{} tool: {}", if enabled { "Disable" } else { "Enable" }, tool.name),
+                shortcut: None,
+                message: Message::ToggleTool(tool.name.clone(), !enabled),
+            });
+        }
+        
+        for conversation in &self.history_rows {
+            entries.push(PaletteEntry {
+                label: format!("Open conversation: {}", conversation.title),
+                shortcut: None,
+                message: Message::SelectConversation(conversation.id),
+            });
+        }
+        entries
+    }
+    
+    fn command_palette_view<'a>(&'a self, palette: &'a CommandPalette) -> widget::Dialog<'a, Message> {
+        const MAX_ROWS: usize = 10;
+        let hint_color = cosmic::style::Text::Color(cosmic::iced::Color::from_rgb(0.6, 0.6, 0.6));
+        let matches = crate::ui::commands::filter(&palette.entries, &palette.query);
+        
+        let mut list = cosmic::widget::column::with_capacity(MAX_ROWS + 1).spacing(2);
+        for (index, entry) in matches.iter().take(MAX_ROWS).enumerate() {
+            let row = cosmic::widget::row::with_capacity(3)
+                .push(cosmic::widget::text(entry.label.clone()).size(14))
+                .push(cosmic::widget::Space::with_width(Length::Fill))
+                .push_maybe(entry.shortcut.clone().map(|shortcut| cosmic::widget::text(shortcut).size(12).class(hint_color.clone())));
+            list = list.push(
+                widget::button::custom(row)
+                    .on_press(Message::RunPaletteCommand(index))
+                    .width(Length::Fill)
+                    .class(if index == palette.selected { cosmic::style::Button::Suggested } else { cosmic::style::Button::Text })
+            );
+        }
+        if matches.is_empty() {
+            list = list.push(cosmic::widget::text("No matching commands").size(13).class(hint_color.clone()));
+        } else if matches.len() > MAX_ROWS {
+            list = list.push(cosmic::widget::text(format!("{} more…", matches.len() - MAX_ROWS)).size(12).class(hint_color));
+        }
+        
+        widget::dialog()
+            .title("Command Palette")
+            .control(
+                cosmic::widget::column::with_capacity(2)
+                    .push(
+                        widget::text_input("Type a command, profile, persona, tool or conversation", &palette.query)
+                            .id(self.command_palette_input_id.clone())
+                            .on_input(Message::CommandPaletteQueryChanged)
+                            .on_submit(|_| Message::RunSelectedPaletteCommand)
+                    )
+                    .push(list)
+                    .spacing(8)
+            )
+            .secondary_action(
+                widget::button::standard("Close")
+                    .on_press(Message::CloseCommandPalette)
+            )
+    }
+    
+    /// The open chat as a stored conversation, for export; None before anything was said
+    fn export_conversation(&self) -> Option<crate::storage::conversation_storage::Conversation> {
+        let title = self.current_conversation_id
+            .and_then(|id| self.history_row(&id))
+            .map(|conversation| conversation.title.clone())
+            .unwrap_or_else(|| "New Chat".to_string());
+        let mut conversation = crate::storage::conversation_storage::Conversation::new(title);
+        for msg in self.messages.iter().filter(|msg| !msg.is_error) {
+            let role = if msg.is_user { "user" } else if msg.is_notice { "notice" } else { "assistant" };
+            conversation.add_message(role.to_string(), msg.content.clone());
+        }
+        // Only the welcome message
+        if !conversation.messages.iter().any(|msg| msg.role == "user") {
+            return None;
+        }
+        Some(conversation)
+    }
+    
+    /// The open conversation, unless it is kept out of the history
+    fn stored_conversation_id(&self) -> Option<Uuid> {
+        self.current_conversation_id.filter(|_| !self.off_the_record)
+    }
+    
+    /// Earlier turns to send with a new message; the system prompt is rendered before sending.
+    /// A stored conversation is read in full, since only a page of it may be on screen.
+    fn request_history(&self) -> Vec<crate::llm::Message> {
+        if let Some(conv_id) = self.stored_conversation_id() {
+            match self.storage.get_conversation(&conv_id) {
+                Ok(Some(conversation)) => {
+                    return conversation
+                        .messages
+                        .into_iter()
+                        .filter_map(|msg| {
+                            let role = match msg.role.as_str() {
+                                "user" => crate::llm::Role::User,
+                                "assistant" => crate::llm::Role::Assistant,
+                                _ => return None,
+                            };
+                            Some(crate::llm::Message::new(role, msg.content))
+                        })
+                        .collect();
+                }
+                Ok(None) => {}
+                Err(e) => eprintln!("Failed to load conversation history: {}", e),
+            }
+        }
+        self.messages
+            .iter()
+            .filter(|msg| !msg.is_notice && !msg.is_error)
+            .map(|msg| {
+                let role = if msg.is_user { crate::llm::Role::User } else { crate::llm::Role::Assistant };
+                crate::llm::Message::new(role, msg.content.clone())
+            })
+            .collect()
+    }
+    
+    /// Persist the settings of the open conversation; a new chat keeps them in memory until it is created
+    fn save_conversation_settings(&self) {
+        if let Some(conv_id) = self.stored_conversation_id() {
+            if let Err(e) = self.storage.update_conversation_settings(&conv_id, &self.conversation_settings) {
+                eprintln!("Failed to save conversation settings: {}", e);
+            }
+        }
+    }
+    
+    /// Put the rendered system prompt in front of `llm_messages`, then start streaming them
+    fn prepare_generation(&mut self, mut llm_messages: Vec<crate::llm::Message>) -> app::Task<Message> {
+        let conv_id = self.current_conversation_id;
+        let request_id = Uuid::new_v4();
+        self.preparing_generation = Some(request_id);
+        // The conversation's system prompt override, its persona's, or the one from system_prompt.md
+        let prompt = self.conversation_settings.system_prompt.clone()
+            .or_else(|| self.active_persona().and_then(|persona| persona.system_prompt.clone()))
+            .or_else(|| self.prompt_manager.get_system_prompt().map(str::to_string));
+        let Some(prompt) = prompt else {
+            return self.update(Message::GenerationPrepared(request_id, conv_id, llm_messages));
+        };
+        let prompt_manager = self.prompt_manager.clone();
+        let mcp_registry = self.mcp_registry.clone();
+        let persona_name = self.conversation_settings.persona.clone();
+        let persona = self.active_persona().cloned();
+        
+        cosmic::Task::perform(
+            async move {
+                let tools: Vec<String> = {
+                    let registry = mcp_registry.read().await;
+                    registry.get_enabled_tools().into_iter()
+                        .map(|tool| tool.name)
+                        .filter(|name| {
+                            let server_name = registry.get_route_for_tool(name).map(|route| route.server_name.as_str()).unwrap_or_default();
+                            persona.as_ref().is_none_or(|persona| persona.allows_tool(name, server_name))
+                        })
+                        .collect()
+                };
+                let values = crate::prompts::system_values(persona_name.as_deref(), &tools);
+                let system_prompt = prompt_manager.render_system_prompt(&prompt, &values);
+                llm_messages.insert(0, crate::llm::Message::new(crate::llm::Role::System, system_prompt));
+                cosmic::Action::App(Message::GenerationPrepared(request_id, conv_id, llm_messages))
+            },
+            |msg| msg,
+        )
+    }
+    
+    /// Close the current turn: archive its tool calls under the assistant bubble and
+    /// persist it. A cancelled turn keeps its partial output and is stored as incomplete.
+    fn finish_current_turn(&mut self, cancelled: bool) {
+        // Stop can arrive after the turn already ended; that turn stays as it finished
+        let turn_in_progress = self.current_ai_message_index.is_some();
+        if cancelled {
+            for tc in self.active_tool_calls.iter_mut().filter(|tc| tc.status == ToolCallStatus::Started) {
+                tc.status = ToolCallStatus::Error;
+                tc.error = Some("Cancelled".to_string());
+            }
+        }
+        let turn_tools = self.active_tool_calls.clone();
+        
+        // Archive active tools under current AI bubble
+        if let Some(anchor) = self.current_ai_message_index {
+            for tc in self.active_tool_calls.drain(..) {
+                self.archived_tool_calls.push(AnchoredToolCall { anchor_index: anchor, tool_call: tc });
+            }
+            let no_text = self.messages.get(anchor).is_some_and(|m| !m.is_user && m.content.trim().is_empty());
+            // A bubble holding only reasoning still has something to show
+            let is_empty = no_text && self.messages.get(anchor).is_some_and(|m| m.reasoning.trim().is_empty());
+            if cancelled {
+                // Leave a visible marker so the partial answer is not mistaken for a complete one
+                if let Some(msg) = self.messages.get_mut(anchor) {
+                    if !msg.is_user {
+                        if no_text {
+                            msg.content = "⏹️ *Generation stopped*".to_string();
+                        } else {
+                            msg.content.push_str("\n\n⏹️ *Generation stopped*");
+                        }
+                    }
+                }
+            } else if is_empty {
+                // If the assistant bubble has nothing to show, remove it and shift everything indexed after it
+                self.messages.remove(anchor);
+                for anchored in &mut self.archived_tool_calls {
+                    if anchored.anchor_index > anchor {
+                        anchored.anchor_index -= 1;
+                    } else if anchored.anchor_index == anchor {
+                        anchored.anchor_index = anchor.saturating_sub(1);
+                    }
+                }
+                self.expanded_reasoning = self.expanded_reasoning
+                    .iter()
+                    .filter(|&&i| i != anchor)
+                    .map(|&i| if i > anchor { i - 1 } else { i })
+                    .collect();
+                self.highlighted_message = match self.highlighted_message {
+                    Some(i) if i == anchor => None,
+                    Some(i) if i > anchor => Some(i - 1),
+                    other => other,
+                };
+            }
+        } else {
+            self.active_tool_calls.clear();
+        }
+        
+        if let Some(turn) = self.turns.last_mut().filter(|turn| turn_in_progress && !turn.complete) {
+            turn.complete = !cancelled;
+            turn.tools.extend(turn_tools);
+            // Persist turn to storage
+            if let Some(conv_id) = self.stored_conversation_id() {
+                let storage_tools: Vec<crate::storage::conversation_storage::ToolCallInfo> = turn.tools.iter().map(|tc| {
+                    crate::storage::conversation_storage::ToolCallInfo {
+                        id: tc.id.clone(),
+                        tool_name: tc.tool_name.clone(),
+                        parameters: tc.parameters.clone(),
+                        status: match tc.status {
+                            ToolCallStatus::Started => crate::storage::conversation_storage::ToolCallStatus::Started,
+                            ToolCallStatus::Completed => crate::storage::conversation_storage::ToolCallStatus::Completed,
+                            ToolCallStatus::Error => crate::storage::conversation_storage::ToolCallStatus::Error,
+                        },
+                        result: tc.result.clone(),
+                        error: tc.error.clone(),
+                    }
+                }).collect();
+                
+                let storage_turn = crate::storage::conversation_storage::Turn {
+                    id: turn.id,
+                    iteration: turn.iteration,
+                    text: turn.text.clone(),
+                    complete: turn.complete,
+                    tools: storage_tools,
+                };
+                let _ = self.storage.add_turn_to_conversation(&conv_id, storage_turn);
+            }
+        }
+        self.current_ai_message_index = None;
+    }
+    
+    /// Copy tools, their enablement and conflicts from a registry snapshot into UI state
+    fn apply_mcp_snapshot(&mut self, snapshot: crate::mcp::registry::MCPRegistrySnapshot) {
+        self.available_mcp_tools = snapshot.tools;
+        self.tool_states = snapshot.tool_states;
+        self.server_states = snapshot.server_states;
+        self.tool_servers = snapshot.tool_servers;
+        self.tool_conflicts = snapshot.conflicts;
+    }
+
+    /// Whether a tool will be offered to the model (tool and its server enabled)
+    fn is_tool_active(&self, tool_name: &str) -> bool {
+        let server_enabled = self.tool_servers.get(tool_name)
+            .and_then(|server| self.server_states.get(server))
+            .copied()
+            .unwrap_or(true);
+        server_enabled && self.tool_states.get(tool_name).copied().unwrap_or(true)
+    }
+
     fn create_menu_bar(&self) -> Element<Message> {
         use cosmic::widget::menu::{items, root, Item, ItemHeight, ItemWidth, MenuBar, Tree};
         use cosmic::widget::RcElementWrapper;
@@ -1437,6 +3148,17 @@ impl CosmicLlmApp {
                 items(
                     &self.key_binds,
                     vec![
+                        Item::Button(
+                            "New Conversation",
+                            None,
+                            MenuAction::NewConversation,
+                        ),
+                        Item::Button(
+                            "Export Conversation…",
+                            None,
+                            MenuAction::ExportConversation,
+                        ),
+                        Item::Divider,
                         Item::Button(
                             "Quit",
                             None,
@@ -1450,6 +3172,11 @@ impl CosmicLlmApp {
                 items(
                     &self.key_binds,
                     vec![
+                        Item::Button(
+                            "Command Palette",
+                            None,
+                            MenuAction::CommandPalette,
+                        ),
                         Item::Button(
                             "Settings",
                             None,
@@ -1478,6 +3205,28 @@ impl CosmicLlmApp {
         .into()
     }
 
+    /// Assistant message content rendered as markdown
+    fn markdown_view<'a>(&'a self, content: &'a String) -> Element<'a, Message> {
+        widget::container(
+            widget::lazy(content, |_| {
+                let items = markdown::parse(content).collect::<Vec<_>>();
+                let style = widget::markdown::Style {
+                    inline_code_padding: cosmic::iced::Padding::from([1, 2]),
+                    inline_code_highlight: widget::markdown::Highlight {
+                        background: cosmic::iced::Background::Color(cosmic::iced::Color::from_rgb(0.1, 0.1, 0.1)),
+                        border: cosmic::iced::Border::default().rounded(2),
+                    },
+                    inline_code_color: cosmic::iced::Color::WHITE,
+                    link_color: cosmic::iced::Color::from_rgb(0.3, 0.6, 1.0),
+                };
+                widget::markdown(&items, widget::markdown::Settings::default(), style)
+                    .map(Message::MarkdownLinkClicked)
+            })
+        )
+        .width(Length::Fill)
+        .into()
+    }
+
     fn chat_view(&self) -> Element<Message> {
         use cosmic::iced::{Length, Padding};
         
@@ -1493,10 +3242,33 @@ impl CosmicLlmApp {
             .push(
                 // Messages area with better styling
                 {
-                    let mut column = cosmic::widget::column::with_capacity(self.messages.len()).spacing(12);
+                    let mut column = cosmic::widget::column::with_capacity(self.messages.len() + 1).spacing(12);
+                    
+                    if self.has_older_messages {
+                        column = column.push(
+                            cosmic::widget::container(
+                                widget::button::text("Load earlier messages")
+                                    .on_press(Message::LoadOlderMessages)
+                            )
+                            .width(Length::Fill)
+                            .align_x(cosmic::iced::Alignment::Center)
+                        );
+                    }
                     
                     // Add regular chat messages
                     for (i, msg) in self.messages.iter().enumerate() {
+                        if msg.is_notice {
+                            column = column.push(
+                                cosmic::widget::container(
+                                    cosmic::widget::text(&msg.content)
+                                        .size(12)
+                                        .class(cosmic::style::Text::Color(cosmic::iced::Color::from_rgb(0.5, 0.5, 0.5)))
+                                )
+                                .width(Length::Fill)
+                                .center_x(Length::Fill)
+                            );
+                            continue;
+                        }
                         let content = msg.content.clone();
                         let message_widget = cosmic::widget::container(
                             {
@@ -1516,25 +3288,31 @@ impl CosmicLlmApp {
                                     )
                                     .width(Length::Fill)
                                     .into()
+                                } else if !msg.reasoning.is_empty() {
+                                    let expanded = self.expanded_reasoning.contains(&i);
+                                    let mut column = cosmic::widget::column::with_capacity(3)
+                                        .push(
+                                            cosmic::widget::button::text(if expanded { "💭 Thinking ▾" } else { "💭 Thinking ▸" })
+                                                .on_press(Message::ToggleReasoning(i))
+                                                .padding(2)
+                                                .class(cosmic::style::Button::Text)
+                                        )
+                                        .spacing(8);
+                                    if expanded {
+                                        column = column.push(
+                                            cosmic::widget::text(&msg.reasoning)
+                                                .size(12)
+                                                .class(cosmic::style::Text::Color(
+                                                    cosmic::theme::active().cosmic().palette.neutral_6.into()
+                                                ))
+                                        );
+                                    }
+                                    column
+                                        .push(self.markdown_view(&msg.content))
+                                        .width(Length::Fill)
+                                        .into()
                                 } else {
-                                    widget::container(
-                                        widget::lazy(&msg.content, |_| {
-                                            let items = markdown::parse(&msg.content).collect::<Vec<_>>();
-                                            let style = widget::markdown::Style {
-                                                inline_code_padding: cosmic::iced::Padding::from([1, 2]),
-                                                inline_code_highlight: widget::markdown::Highlight {
-                                                    background: cosmic::iced::Background::Color(cosmic::iced::Color::from_rgb(0.1, 0.1, 0.1)),
-                                                    border: cosmic::iced::Border::default().rounded(2),
-                                                },
-                                                inline_code_color: cosmic::iced::Color::WHITE,
-                                                link_color: cosmic::iced::Color::from_rgb(0.3, 0.6, 1.0),
-                                            };
-                                            widget::markdown(&items, widget::markdown::Settings::default(), style)
-                                                .map(Message::MarkdownLinkClicked)
-                                        })
-                                    )
-                                    .width(Length::Fill)
-                                    .into()
+                                    self.markdown_view(&msg.content)
                                 };
                                 
                                 cosmic::widget::row::with_capacity(2)
@@ -1557,6 +3335,24 @@ impl CosmicLlmApp {
                         })
                         .width(Length::FillPortion(7)); // 70% width
                         
+                        // Outline the message a search result pointed at
+                        let message_widget: Element<Message> = if self.highlighted_message == Some(i) {
+                            cosmic::widget::container(message_widget)
+                                .padding(2)
+                                .class(cosmic::style::Container::custom(|theme| cosmic::widget::container::Style {
+                                    border: cosmic::iced::Border {
+                                        color: theme.cosmic().accent_color().into(),
+                                        width: 2.0,
+                                        radius: theme.cosmic().corner_radii.radius_m.into(),
+                                    },
+                                    ..Default::default()
+                                }))
+                                .width(Length::FillPortion(7))
+                                .into()
+                        } else {
+                            message_widget.into()
+                        };
+                        
                         let message_row = if msg.is_user {
                             // User messages: right-aligned
                             cosmic::widget::row::with_capacity(2)
@@ -1645,6 +3441,17 @@ impl CosmicLlmApp {
                         }
                     }
                     
+                    if self.has_newer_messages {
+                        column = column.push(
+                            cosmic::widget::container(
+                                widget::button::text("Load later messages")
+                                    .on_press(Message::LoadNewerMessages)
+                            )
+                            .width(Length::Fill)
+                            .align_x(cosmic::iced::Alignment::Center)
+                        );
+                    }
+                    
                     // Add spacer at bottom to force scroll to bottom
                     column = column.push(
                         cosmic::widget::Space::with_height(Length::Fixed(1.0))
@@ -1655,6 +3462,10 @@ impl CosmicLlmApp {
                         .scrollbar_width(8)
                         .scrollbar_padding(4)
                         .id(self.scrollable_id.clone())
+                        .on_scroll(|viewport| {
+                            let end = viewport.absolute_offset().y + viewport.bounds().height;
+                            Message::ChatScrolled(end >= viewport.content_bounds().height - 8.0)
+                        })
                 }
                 .height(Length::Fill)
                 .width(Length::Fill)
@@ -1699,12 +3510,17 @@ impl CosmicLlmApp {
                         )
                         .push(
                             // Input row with buttons inline
-                            cosmic::widget::row::with_capacity(3)
+                            cosmic::widget::row::with_capacity(4)
                                 .push(
                                     // Attach file button (left side)
                                     widget::button::icon(crate::ui::icons::get_handle("mail-attachment-symbolic", 16))
                                         .on_press(Message::AttachFile)
                                 )
+                                .push(
+                                    // Prompt template picker
+                                    widget::button::icon(crate::ui::icons::get_handle("insert-text-symbolic", 16))
+                                        .on_press(Message::ShowPromptTemplates)
+                                )
                                 .push(
                                     // Text editor for message (multi-line)
                                     text_editor(&self.input_content)
@@ -1729,7 +3545,7 @@ impl CosmicLlmApp {
                                 )
                                 .push(
                                     // Send/Stop button (right side)
-                                    if self.is_streaming {
+                                    if self.is_streaming || self.preparing_generation.is_some() {
                                         // Stop button when streaming
                                         widget::button::icon(crate::ui::icons::get_handle("process-stop-symbolic", 16))
                                             .class(widget::button::ButtonClass::Destructive)
@@ -1759,33 +3575,26 @@ impl CosmicLlmApp {
         // Count enabled/disabled tools
         let total_tools = self.available_mcp_tools.len();
         let enabled_count = self.available_mcp_tools.iter()
-            .filter(|tool| self.tool_states.get(&tool.name).copied().unwrap_or(true))
+            .filter(|tool| self.is_tool_active(&tool.name))
             .count();
         
-        // Conversation info
-        let (title, created_text, msg_count) = if let Some(id) = self.current_conversation_id {
-            if let Ok(Some(conv)) = self.storage.get_conversation(&id) {
-                let created = conv.created_at.format("%Y-%m-%d %H:%M").to_string();
-                // Prefer the latest title from the on-disk index (updated by background tasks)
-                let index = self.storage.list_conversations_from_index().unwrap_or_else(|e| {
-                    eprintln!("Failed to list conversations: {}", e);
-                    Vec::new()
-                });
-                let latest_title = index
-                    .into_iter()
-                    .find(|ci| ci.id == id)
-                    .map(|ci| ci.title)
-                    .unwrap_or_else(|| conv.title.clone());
-                (latest_title, Some(created), conv.messages.len())
-            } else {
-                ("New Chat".to_string(), None, self.messages.len())
+        // Conversation info from its summary row (title updated by background tasks, activity kept by storage)
+        let summary = self.current_conversation_id.and_then(|id| self.history_row(&id));
+        let (title, activity) = match summary {
+            Some(conv) => {
+                let mut activity = format!(
+                    "{} messages · {}",
+                    conv.message_count,
+                    conv.updated_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")
+                );
+                if let Some(model) = &conv.last_model {
+                    activity.push_str(&format!(" · {}", model));
+                }
+                (conv.title.clone(), activity)
             }
-        } else {
-            ("New Chat".to_string(), None, self.messages.len())
+            None => ("New Chat".to_string(), format!("{} messages", self.messages.len())),
         };
         
-        let _created_label = created_text.unwrap_or_else(|| "".to_string());
-        
         cosmic::widget::container(
             cosmic::widget::column::with_capacity(2)
                 .push(
@@ -1796,7 +3605,7 @@ impl CosmicLlmApp {
                                 .size(18)
                         )
                         .push(
-                            cosmic::widget::text(format!("{} messages", msg_count))
+                            cosmic::widget::text(activity)
                                 .size(12)
                                 .class(cosmic::style::Text::Color(cosmic::iced::Color::from_rgb(0.4, 0.4, 0.4)))
                         )
@@ -1811,17 +3620,24 @@ impl CosmicLlmApp {
                         .align_y(cosmic::iced::Alignment::Center)
                 )
                 .push(
-                    // Bottom row: Model select, Tools summary with icons
-                    cosmic::widget::row::with_capacity(4)
+                    // Bottom row: Persona, Model select, Tools summary with icons
+                    cosmic::widget::row::with_capacity(5)
+                        .push_maybe(self.persona_selector())
                         .push(
-                            // Profile selection dropdown
+                            // Profile selection dropdown for this conversation
                             {
                                 let mut names: Vec<String> = self.config.profiles.keys().cloned().collect();
                                 names.sort();
-                                let idx = names.iter().position(|k| k == &self.config.default);
-                                widget::dropdown(names, idx, Message::ChangeDefaultProfile)
+                                let active = self.conversation_profile_name();
+                                let idx = names.iter().position(|k| k == &active);
+                                widget::dropdown(names, idx, Message::ChangeConversationProfile)
                             }
                         )
+                        .push(
+                            // Temperature, max tokens and system prompt of this conversation
+                            widget::button::icon(crate::ui::icons::get_handle("document-properties-symbolic", 16))
+                                .on_press(Message::ShowConversationSettings)
+                        )
                         .push(cosmic::widget::Space::with_width(Length::Fill))
                         .push(
                             // Tools summary with toggle and configure icons
@@ -1871,11 +3687,153 @@ impl CosmicLlmApp {
     }
 
 
+    /// Persona picker for a new chat, or the persona label once the conversation started
+    fn persona_selector(&self) -> Option<Element<Message>> {
+        if self.config.personas.is_empty() {
+            return None;
+        }
+        if self.current_conversation_id.is_some() {
+            let name = self.conversation_settings.persona.as_ref()?;
+            return Some(
+                cosmic::widget::text(format!("🧑 {}", name))
+                    .size(12)
+                    .into()
+            );
+        }
+        let mut names: Vec<String> = self.config.personas.keys().cloned().collect();
+        names.sort();
+        let selected = match &self.conversation_settings.persona {
+            Some(persona) => names.iter().position(|name| name == persona).map(|i| i + 1),
+            None => Some(0),
+        };
+        names.insert(0, "No persona".to_string());
+        Some(widget::dropdown(names, selected, Message::SelectPersona).into())
+    }
+    
+    /// Context drawer listing prompt templates, or the argument form of the picked one
+    fn prompt_templates_view(&self) -> Element<Message> {
+        let templates = self.prompt_manager.templates();
+        let hint_color = cosmic::style::Text::Color(cosmic::iced::Color::from_rgb(0.6, 0.6, 0.6));
+        
+        if let Some(template) = self.selected_template.and_then(|index| templates.get(index)) {
+            let mut form = cosmic::widget::column::with_capacity(self.template_values.len() * 2 + 2)
+                .push(cosmic::widget::text(format!("📝 {}", template.name)).size(16))
+                .spacing(8);
+            for (index, (field, value)) in self.template_values.iter().enumerate() {
+                form = form
+                    .push(cosmic::widget::text(field.clone()).size(12).class(hint_color.clone()))
+                    .push(
+                        widget::text_input(field.clone(), value)
+                            .on_input(move |value| Message::PromptTemplateFieldChanged(index, value))
+                    );
+            }
+            return form
+                .push(
+                    cosmic::widget::row::with_capacity(2)
+                        .push(
+                            widget::button::standard("Back")
+                                .on_press(Message::BackToPromptTemplates)
+                        )
+                        .push(
+                            widget::button::suggested("Insert")
+                                .on_press(Message::InsertPromptTemplate)
+                        )
+                        .spacing(8)
+                )
+                .into();
+        }
+        
+        if templates.is_empty() {
+            let dir = self.config.prompts.templates_dir.clone().unwrap_or_default();
+            return cosmic::widget::column::with_capacity(2)
+                .push(cosmic::widget::text("No prompt templates").size(14))
+                .push(
+                    cosmic::widget::text(format!("Add Markdown files with {{{{variables}}}} to {}", dir))
+                        .size(12)
+                        .class(hint_color)
+                )
+                .spacing(4)
+                .into();
+        }
+        
+        let mut list = cosmic::widget::column::with_capacity(templates.len()).spacing(4);
+        for (index, template) in templates.iter().enumerate() {
+            let preview: String = template.body.lines().next().unwrap_or_default().chars().take(60).collect();
+            list = list.push(
+                widget::button::custom(
+                    cosmic::widget::column::with_capacity(2)
+                        .push(cosmic::widget::text(template.name.clone()).size(14))
+                        .push(cosmic::widget::text(preview).size(12).class(hint_color.clone()))
+                        .spacing(2)
+                )
+                .on_press(Message::SelectPromptTemplate(index))
+                .width(Length::Fill)
+                .class(cosmic::style::Button::Text)
+            );
+        }
+        list.into()
+    }
+    
+    /// Context drawer with the profile overrides of the open conversation
+    fn conversation_settings_view(&self) -> Element<Message> {
+        let profile_name = self.conversation_profile_name();
+        let profile = self.config.get_profile(&profile_name);
+        let persona = self.active_persona();
+        let default_hint = |value: Option<String>| match value {
+            Some(value) => format!("Default: {}", value),
+            None => "Default".to_string(),
+        };
+        let label = |text: &'static str| {
+            cosmic::widget::text(text)
+                .size(12)
+                .class(cosmic::style::Text::Color(cosmic::iced::Color::from_rgb(0.6, 0.6, 0.6)))
+        };
+        
+        cosmic::widget::column::with_capacity(8)
+            .push(
+                cosmic::widget::text(match profile {
+                    Some(profile) => format!("🤖 {} ({})", profile_name, profile.model),
+                    None => format!("🤖 {}", profile_name),
+                })
+                .size(16)
+            )
+            .push(label("Temperature"))
+            .push(
+                widget::text_input(
+                    default_hint(persona.and_then(|p| p.temperature).or(profile.and_then(|p| p.temperature)).map(|t| t.to_string())),
+                    &self.temperature_input,
+                )
+                .on_input(Message::ConversationTemperatureChanged)
+            )
+            .push(label("Max tokens"))
+            .push(
+                widget::text_input(
+                    default_hint(persona.and_then(|p| p.max_tokens).or(profile.and_then(|p| p.max_tokens)).map(|t| t.to_string())),
+                    &self.max_tokens_input,
+                )
+                .on_input(Message::ConversationMaxTokensChanged)
+            )
+            .push(label("System prompt"))
+            .push(
+                widget::text_input(
+                    if persona.and_then(|p| p.system_prompt.as_ref()).is_some() { "Use the persona's prompt" } else { "Use system_prompt.md" },
+                    &self.system_prompt_input,
+                )
+                    .on_input(Message::ConversationSystemPromptChanged)
+            )
+            .push(
+                widget::button::suggested("Apply")
+                    .on_press(Message::ApplyConversationSettings)
+            )
+            .spacing(8)
+            .into()
+    }
+    
     fn tools_context_view(&self) -> Element<Message> {
         
         let total_tools = self.available_mcp_tools.len();
         let enabled_count = self.available_mcp_tools.iter()
-            .filter(|tool| self.tool_states.get(&tool.name).copied().unwrap_or(true))
+            .filter(|tool| self.is_tool_active(&tool.name))
             .count();
         
         cosmic::widget::column::with_capacity(3)
@@ -1934,10 +3892,12 @@ impl CosmicLlmApp {
                     
                     for tool in &self.available_mcp_tools {
                         let is_enabled = self.tool_states.get(&tool.name).copied().unwrap_or(true);
+                        let is_active = self.is_tool_active(&tool.name);
+                        let server_name = self.tool_servers.get(&tool.name).cloned().unwrap_or_default();
                         let tool_row = cosmic::widget::container(
                             cosmic::widget::column::with_capacity(3)
                                 .push(
-                                    cosmic::widget::row::with_capacity(2)
+                                    cosmic::widget::row::with_capacity(3)
                                         .push(
                                             cosmic::widget::toggler(is_enabled)
                                                 .on_toggle(|enabled| Message::ToggleTool(tool.name.clone(), enabled))
@@ -1945,12 +3905,17 @@ impl CosmicLlmApp {
                                         .push(
                                             cosmic::widget::text(&tool.name)
                                                 .size(14)
-                                                .class(if is_enabled {
+                                                .class(if is_active {
                                                     cosmic::style::Text::Default
                                                 } else {
                                                     cosmic::style::Text::Color(cosmic::iced::Color::from_rgb(0.5, 0.5, 0.5))
                                                 })
                                         )
+                                        .push(
+                                            cosmic::widget::text(server_name)
+                                                .size(10)
+                                                .class(cosmic::style::Text::Color(cosmic::iced::Color::from_rgb(0.6, 0.6, 0.6)))
+                                        )
                                         .spacing(8)
                                         .align_y(cosmic::iced::Alignment::Center)
                                 )
@@ -1976,12 +3941,9 @@ impl CosmicLlmApp {
     }
 
     fn history_view(&self) -> Element<Message> {
-        let conversations = self.storage.list_conversations_from_index().unwrap_or_else(|e| {
-            eprintln!("Failed to list conversations: {}", e);
-            Vec::new()
-        });
+        let conversations = self.visible_conversations();
         
-        cosmic::widget::column::with_capacity(3)
+        cosmic::widget::column::with_capacity(5)
             .push(
                 // Enhanced header with icon and stats
                 cosmic::widget::container(
@@ -2020,7 +3982,7 @@ impl CosmicLlmApp {
                                 .size(16)
                         )
                         .push(
-                            cosmic::widget::text_input("Search conversations...", &self.search_query)
+                            cosmic::widget::text_input("Search conversations... (role:, tag:, model:, before:, after:)", &self.search_query)
                                 .on_input(Message::SearchChanged)
                                 .width(Length::Fill)
                         )
@@ -2030,6 +3992,8 @@ impl CosmicLlmApp {
                 .padding(12)
                 .class(cosmic::style::Container::Card)
             )
+            .push(self.history_filter_bar())
+            .push_maybe(self.history_bulk_bar())
             .push(
                 // Enhanced conversations list or search results
                 {
@@ -2061,69 +4025,71 @@ impl CosmicLlmApp {
                                 .class(cosmic::style::Container::Card)
                             );
                         } else {
+                            let titles: std::collections::HashMap<Uuid, &str> = self.history_rows
+                                .iter()
+                                .map(|conv| (conv.id, conv.title.as_str()))
+                                .collect();
                             for result in &self.search_results {
                                 // Parse conversation ID to UUID
                                 if let Ok(conv_id) = Uuid::parse_str(&result.conversation_id) {
-                                    // Get conversation title
-                                    let title = if let Ok(Some(conv)) = self.storage.get_conversation(&conv_id) {
-                                        conv.title
-                                    } else {
-                                        "Unknown Conversation".to_string()
-                                    };
+                                    let title = titles.get(&conv_id).map(|title| title.to_string()).unwrap_or_else(|| "Unknown Conversation".to_string());
                                     
                                     // Format timestamp
                                     let date_str = chrono::DateTime::from_timestamp(result.timestamp, 0)
                                         .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
                                         .unwrap_or_else(|| "Unknown date".to_string());
-                                    
-                                    // Truncate content for preview
-                                    let preview = if result.content.chars().count() > 200 {
-                                        result.content.chars().take(200).collect::<String>() + "..."
-                                    } else {
-                                        result.content.clone()
+                                    let role = match result.role.as_str() {
+                                        "user" => "You",
+                                        "assistant" => "Assistant",
+                                        _ => "Notice",
                                     };
                                     
-                                    let search_result_card = cosmic::widget::container(
-                                        cosmic::widget::column::with_capacity(4)
+                                    let search_result_card = widget::button::custom(
+                                        cosmic::widget::column::with_capacity(2)
                                             .push(
-                                                cosmic::widget::row::with_capacity(3)
+                                                cosmic::widget::row::with_capacity(4)
                                                     .push(
-                                                        cosmic::widget::text(title.clone())
+                                                        cosmic::widget::text(title)
                                                             .size(16)
                                                     )
                                                     .push(cosmic::widget::Space::with_width(Length::Fill))
                                                     .push(
-                                                        cosmic::widget::text(date_str)
+                                                        cosmic::widget::text(format!("{} · {}", role, date_str))
                                                             .size(12)
                                                             .class(cosmic::style::Text::Color(cosmic::iced::Color::from_rgb(0.6, 0.6, 0.6)))
                                                     )
                                                     .align_y(cosmic::iced::Alignment::Center)
                                             )
-                                            .push(
-                                                cosmic::widget::text(preview)
-                                                    .size(12)
-                                                    .class(cosmic::style::Text::Color(cosmic::iced::Color::from_rgb(0.5, 0.5, 0.5)))
-                                            )
-                                            .push(
-                                                cosmic::widget::row::with_capacity(2)
-                                                    .push(cosmic::widget::Space::with_width(Length::Fill))
-                                                    .push(
-                                                        widget::button::icon(crate::ui::icons::get_handle("chat-bubble-text-symbolic", 16))
-                                                            .on_press(Message::SelectConversation(conv_id))
-                                                    )
-                                                    .spacing(8)
-                                                    .align_y(cosmic::iced::Alignment::Center)
-                                            )
+                                            .push(Self::search_snippet_view(result))
                                             .spacing(8)
                                     )
+                                    .on_press(Message::OpenSearchResult(conv_id, result.message_id))
                                     .padding(16)
-                                    .class(cosmic::style::Container::Card);
+                                    .width(Length::Fill)
+                                    .class(cosmic::style::Button::Standard);
                                     
                                     column = column.push(search_result_card);
                                 }
                             }
+                            if self.search_has_more {
+                                column = column.push(
+                                    cosmic::widget::container(
+                                        widget::button::standard("Load more results")
+                                            .on_press(Message::LoadMoreSearchResults)
+                                    )
+                                    .center_x(Length::Fill)
+                                );
+                            }
                         }
                         
+                        let ignored = SearchQuery::parse(&self.search_query).invalid;
+                        if !ignored.is_empty() {
+                            column = column.push(
+                                cosmic::widget::text(format!("Ignored filters: {} (dates are YYYY-MM-DD, roles are user, assistant or notice)", ignored.join(", ")))
+                                    .size(12)
+                                    .class(cosmic::style::Text::Color(cosmic::iced::Color::from_rgb(0.8, 0.5, 0.2)))
+                            );
+                        }
                         scrollable(column.spacing(8))
                     } else {
                         // Show normal conversation list
@@ -2137,11 +4103,15 @@ impl CosmicLlmApp {
                                                 .size(48)
                                         )
                                         .push(
-                                            cosmic::widget::text("No conversations yet")
+                                            cosmic::widget::text(if self.history_filtered() { "No matching conversations" } else { "No conversations yet" })
                                                 .size(16)
                                         )
                                         .push(
-                                            cosmic::widget::text("Start a new chat to create your first conversation!")
+                                            cosmic::widget::text(if self.history_filtered() {
+                                                "Try another folder or tag, or clear the filters."
+                                            } else {
+                                                "Start a new chat to create your first conversation!"
+                                            })
                                                 .size(12)
                                                 .class(cosmic::style::Text::Color(cosmic::iced::Color::from_rgb(0.6, 0.6, 0.6)))
                                         )
@@ -2152,56 +4122,19 @@ impl CosmicLlmApp {
                                 .class(cosmic::style::Container::Card)
                             );
                         } else {
-                            for conv in conversations {
-                                let title = conv.title.clone();
-                                let date_str = conv.updated_at.format("%Y-%m-%d %H:%M").to_string();
-                                let message_count = 0; // TODO: Get actual message count from conversation
-                                
-                                let conversation_card = cosmic::widget::container(
-                                    cosmic::widget::column::with_capacity(3)
-                                        .push(
-                                            cosmic::widget::row::with_capacity(3)
-                                                .push(
-                                                    cosmic::widget::text(title.clone())
-                                                        .size(16)
-                                                )
-                                                .push(cosmic::widget::Space::with_width(Length::Fill))
-                                                .push(
-                                                    cosmic::widget::text(date_str)
-                                                        .size(12)
-                                                        .class(cosmic::style::Text::Color(cosmic::iced::Color::from_rgb(0.6, 0.6, 0.6)))
-                                                )
-                                                .align_y(cosmic::iced::Alignment::Center)
-                                        )
-                                        .push(
-                                            cosmic::widget::row::with_capacity(2)
-                                                .push(
-                                                    cosmic::widget::text(format!("{} messages", message_count))
-                                                        .size(12)
-                                                        .class(cosmic::style::Text::Color(cosmic::iced::Color::from_rgb(0.5, 0.5, 0.5)))
-                                                )
-                                                .push(cosmic::widget::Space::with_width(Length::Fill))
-                                                .push(
-                                                    cosmic::widget::row::with_capacity(2)
-                                                        .push(
-                                                            widget::button::icon(crate::ui::icons::get_handle("chat-bubble-text-symbolic", 16))
-                                                                .on_press(Message::SelectConversation(conv.id))
-                                                        )
-                                                        .push(
-                                                            widget::button::icon(crate::ui::icons::get_handle("user-trash-full-symbolic", 16))
-                                                                .class(widget::button::ButtonClass::Destructive)
-                                                                .on_press(Message::DeleteConversation(conv.id))
-                                                        )
-                                                        .spacing(8)
-                                                )
-                                                .align_y(cosmic::iced::Alignment::Center)
-                                        )
-                                        .spacing(8)
-                                )
-                                .padding(16)
-                                .class(cosmic::style::Container::Card);
-                                
-                                column = column.push(conversation_card);
+                            let today = chrono::Local::now().date_naive();
+                            let mut current_group = None;
+                            for conv in &conversations {
+                                let group = if conv.pinned { "Pinned" } else { history_group(conv.updated_at, today) };
+                                if current_group != Some(group) {
+                                    current_group = Some(group);
+                                    column = column.push(
+                                        cosmic::widget::text(group)
+                                            .size(14)
+                                            .class(cosmic::style::Text::Color(cosmic::iced::Color::from_rgb(0.6, 0.6, 0.6)))
+                                    );
+                                }
+                                column = column.push(self.history_card(conv));
                             }
                         }
                         scrollable(column.spacing(8))
@@ -2214,6 +4147,248 @@ impl CosmicLlmApp {
             .into()
     }
 
+    /// Append the next page of results for the History search
+    fn search_page(&mut self) {
+        let query = SearchQuery::parse(&self.search_query);
+        if query.is_empty() {
+            self.search_results.clear();
+            self.search_has_more = false;
+            return;
+        }
+        let prefix = SearchQuery::ends_with_bare_word(&self.search_query);
+        // One extra row tells whether there is another page
+        match self.storage.search(&query, prefix, SEARCH_PAGE_SIZE + 1, self.search_results.len()) {
+            Ok(mut results) => {
+                self.search_has_more = results.len() > SEARCH_PAGE_SIZE;
+                results.truncate(SEARCH_PAGE_SIZE);
+                self.search_results.extend(results);
+            }
+            Err(e) => {
+                eprintln!("Search error: {}", e);
+                self.search_has_more = false;
+            }
+        }
+    }
+    
+    /// A search excerpt with the matched words emphasized
+    fn search_snippet_view(result: &crate::storage::sqlite_storage_simple::Snippet) -> Element<'_, Message> {
+        use cosmic::iced::widget::{rich_text, span, text::Span};
+        
+        let accent: cosmic::iced::Color = cosmic::theme::active().cosmic().accent_text_color().into();
+        let spans: Vec<Span<'_, (), cosmic::iced::Font>> = result.highlights()
+            .into_iter()
+            .map(|(part, matched)| {
+                let piece = span(part.replace('\n', " ")).size(12);
+                if matched {
+                    piece.color(accent).font(cosmic::font::bold())
+                } else {
+                    piece
+                }
+            })
+            .collect();
+        rich_text(spans).into()
+    }
+    
+    /// Conversations on the History page after the archive, folder and tag filters, pinned ones first
+    fn visible_conversations(&self) -> Vec<&ConversationIndex> {
+        let mut visible: Vec<&ConversationIndex> = self.history_rows
+            .iter()
+            .filter(|conv| conv.archived == self.history_show_archived)
+            .filter(|conv| match &self.history_folder_filter {
+                FolderFilter::All => true,
+                FolderFilter::Unfiled => conv.folder.is_none(),
+                FolderFilter::Folder(folder) => conv.folder.as_ref() == Some(folder),
+            })
+            .filter(|conv| self.history_tag_filter.as_ref().is_none_or(|tag| conv.tags.contains(tag)))
+            .collect();
+        // Stable sort keeps the newest first within each part
+        visible.sort_by_key(|conv| !conv.pinned);
+        visible
+    }
+    
+    fn history_filtered(&self) -> bool {
+        self.history_folder_filter != FolderFilter::All || self.history_tag_filter.is_some() || self.history_show_archived
+    }
+    
+    fn history_filter_bar(&self) -> Element<Message> {
+        let folders = history_folders(&self.history_rows);
+        let tags = history_tags(&self.history_rows);
+        
+        let folder_selected = match &self.history_folder_filter {
+            FolderFilter::All => Some(0),
+            FolderFilter::Unfiled => Some(1),
+            FolderFilter::Folder(folder) => folders.iter().position(|f| f == folder).map(|i| i + 2),
+        };
+        let mut folder_options = vec!["All folders".to_string(), "Unfiled".to_string()];
+        folder_options.extend(folders);
+        
+        let tag_selected = match &self.history_tag_filter {
+            Some(tag) => tags.iter().position(|t| t == tag).map(|i| i + 1),
+            None => Some(0),
+        };
+        let mut tag_options = vec!["All tags".to_string()];
+        tag_options.extend(tags.into_iter().map(|tag| format!("#{}", tag)));
+        
+        cosmic::widget::container(
+            cosmic::widget::row::with_capacity(5)
+                .push(widget::dropdown(folder_options, folder_selected, Message::HistoryFolderFilterSelected))
+                .push(widget::dropdown(tag_options, tag_selected, Message::HistoryTagFilterSelected))
+                .push(
+                    widget::checkbox("Archived", self.history_show_archived)
+                        .on_toggle(Message::HistoryShowArchivedToggled)
+                )
+                .push(cosmic::widget::Space::with_width(Length::Fill))
+                .push(
+                    widget::button::text("Select all")
+                        .on_press(Message::SelectAllConversations)
+                )
+                .spacing(12)
+                .align_y(cosmic::iced::Alignment::Center)
+        )
+        .padding(12)
+        .class(cosmic::style::Container::Card)
+        .into()
+    }
+    
+    /// Actions on the selected conversations; None while nothing is selected
+    fn history_bulk_bar(&self) -> Option<Element<Message>> {
+        if self.history_selection.is_empty() {
+            return None;
+        }
+        let archive = !self.history_show_archived;
+        Some(
+            cosmic::widget::container(
+                cosmic::widget::row::with_capacity(6)
+                    .push(cosmic::widget::text(format!("{} selected", self.history_selection.len())).size(14))
+                    .push(cosmic::widget::Space::with_width(Length::Fill))
+                    .push(
+                        widget::button::standard(if archive { "Archive" } else { "Unarchive" })
+                            .on_press(Message::ArchiveSelectedConversations(archive))
+                    )
+                    .push(
+                        widget::button::standard("Export")
+                            .on_press(Message::ExportSelectedConversations)
+                    )
+                    .push(
+                        widget::button::destructive("Delete")
+                            .on_press(Message::DeleteSelectedConversations)
+                    )
+                    .push(
+                        widget::button::text("Clear")
+                            .on_press(Message::ClearConversationSelection)
+                    )
+                    .spacing(8)
+                    .align_y(cosmic::iced::Alignment::Center)
+            )
+            .padding(12)
+            .class(cosmic::style::Container::Card)
+            .into()
+        )
+    }
+    
+    fn history_card(&self, conv: &ConversationIndex) -> Element<Message> {
+        let hint_color = cosmic::style::Text::Color(cosmic::iced::Color::from_rgb(0.5, 0.5, 0.5));
+        let date_str = conv.updated_at.format("%Y-%m-%d %H:%M").to_string();
+        let id = conv.id;
+        
+        let mut labels = vec![format!("{} messages", conv.message_count)];
+        if let Some(model) = &conv.last_model {
+            labels.push(model.clone());
+        }
+        if let Some(folder) = &conv.folder {
+            labels.push(format!("📁 {}", folder));
+        }
+        labels.extend(conv.tags.iter().map(|tag| format!("#{}", tag)));
+        
+        let editor = self.history_labels_edit.as_ref()
+            .filter(|(edit_id, _, _)| *edit_id == id)
+            .map(|(_, folder, tags)| {
+                cosmic::widget::row::with_capacity(4)
+                    .push(
+                        widget::text_input("Folder", folder)
+                            .on_input(Message::ConversationFolderInput)
+                            .width(Length::FillPortion(1))
+                    )
+                    .push(
+                        widget::text_input("Tags, separated by commas", tags)
+                            .on_input(Message::ConversationTagsInput)
+                            .on_submit(|_| Message::SaveConversationLabels)
+                            .width(Length::FillPortion(2))
+                    )
+                    .push(widget::button::suggested("Save").on_press(Message::SaveConversationLabels))
+                    .push(widget::button::standard("Cancel").on_press(Message::CancelConversationLabels))
+                    .spacing(8)
+                    .align_y(cosmic::iced::Alignment::Center)
+            });
+        
+        let snippet = conv.last_snippet.as_ref().map(|snippet| {
+            let line: String = snippet.lines().find(|line| !line.trim().is_empty()).unwrap_or_default().chars().take(120).collect();
+            cosmic::widget::text(line).size(13).class(hint_color.clone())
+        });
+        
+        cosmic::widget::container(
+            cosmic::widget::column::with_capacity(4)
+                .push(
+                    cosmic::widget::row::with_capacity(4)
+                        .push(
+                            widget::checkbox("", self.history_selection.contains(&id))
+                                .on_toggle(move |selected| Message::ToggleConversationSelected(id, selected))
+                        )
+                        .push(
+                            cosmic::widget::text(conv.title.clone())
+                                .size(16)
+                        )
+                        .push(cosmic::widget::Space::with_width(Length::Fill))
+                        .push(
+                            cosmic::widget::text(date_str)
+                                .size(12)
+                                .class(cosmic::style::Text::Color(cosmic::iced::Color::from_rgb(0.6, 0.6, 0.6)))
+                        )
+                        .align_y(cosmic::iced::Alignment::Center)
+                )
+                .push_maybe(snippet)
+                .push(
+                    cosmic::widget::row::with_capacity(2)
+                        .push(
+                            cosmic::widget::text(labels.join(" · "))
+                                .size(12)
+                                .class(hint_color)
+                        )
+                        .push(cosmic::widget::Space::with_width(Length::Fill))
+                        .push(
+                            cosmic::widget::row::with_capacity(4)
+                                .push(
+                                    widget::button::icon(crate::ui::icons::get_handle("view-pin-symbolic", 16))
+                                        .selected(conv.pinned)
+                                        .tooltip(if conv.pinned { "Unpin" } else { "Pin" })
+                                        .on_press(Message::PinConversation(id, !conv.pinned))
+                                )
+                                .push(
+                                    widget::button::icon(crate::ui::icons::get_handle("edit-symbolic", 16))
+                                        .tooltip("Folder and tags")
+                                        .on_press(Message::EditConversationLabels(id))
+                                )
+                                .push(
+                                    widget::button::icon(crate::ui::icons::get_handle("chat-bubble-text-symbolic", 16))
+                                        .on_press(Message::SelectConversation(id))
+                                )
+                                .push(
+                                    widget::button::icon(crate::ui::icons::get_handle("user-trash-full-symbolic", 16))
+                                        .class(widget::button::ButtonClass::Destructive)
+                                        .on_press(Message::DeleteConversation(id))
+                                )
+                                .spacing(8)
+                        )
+                        .align_y(cosmic::iced::Alignment::Center)
+                )
+                .push_maybe(editor)
+                .spacing(8)
+        )
+        .padding(16)
+        .class(cosmic::style::Container::Card)
+        .into()
+    }
+    
     fn mcp_config_view(&self) -> Element<Message> {
         // Load the actual MCP config (same as startup)
         let mcp_config = crate::config::MCPConfig::load_from_json()
@@ -2230,19 +4405,30 @@ impl CosmicLlmApp {
                 server_config.args.join(" ")
             );
             
+            let server_enabled = self.server_states.get(&server_name).copied().unwrap_or(!server_config.disabled);
+            let toggle_name = server_name.clone();
             let server_widget = cosmic::widget::column::with_capacity(4)
                 .push(
-                    cosmic::widget::row::with_capacity(2)
+                    cosmic::widget::row::with_capacity(4)
                         .push(
                             cosmic::widget::text(server_name.clone())
                                 .size(16)
                         )
                         .push(cosmic::widget::Space::with_width(Length::Fill))
                         .push(
-                            cosmic::widget::text("Connected")
+                            cosmic::widget::text(if server_enabled { "Connected" } else { "Disabled" })
                                 .size(12)
-                                .class(cosmic::style::Text::Color(cosmic::iced::Color::from_rgb(0.2, 0.8, 0.2)))
+                                .class(if server_enabled {
+                                    cosmic::style::Text::Color(cosmic::iced::Color::from_rgb(0.2, 0.8, 0.2))
+                                } else {
+                                    cosmic::style::Text::Color(cosmic::iced::Color::from_rgb(0.5, 0.5, 0.5))
+                                })
+                        )
+                        .push(
+                            cosmic::widget::toggler(server_enabled)
+                                .on_toggle(move |enabled| Message::ToggleServer(toggle_name.clone(), enabled))
                         )
+                        .spacing(8)
                         .align_y(cosmic::iced::Alignment::Center)
                 )
                 .push(
@@ -2260,7 +4446,7 @@ impl CosmicLlmApp {
             server_column = server_column.push(server_widget);
         }
         
-        cosmic::widget::column::with_capacity(4)
+        cosmic::widget::column::with_capacity(5)
             .push(
                 // Simple header
                 cosmic::widget::row::with_capacity(3)
@@ -2306,7 +4492,7 @@ impl CosmicLlmApp {
                                             .size(16)
                                     )
                                     .push(
-                                        cosmic::widget::text("Add MCP servers to enable tools and capabilities")
+                                        cosmic::widget::text("Add MCP servers in Settings to enable tools and capabilities")
                                             .size(12)
                                             .class(cosmic::style::Text::Color(cosmic::iced::Color::from_rgb(0.6, 0.6, 0.6)))
                                     )
@@ -2319,6 +4505,26 @@ impl CosmicLlmApp {
                     )
                     .spacing(12)
             )
+            .push(
+                // Conflicts section: same bare tool name on several servers
+                if self.tool_conflicts.is_empty() {
+                    cosmic::widget::column::with_capacity(0)
+                } else {
+                    let mut conflicts_column = cosmic::widget::column::with_capacity(self.tool_conflicts.len() + 1)
+                        .push(
+                            cosmic::widget::text(format!("⚠️ Tool name conflicts ({})", self.tool_conflicts.len()))
+                                .size(14)
+                        );
+                    for conflict in &self.tool_conflicts {
+                        conflicts_column = conflicts_column.push(
+                            cosmic::widget::text(format!("{} is provided by {}", conflict.tool_name, conflict.servers.join(", ")))
+                                .size(12)
+                                .class(cosmic::style::Text::Color(cosmic::iced::Color::from_rgb(0.8, 0.6, 0.2)))
+                        );
+                    }
+                    conflicts_column.spacing(4)
+                }
+            )
             .push(
                 // Tools section
                 cosmic::widget::column::with_capacity(2)
@@ -2413,3 +4619,40 @@ impl CosmicLlmApp {
 
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    /// Noon of `date` in local time, where History groups are counted
+    fn local_noon(date: NaiveDate) -> chrono::DateTime<chrono::Utc> {
+        date.and_hms_opt(12, 0, 0).unwrap()
+            .and_local_timezone(chrono::Local).unwrap()
+            .with_timezone(&chrono::Utc)
+    }
+
+    #[test]
+    fn test_history_group() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap();
+        let days_ago = |days| local_noon(today - chrono::Days::new(days));
+        assert_eq!(history_group(days_ago(0), today), "Today");
+        assert_eq!(history_group(days_ago(1), today), "Yesterday");
+        assert_eq!(history_group(days_ago(2), today), "Last week");
+        assert_eq!(history_group(days_ago(7), today), "Last week");
+        assert_eq!(history_group(days_ago(8), today), "Last month");
+        assert_eq!(history_group(days_ago(30), today), "Last month");
+        assert_eq!(history_group(days_ago(31), today), "Older");
+        assert_eq!(history_group(days_ago(400), today), "Older");
+        // A clock that was ahead still counts as today
+        assert_eq!(history_group(local_noon(today + chrono::Days::new(1)), today), "Today");
+    }
+
+    #[test]
+    fn test_parse_tags() {
+        assert_eq!(parse_tags(" Work , rust,work,, ,Rust "), vec!["rust", "work"]);
+        assert_eq!(parse_tags("two words"), vec!["two words"]);
+        assert!(parse_tags("").is_empty());
+        assert!(parse_tags(" , ,").is_empty());
+    }
+}
@@ -0,0 +1,210 @@
+//! Command registry behind the keyboard shortcuts and the command palette
+
+use cosmic::iced::keyboard::{key::Named, Key};
+use cosmic::widget::menu::key_bind::{KeyBind, Modifier};
+use std::collections::HashMap;
+
+use super::app::{MenuAction, Message};
+
+/// A command that can be bound to a shortcut and run from the palette
+pub struct Command {
+    pub action: MenuAction,
+    /// Name used in the `[keybindings]` section of config.toml
+    pub id: &'static str,
+    pub label: &'static str,
+    pub default_shortcut: Option<&'static str>,
+}
+
+pub const COMMANDS: &[Command] = &[
+    Command { action: MenuAction::CommandPalette, id: "command_palette", label: "Command palette", default_shortcut: Some("Ctrl+K") },
+    Command { action: MenuAction::NewConversation, id: "new_conversation", label: "New conversation", default_shortcut: Some("Ctrl+N") },
+    Command { action: MenuAction::SendMessage, id: "send_message", label: "Send message", default_shortcut: Some("Ctrl+Enter") },
+    Command { action: MenuAction::StopMessage, id: "stop_message", label: "Stop the reply", default_shortcut: Some("Ctrl+.") },
+    Command { action: MenuAction::AttachFile, id: "attach_file", label: "Attach a file", default_shortcut: Some("Ctrl+O") },
+    Command { action: MenuAction::PromptTemplates, id: "prompt_templates", label: "Insert a prompt template", default_shortcut: Some("Ctrl+T") },
+    Command { action: MenuAction::ConversationSettings, id: "conversation_settings", label: "Conversation settings", default_shortcut: None },
+    Command { action: MenuAction::ExportConversation, id: "export_conversation", label: "Export conversation as Markdown", default_shortcut: Some("Ctrl+E") },
+    Command { action: MenuAction::ShowChat, id: "show_chat", label: "Go to Chat", default_shortcut: Some("Ctrl+1") },
+    Command { action: MenuAction::ShowHistory, id: "show_history", label: "Go to History", default_shortcut: Some("Ctrl+2") },
+    Command { action: MenuAction::ShowMcpConfig, id: "show_mcp_config", label: "Go to MCP Config", default_shortcut: Some("Ctrl+3") },
+    Command { action: MenuAction::Settings, id: "settings", label: "Go to Settings", default_shortcut: Some("Ctrl+,") },
+    Command { action: MenuAction::ShowTools, id: "show_tools", label: "Show tools", default_shortcut: None },
+    Command { action: MenuAction::EnableAllTools, id: "enable_all_tools", label: "Enable all tools", default_shortcut: None },
+    Command { action: MenuAction::DisableAllTools, id: "disable_all_tools", label: "Disable all tools", default_shortcut: None },
+    Command { action: MenuAction::About, id: "about", label: "About", default_shortcut: None },
+    Command { action: MenuAction::Quit, id: "quit", label: "Quit", default_shortcut: Some("Ctrl+Q") },
+];
+
+/// Parse a shortcut such as "Ctrl+Shift+K" or "Ctrl+Enter"
+pub fn parse_shortcut(shortcut: &str) -> Result<KeyBind, String> {
+    let parts: Vec<&str> = shortcut.split('+').map(str::trim).collect();
+    // "Ctrl++" binds the plus key
+    let (key_name, modifier_names) = match parts.as_slice() {
+        [modifiers @ .., "", ""] => ("+", modifiers),
+        [modifiers @ .., key] => (*key, modifiers),
+        [] => unreachable!("split always yields one part"),
+    };
+
+    let mut modifiers = Vec::new();
+    for name in modifier_names {
+        let modifier = match name.to_ascii_lowercase().as_str() {
+            "ctrl" | "control" => Modifier::Ctrl,
+            "shift" => Modifier::Shift,
+            "alt" => Modifier::Alt,
+            "super" | "logo" | "meta" => Modifier::Super,
+            _ => return Err(format!("unknown modifier '{}' in '{}'", name, shortcut)),
+        };
+        if !modifiers.contains(&modifier) {
+            modifiers.push(modifier);
+        }
+    }
+
+    let key = match key_name.to_ascii_lowercase().as_str() {
+        "" => return Err(format!("'{}' has no key", shortcut)),
+        "enter" | "return" => Key::Named(Named::Enter),
+        "escape" | "esc" => Key::Named(Named::Escape),
+        "tab" => Key::Named(Named::Tab),
+        "space" => Key::Named(Named::Space),
+        "backspace" => Key::Named(Named::Backspace),
+        "delete" | "del" => Key::Named(Named::Delete),
+        "up" => Key::Named(Named::ArrowUp),
+        "down" => Key::Named(Named::ArrowDown),
+        "left" => Key::Named(Named::ArrowLeft),
+        "right" => Key::Named(Named::ArrowRight),
+        "home" => Key::Named(Named::Home),
+        "end" => Key::Named(Named::End),
+        "pageup" => Key::Named(Named::PageUp),
+        "pagedown" => Key::Named(Named::PageDown),
+        "f1" => Key::Named(Named::F1),
+        "f2" => Key::Named(Named::F2),
+        "f3" => Key::Named(Named::F3),
+        "f4" => Key::Named(Named::F4),
+        "f5" => Key::Named(Named::F5),
+        "f6" => Key::Named(Named::F6),
+        "f7" => Key::Named(Named::F7),
+        "f8" => Key::Named(Named::F8),
+        "f9" => Key::Named(Named::F9),
+        "f10" => Key::Named(Named::F10),
+        "f11" => Key::Named(Named::F11),
+        "f12" => Key::Named(Named::F12),
+        name if name.chars().count() == 1 => Key::Character(name.into()),
+        _ => return Err(format!("unknown key '{}' in '{}'", key_name, shortcut)),
+    };
+
+    Ok(KeyBind { modifiers, key })
+}
+
+/// Default shortcuts with the user's `[keybindings]` applied; an empty shortcut unbinds a command.
+/// Also returns a message for every entry that could not be used.
+pub fn key_binds(overrides: &HashMap<String, String>) -> (HashMap<KeyBind, MenuAction>, Vec<String>) {
+    let mut errors = Vec::new();
+    for id in overrides.keys() {
+        if !COMMANDS.iter().any(|command| command.id == id) {
+            errors.push(format!("unknown command '{}'", id));
+        }
+    }
+
+    let mut key_binds = HashMap::new();
+    for command in COMMANDS {
+        let shortcut = match overrides.get(command.id) {
+            Some(shortcut) => shortcut.as_str(),
+            None => command.default_shortcut.unwrap_or_default(),
+        };
+        if shortcut.trim().is_empty() {
+            continue;
+        }
+        match parse_shortcut(shortcut) {
+            Ok(key_bind) => {
+                if let Some(previous) = key_binds.insert(key_bind, command.action) {
+                    errors.push(format!("'{}' is bound to both {:?} and {:?}", shortcut, previous, command.action));
+                }
+            }
+            Err(e) => errors.push(format!("{}: {}", command.id, e)),
+        }
+    }
+    errors.sort();
+    (key_binds, errors)
+}
+
+/// One row of the command palette
+#[derive(Debug, Clone)]
+pub struct PaletteEntry {
+    pub label: String,
+    pub shortcut: Option<String>,
+    pub message: Message,
+}
+
+/// Score `candidate` against `query`: every query character must appear in order.
+/// Consecutive matches and matches at word starts score higher; None when it does not match.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i32> {
+    let candidate: Vec<char> = candidate.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous_match: Option<usize> = None;
+    for query_char in query.chars().flat_map(char::to_lowercase).filter(|c| !c.is_whitespace()) {
+        let offset = candidate[position..].iter().position(|c| *c == query_char)?;
+        let index = position + offset;
+        score += 1;
+        if previous_match.is_some_and(|previous| previous + 1 == index) {
+            score += 5;
+        }
+        if index == 0 || !candidate[index - 1].is_alphanumeric() {
+            score += 3;
+        }
+        score -= offset.min(5) as i32;
+        previous_match = Some(index);
+        position = index + 1;
+    }
+    Some(score)
+}
+
+/// Entries matching `query`, best first; an empty query keeps the given order
+pub fn filter(entries: &[PaletteEntry], query: &str) -> Vec<PaletteEntry> {
+    if query.trim().is_empty() {
+        return entries.to_vec();
+    }
+    let mut scored: Vec<(i32, &PaletteEntry)> = entries
+        .iter()
+        .filter_map(|entry| fuzzy_score(query, &entry.label).map(|score| (score, entry)))
+        .collect();
+    // Stable sort: equal scores keep their order
+    scored.sort_by(|a, b| b.0.cmp(&a.0));
+    scored.into_iter().map(|(_, entry)| entry.clone()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_shortcut() {
+        let key_bind = parse_shortcut("Ctrl+Shift+K").unwrap();
+        assert_eq!(key_bind.modifiers, vec![Modifier::Ctrl, Modifier::Shift]);
+        assert_eq!(key_bind.key, Key::Character("k".into()));
+        assert_eq!(parse_shortcut("ctrl+enter").unwrap().key, Key::Named(Named::Enter));
+        assert_eq!(parse_shortcut("Ctrl++").unwrap().key, Key::Character("+".into()));
+        assert!(parse_shortcut("Hyper+K").is_err());
+        assert!(parse_shortcut("Ctrl+").is_err());
+    }
+
+    #[test]
+    fn test_key_bind_overrides() {
+        let overrides = HashMap::from([
+            ("quit".to_string(), String::new()),
+            ("show_tools".to_string(), "Ctrl+Shift+T".to_string()),
+            ("nope".to_string(), "Ctrl+J".to_string()),
+        ]);
+        let (key_binds, errors) = key_binds(&overrides);
+        assert!(!key_binds.values().any(|action| *action == MenuAction::Quit));
+        assert_eq!(key_binds.get(&parse_shortcut("Ctrl+Shift+T").unwrap()), Some(&MenuAction::ShowTools));
+        assert_eq!(errors, vec!["unknown command 'nope'".to_string()]);
+    }
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert!(fuzzy_score("nc", "New conversation").is_some());
+        assert!(fuzzy_score("xyz", "New conversation").is_none());
+        // Word starts and runs beat scattered matches
+        assert!(fuzzy_score("hist", "Go to History") > fuzzy_score("hist", "Show tools list"));
+    }
+}
@@ -6,6 +6,10 @@
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum ContextPage {
     About,
+    /// Profile overrides of the open conversation
+    ConversationSettings,
+    /// Prompt template picker
+    PromptTemplates,
     // Future: Settings, MCPServers, etc.
 }
 
@@ -15,6 +19,8 @@ impl ContextPage {
     pub fn title(&self) -> String {
         match self {
             Self::About => "About".to_string(),
+            Self::ConversationSettings => "Conversation Settings".to_string(),
+            Self::PromptTemplates => "Prompt Templates".to_string(),
             // Future: Self::Settings => "Settings".to_string(),
         }
     }
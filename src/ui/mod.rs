@@ -1,7 +1,9 @@
 pub mod app;
+pub mod commands;
 pub mod context;
 pub mod dialogs;
 pub mod icons;
+pub mod notifications;
 pub mod pages;
 pub mod widgets;
 
@@ -0,0 +1,95 @@
+//! Desktop notifications through the freedesktop notification service (D-Bus)
+
+use notify_rust::{Notification, Timeout};
+
+/// Action the notification server reports when the notification body is clicked
+const DEFAULT_ACTION: &str = "default";
+
+/// Longest body shown in a notification
+const MAX_BODY_CHARS: usize = 200;
+
+/// How long a notification stays up and can be clicked
+const EXPIRE_AFTER: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// First line of `text`, shortened to fit a notification body
+pub fn preview(text: &str) -> String {
+    let line = text.trim().lines().next().unwrap_or_default();
+    if line.chars().count() > MAX_BODY_CHARS {
+        let shortened: String = line.chars().take(MAX_BODY_CHARS - 3).collect();
+        format!("{}...", shortened)
+    } else {
+        line.to_string()
+    }
+}
+
+/// Show a notification and wait until it is closed or expires; true when the user clicked it
+pub async fn notify(summary: String, body: String) -> bool {
+    let notification = Notification::new()
+        .appname("Cosmic LLM")
+        .summary(&summary)
+        .body(&body)
+        .icon("dialog-information")
+        .action(DEFAULT_ACTION, "Open conversation")
+        // Expiring closes it, which ends the wait below on servers that honor timeouts
+        .timeout(Timeout::Milliseconds(EXPIRE_AFTER.as_millis() as u32))
+        .finalize();
+    let (shown_tx, mut shown_rx) = tokio::sync::oneshot::channel();
+    let to_show = notification.clone();
+    let wait = tokio::task::spawn_blocking(move || match to_show.show() {
+        Ok(handle) => {
+            let _ = shown_tx.send(handle.id());
+            let mut clicked = false;
+            handle.wait_for_action(|action| clicked = action == DEFAULT_ACTION);
+            clicked
+        }
+        Err(e) => {
+            log::warn!("⚠️ Failed to show notification: {}", e);
+            false
+        }
+    });
+    // Servers that keep notifications until dismissed would hold the wait open indefinitely;
+    // a little after the notification should have expired, close it to release the thread
+    match tokio::time::timeout(EXPIRE_AFTER + std::time::Duration::from_secs(5), wait).await {
+        Ok(clicked) => clicked.unwrap_or(false),
+        Err(_) => {
+            if let Ok(id) = shown_rx.try_recv() {
+                tokio::task::spawn_blocking(move || close(notification, id));
+            }
+            false
+        }
+    }
+}
+
+/// Close the shown notification `id`, which ends a `wait_for_action` on it
+fn close(mut notification: Notification, id: u32) {
+    // notify-rust closes only through a handle; showing the same content under the id
+    // replaces the notification with itself and returns one
+    match notification.id(id).show() {
+        Ok(handle) => handle.close(),
+        Err(e) => log::warn!("⚠️ Failed to close notification: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preview() {
+        assert_eq!(preview("  Done\nsecond line"), "Done");
+        assert_eq!(preview(""), "");
+
+        let fits = "a".repeat(MAX_BODY_CHARS);
+        assert_eq!(preview(&fits), fits);
+        let long = "a".repeat(MAX_BODY_CHARS + 1);
+        assert_eq!(preview(&long), format!("{}...", "a".repeat(MAX_BODY_CHARS - 3)));
+
+        // Counted in characters, so multibyte text is cut between characters
+        let wide = "é🙂".repeat(MAX_BODY_CHARS);
+        let shortened = preview(&wide);
+        assert_eq!(shortened.chars().count(), MAX_BODY_CHARS);
+        assert!(shortened.starts_with("é🙂é🙂"));
+        assert!(shortened.ends_with("..."));
+        assert_eq!(preview(&"ü".repeat(MAX_BODY_CHARS)), "ü".repeat(MAX_BODY_CHARS));
+    }
+}
@@ -4,13 +4,71 @@ use cosmic::{
     Element, theme,
 };
 
-use crate::config::{AppConfig, LlmProfile};
+use std::collections::HashSet;
+
+use crate::config::{AppConfig, LlmProfile, MCPConfig, MCPServerConfig, Persona, ThemePreference};
+
+/// Result of the last model fetch or connection test for the new profile
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectionStatus {
+    Idle,
+    Checking,
+    /// A model download is running; holds its latest progress line
+    Pulling(String),
+    Ok(String),
+    Failed(String),
+}
+
+impl ConnectionStatus {
+    /// Whether a request is running, so another should not be started
+    pub fn is_busy(&self) -> bool {
+        matches!(self, ConnectionStatus::Checking | ConnectionStatus::Pulling(_))
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct SimpleSettingsPage {
     pub new_profile_name: String,
+    pub new_profile_backend: String,
     pub new_profile_model: String,
     pub new_profile_endpoint: String,
+    pub new_profile_api_key: String,
+    /// Backends compiled into this build
+    pub backends: Vec<String>,
+    /// Models reported by the backend for the draft profile
+    pub available_models: Vec<String>,
+    pub connection_status: ConnectionStatus,
+    /// Bumped when the draft changes, so late discovery results can be told apart
+    pub discovery_generation: u64,
+    // Persona form, also used to edit an existing persona
+    /// Name of the persona being edited; None adds a new one
+    pub persona_editing: Option<String>,
+    pub persona_name: String,
+    /// None follows the default profile
+    pub persona_profile: Option<String>,
+    pub persona_system_prompt: String,
+    /// Comma-separated qualified tool names (`server__tool`) or server names; empty allows every tool
+    pub persona_tools: String,
+    pub persona_temperature: String,
+    pub persona_max_tokens: String,
+    pub persona_error: Option<String>,
+    /// mcp_config.json as written, with `${env:...}` references unexpanded
+    pub mcp_config: MCPConfig,
+    // MCP server form, also used to edit an existing server
+    /// Name of the server being edited; None adds a new one
+    pub mcp_editing: Option<String>,
+    pub mcp_name: String,
+    pub mcp_command: String,
+    pub mcp_args: Vec<String>,
+    pub mcp_env: Vec<(String, String)>,
+    /// Environment rows whose value is shown in clear text
+    pub mcp_env_revealed: HashSet<usize>,
+    pub mcp_enabled: bool,
+    pub mcp_test_status: ConnectionStatus,
+    /// Bumped when the form is reset, so a late test result of another server is dropped
+    pub mcp_test_generation: u64,
+    pub mcp_discovered_tools: Vec<String>,
+    pub mcp_error: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -18,21 +76,260 @@ pub enum SimpleSettingsMessage {
     BackToMain,
     SetDefaultProfile(String),
     NewProfileNameChanged(String),
+    NewProfileBackendChanged(usize),
     NewProfileModelChanged(String),
+    NewProfileModelSelected(usize),
     NewProfileEndpointChanged(String),
+    NewProfileApiKeyChanged(String),
+    FetchModels,
+    ModelsFetched(u64, Result<Vec<String>, String>),
+    TestConnection,
+    ConnectionTested(u64, Result<(), String>),
+    PullModel,
+    PullProgress(String),
+    ModelPulled(Result<(), String>),
     AddNewProfile,
+    PersonaNameChanged(String),
+    PersonaProfileSelected(usize),
+    PersonaSystemPromptChanged(String),
+    PersonaToolsChanged(String),
+    PersonaTemperatureChanged(String),
+    PersonaMaxTokensChanged(String),
+    EditPersona(String),
+    DeletePersona(String),
+    SavePersona,
+    ThemeSelected(usize),
+    SaveHistoryToggled(bool),
+    NotificationsToggled(bool),
+    AutoScrollToggled(bool),
+    SaveSettings,
+    McpServerNameChanged(String),
+    McpServerCommandChanged(String),
+    McpServerArgChanged(usize, String),
+    AddMcpServerArg,
+    RemoveMcpServerArg(usize),
+    McpServerEnvKeyChanged(usize, String),
+    McpServerEnvValueChanged(usize, String),
+    ToggleMcpServerEnvVisible(usize),
+    AddMcpServerEnv,
+    RemoveMcpServerEnv(usize),
+    McpServerEnabledToggled(bool),
+    ToggleMcpServer(String, bool),
+    EditMcpServer(String),
+    DeleteMcpServer(String),
+    TestMcpServer,
+    McpServerTested(u64, Result<Vec<String>, String>),
+    SaveMcpServer,
+    ResetMcpServer,
 }
 
 impl SimpleSettingsPage {
     pub fn new() -> Self {
+        let backends: Vec<String> = crate::llm::PROVIDERS
+            .iter()
+            .map(|provider| provider.name.to_string())
+            .collect();
         Self {
             new_profile_name: String::new(),
+            new_profile_backend: backends.first().cloned().unwrap_or_default(),
             new_profile_model: String::new(),
             new_profile_endpoint: String::new(),
+            new_profile_api_key: String::new(),
+            backends,
+            available_models: Vec::new(),
+            connection_status: ConnectionStatus::Idle,
+            discovery_generation: 0,
+            persona_editing: None,
+            persona_name: String::new(),
+            persona_profile: None,
+            persona_system_prompt: String::new(),
+            persona_tools: String::new(),
+            persona_temperature: String::new(),
+            persona_max_tokens: String::new(),
+            persona_error: None,
+            mcp_config: MCPConfig::default(),
+            mcp_editing: None,
+            mcp_name: String::new(),
+            mcp_command: String::new(),
+            mcp_args: Vec::new(),
+            mcp_env: Vec::new(),
+            mcp_env_revealed: HashSet::new(),
+            mcp_enabled: true,
+            mcp_test_status: ConnectionStatus::Idle,
+            mcp_test_generation: 0,
+            mcp_discovered_tools: Vec::new(),
+            mcp_error: None,
+        }
+    }
+
+    /// Profile built from the add-profile form, used for discovery and saving
+    pub fn draft_profile(&self) -> LlmProfile {
+        LlmProfile {
+            backend: self.new_profile_backend.clone(),
+            api_key: self.new_profile_api_key.trim().to_string(),
+            model: self.new_profile_model.trim().to_string(),
+            endpoint: self.new_profile_endpoint.trim().to_string(),
+            temperature: Some(0.7),
+            max_tokens: Some(1000),
+            thinking_budget: None,
+            fallback: None,
+            agent: None,
+            ollama: None,
+            azure: None,
+            responses: None,
+        }
+    }
+
+    /// Drop fetched models and status once the backend, endpoint or key change
+    pub fn invalidate_discovery(&mut self) {
+        self.available_models.clear();
+        self.connection_status = ConnectionStatus::Idle;
+        self.discovery_generation += 1;
+    }
+
+    pub fn reset_new_profile(&mut self) {
+        self.new_profile_name.clear();
+        self.new_profile_model.clear();
+        self.new_profile_endpoint.clear();
+        self.new_profile_api_key.clear();
+        self.invalidate_discovery();
+    }
+
+    /// Fill the persona form with an existing persona
+    pub fn edit_persona(&mut self, name: &str, persona: &Persona) {
+        self.persona_editing = Some(name.to_string());
+        self.persona_name = name.to_string();
+        self.persona_profile = persona.profile.clone();
+        self.persona_system_prompt = persona.system_prompt.clone().unwrap_or_default();
+        self.persona_tools = persona.tools.as_ref().map(|tools| tools.join(", ")).unwrap_or_default();
+        self.persona_temperature = persona.temperature.map(|t| t.to_string()).unwrap_or_default();
+        self.persona_max_tokens = persona.max_tokens.map(|t| t.to_string()).unwrap_or_default();
+        self.persona_error = None;
+    }
+
+    /// Persona built from the form
+    pub fn draft_persona(&self) -> Result<Persona, String> {
+        let temperature = match self.persona_temperature.trim() {
+            "" => None,
+            value => match value.parse::<f32>() {
+                Ok(t) if (0.0..=2.0).contains(&t) => Some(t),
+                _ => return Err(format!("Temperature must be between 0 and 2, got '{}'", value)),
+            },
+        };
+        let max_tokens = match self.persona_max_tokens.trim() {
+            "" => None,
+            value => match value.parse::<u32>() {
+                Ok(t) if t > 0 => Some(t),
+                _ => return Err(format!("Max tokens must be a positive number, got '{}'", value)),
+            },
+        };
+        let tools: Vec<String> = self.persona_tools
+            .split(',')
+            .map(|tool| tool.trim().to_string())
+            .filter(|tool| !tool.is_empty())
+            .collect();
+        let system_prompt = self.persona_system_prompt.trim();
+        Ok(Persona {
+            system_prompt: (!system_prompt.is_empty()).then(|| system_prompt.to_string()),
+            profile: self.persona_profile.clone(),
+            tools: (!tools.is_empty()).then_some(tools),
+            temperature,
+            max_tokens,
+        })
+    }
+
+    pub fn reset_persona(&mut self) {
+        self.persona_editing = None;
+        self.persona_name.clear();
+        self.persona_profile = None;
+        self.persona_system_prompt.clear();
+        self.persona_tools.clear();
+        self.persona_temperature.clear();
+        self.persona_max_tokens.clear();
+        self.persona_error = None;
+    }
+
+    /// Re-read mcp_config.json, or use the `[mcp]` section of config.toml when there is none
+    pub fn load_mcp_config(&mut self, config: &AppConfig) {
+        self.mcp_config = if MCPConfig::mcp_config_path().exists() {
+            MCPConfig::load_raw_json().unwrap_or_else(|e| {
+                eprintln!("Failed to load mcp_config.json: {}", e);
+                self.mcp_config.clone()
+            })
+        } else {
+            config.mcp.clone()
+        };
+    }
+
+    /// Fill the MCP server form with an existing server
+    pub fn edit_mcp_server(&mut self, name: &str) {
+        let Some(server) = self.mcp_config.servers.get(name).cloned() else {
+            return;
+        };
+        self.reset_mcp_server();
+        self.mcp_editing = Some(name.to_string());
+        self.mcp_name = name.to_string();
+        self.mcp_command = server.command;
+        self.mcp_args = server.args;
+        let mut env: Vec<(String, String)> = server.env.into_iter().collect();
+        env.sort();
+        self.mcp_env = env;
+        self.mcp_enabled = !server.disabled;
+    }
+
+    /// Server built from the form; settings the form does not show are kept from the edited server
+    pub fn draft_mcp_server(&self) -> Result<MCPServerConfig, String> {
+        let command = self.mcp_command.trim();
+        if command.is_empty() {
+            return Err("The server needs a command".to_string());
+        }
+        let mut server = self.mcp_editing.as_ref()
+            .and_then(|name| self.mcp_config.servers.get(name).cloned())
+            .unwrap_or_else(|| MCPServerConfig::new(String::new()));
+        server.command = command.to_string();
+        server.args = self.mcp_args.iter()
+            .map(|arg| arg.trim().to_string())
+            .filter(|arg| !arg.is_empty())
+            .collect();
+        server.env.clear();
+        for (key, value) in &self.mcp_env {
+            let key = key.trim();
+            if key.is_empty() {
+                if !value.is_empty() {
+                    return Err("Every environment value needs a variable name".to_string());
+                }
+                continue;
+            }
+            server.env.insert(key.to_string(), value.clone());
         }
+        server.disabled = !self.mcp_enabled;
+        Ok(server)
+    }
+
+    pub fn reset_mcp_server(&mut self) {
+        self.mcp_editing = None;
+        self.mcp_name.clear();
+        self.mcp_command.clear();
+        self.mcp_args.clear();
+        self.mcp_env.clear();
+        self.mcp_env_revealed.clear();
+        self.mcp_enabled = true;
+        self.mcp_test_status = ConnectionStatus::Idle;
+        self.mcp_test_generation += 1;
+        self.mcp_discovered_tools.clear();
+        self.mcp_error = None;
     }
 
-    pub fn view<'a>(&'a self, config: &'a AppConfig) -> Element<'a, SimpleSettingsMessage> {
+    /// Choices of the persona profile dropdown: the default profile, then every profile by name
+    pub fn persona_profile_options(config: &AppConfig) -> Vec<String> {
+        let mut names: Vec<String> = config.profiles.keys().cloned().collect();
+        names.sort();
+        names.insert(0, "Default profile".to_string());
+        names
+    }
+
+    /// `unsaved` enables the save button once something differs from config.toml
+    pub fn view<'a>(&'a self, config: &'a AppConfig, unsaved: bool) -> Element<'a, SimpleSettingsMessage> {
         let mut content = column().spacing(16);
 
         // Create profile dropdown - use a simple text display for now
@@ -73,13 +370,43 @@ impl SimpleSettingsPage {
             self.add_profile_section()
         );
 
+        // Personas
+        let mut persona_names: Vec<&String> = config.personas.keys().collect();
+        persona_names.sort();
+        for name in persona_names {
+            content = content.push(
+                self.persona_card(name, &config.personas[name])
+            );
+        }
+        content = content.push(
+            self.persona_section(config)
+        );
+
+        // MCP servers
+        let mut server_names: Vec<&String> = self.mcp_config.servers.keys().collect();
+        server_names.sort();
+        for name in server_names {
+            content = content.push(
+                self.mcp_server_card(name, &self.mcp_config.servers[name])
+            );
+        }
+        content = content.push(
+            self.mcp_server_section()
+        );
+
+        content = content.push(
+            self.preferences_section(config)
+        );
 
-        // Back button
+        // Save and back buttons
         content = content.push(
             row()
                 .push(widget::Space::with_width(Length::Fill))
                 .push(button::standard("Back to Chat")
                     .on_press(SimpleSettingsMessage::BackToMain))
+                .push(widget::Space::with_width(8))
+                .push(button::suggested("Save Settings")
+                    .on_press_maybe(unsaved.then_some(SimpleSettingsMessage::SaveSettings)))
         );
 
         widget::scrollable(content)
@@ -156,20 +483,44 @@ impl SimpleSettingsPage {
                         )
                         .push(widget::Space::with_width(8))
                         .push(
-                            text_input("Model", &self.new_profile_model)
-                                .on_input(SimpleSettingsMessage::NewProfileModelChanged)
-                                .width(Length::Fill)
+                            widget::dropdown(
+                                &self.backends,
+                                self.backends.iter().position(|b| b == &self.new_profile_backend),
+                                SimpleSettingsMessage::NewProfileBackendChanged,
+                            )
                         )
-                        .push(widget::Space::with_width(8))
+                        .align_y(Alignment::Center)
+                )
+                .push(
+                    row()
                         .push(
                             text_input("Endpoint", &self.new_profile_endpoint)
                                 .on_input(SimpleSettingsMessage::NewProfileEndpointChanged)
                                 .width(Length::Fill)
                         )
+                        .push(widget::Space::with_width(8))
+                        .push(
+                            text_input::secure_input("API Key", &self.new_profile_api_key, None, true)
+                                .on_input(SimpleSettingsMessage::NewProfileApiKeyChanged)
+                                .width(Length::Fill)
+                        )
                 )
+                .push(self.model_row())
+                .push_maybe(self.connection_status_text())
                 .push(
                     row()
                         .push(widget::Space::with_width(Length::Fill))
+                        .push_maybe((self.new_profile_backend == "ollama").then(|| {
+                            row()
+                                .push(button::standard("Pull Model")
+                                    .on_press_maybe((!self.connection_status.is_busy())
+                                        .then_some(SimpleSettingsMessage::PullModel)))
+                                .push(widget::Space::with_width(8))
+                        }))
+                        .push(button::standard("Test Connection")
+                            .on_press_maybe((!self.connection_status.is_busy())
+                                .then_some(SimpleSettingsMessage::TestConnection)))
+                        .push(widget::Space::with_width(8))
                         .push(button::suggested("Add Profile")
                             .on_press(SimpleSettingsMessage::AddNewProfile))
                 )
@@ -179,4 +530,413 @@ impl SimpleSettingsPage {
         .into()
     }
 
+    fn persona_card<'a>(&self, name: &'a str, persona: &'a Persona) -> Element<'a, SimpleSettingsMessage> {
+        let tools = match &persona.tools {
+            Some(tools) => tools.join(", "),
+            None => "All enabled tools".to_string(),
+        };
+        container(
+            column()
+                .push(
+                    row()
+                        .push(text(name)
+                            .size(14)
+                            .class(cosmic::style::Text::Color(
+                                theme::active().cosmic().palette.neutral_9.into()
+                            )))
+                        .push(widget::Space::with_width(Length::Fill))
+                        .push(button::standard("Edit")
+                            .on_press(SimpleSettingsMessage::EditPersona(name.to_string())))
+                        .push(widget::Space::with_width(8))
+                        .push(button::destructive("Delete")
+                            .on_press(SimpleSettingsMessage::DeletePersona(name.to_string())))
+                        .align_y(Alignment::Center)
+                )
+                .push(text(format!("Profile: {}", persona.profile.as_deref().unwrap_or("default")))
+                    .size(12)
+                    .class(cosmic::style::Text::Color(
+                        theme::active().cosmic().palette.neutral_6.into()
+                    )))
+                .push(text(format!("Tools: {}", tools))
+                    .size(12)
+                    .class(cosmic::style::Text::Color(
+                        theme::active().cosmic().palette.neutral_6.into()
+                    )))
+                .spacing(4)
+        )
+        .padding(16)
+        .into()
+    }
+
+    fn persona_section<'a>(&'a self, config: &'a AppConfig) -> Element<'a, SimpleSettingsMessage> {
+        let profiles = Self::persona_profile_options(config);
+        let selected = match &self.persona_profile {
+            Some(profile) => profiles.iter().position(|p| p == profile),
+            None => Some(0),
+        };
+        let title = if self.persona_editing.is_some() {
+            "Edit Persona"
+        } else {
+            "Add Persona"
+        };
+
+        container(
+            column()
+                .push(
+                    text(title)
+                        .size(16)
+                        .class(cosmic::style::Text::Color(
+                            theme::active().cosmic().palette.neutral_9.into()
+                        ))
+                )
+                .push(
+                    row()
+                        .push(
+                            text_input("Persona Name", &self.persona_name)
+                                .on_input(SimpleSettingsMessage::PersonaNameChanged)
+                                .width(Length::Fill)
+                        )
+                        .push(widget::Space::with_width(8))
+                        .push(widget::dropdown(profiles, selected, SimpleSettingsMessage::PersonaProfileSelected))
+                        .align_y(Alignment::Center)
+                )
+                .push(
+                    text_input("System prompt", &self.persona_system_prompt)
+                        .on_input(SimpleSettingsMessage::PersonaSystemPromptChanged)
+                )
+                .push(
+                    text_input("Tools as server__tool, or whole servers, comma separated (empty allows all)", &self.persona_tools)
+                        .on_input(SimpleSettingsMessage::PersonaToolsChanged)
+                )
+                .push(
+                    row()
+                        .push(
+                            text_input("Temperature", &self.persona_temperature)
+                                .on_input(SimpleSettingsMessage::PersonaTemperatureChanged)
+                                .width(Length::Fill)
+                        )
+                        .push(widget::Space::with_width(8))
+                        .push(
+                            text_input("Max tokens", &self.persona_max_tokens)
+                                .on_input(SimpleSettingsMessage::PersonaMaxTokensChanged)
+                                .width(Length::Fill)
+                        )
+                )
+                .push_maybe(self.persona_error.as_ref().map(|error| {
+                    text(format!("✗ {}", error))
+                        .size(12)
+                        .class(cosmic::style::Text::Color(
+                            theme::active().cosmic().destructive_color().into()
+                        ))
+                }))
+                .push(
+                    row()
+                        .push(widget::Space::with_width(Length::Fill))
+                        .push(button::suggested("Save Persona")
+                            .on_press(SimpleSettingsMessage::SavePersona))
+                )
+                .spacing(12)
+        )
+        .padding(16)
+        .into()
+    }
+
+    fn mcp_server_card<'a>(&self, name: &'a str, server: &'a MCPServerConfig) -> Element<'a, SimpleSettingsMessage> {
+        let mut env_names: Vec<&str> = server.env.keys().map(String::as_str).collect();
+        env_names.sort();
+        let toggle_name = name.to_string();
+        container(
+            column()
+                .push(
+                    row()
+                        .push(text(name)
+                            .size(14)
+                            .class(cosmic::style::Text::Color(
+                                theme::active().cosmic().palette.neutral_9.into()
+                            )))
+                        .push(widget::Space::with_width(Length::Fill))
+                        .push(widget::toggler(!server.disabled)
+                            .on_toggle(move |enabled| SimpleSettingsMessage::ToggleMcpServer(toggle_name.clone(), enabled)))
+                        .push(widget::Space::with_width(8))
+                        .push(button::standard("Edit")
+                            .on_press(SimpleSettingsMessage::EditMcpServer(name.to_string())))
+                        .push(widget::Space::with_width(8))
+                        .push(button::destructive("Delete")
+                            .on_press(SimpleSettingsMessage::DeleteMcpServer(name.to_string())))
+                        .align_y(Alignment::Center)
+                )
+                .push(text(format!("Command: {} {}", server.command, server.args.join(" ")))
+                    .size(12)
+                    .class(cosmic::style::Text::Color(
+                        theme::active().cosmic().palette.neutral_6.into()
+                    )))
+                .push_maybe((!env_names.is_empty()).then(|| {
+                    // Values may be secrets; only the names are listed
+                    text(format!("Environment: {}", env_names.join(", ")))
+                        .size(12)
+                        .class(cosmic::style::Text::Color(
+                            theme::active().cosmic().palette.neutral_6.into()
+                        ))
+                }))
+                .spacing(4)
+        )
+        .padding(16)
+        .into()
+    }
+
+    fn mcp_server_section<'a>(&'a self) -> Element<'a, SimpleSettingsMessage> {
+        let title = if self.mcp_editing.is_some() { "Edit MCP Server" } else { "Add MCP Server" };
+
+        let mut args = column().spacing(8);
+        for (index, arg) in self.mcp_args.iter().enumerate() {
+            args = args.push(
+                row()
+                    .push(
+                        text_input("Argument", arg)
+                            .on_input(move |value| SimpleSettingsMessage::McpServerArgChanged(index, value))
+                            .width(Length::Fill)
+                    )
+                    .push(widget::Space::with_width(8))
+                    .push(button::standard("Remove")
+                        .on_press(SimpleSettingsMessage::RemoveMcpServerArg(index)))
+                    .align_y(Alignment::Center)
+            );
+        }
+
+        let mut env = column().spacing(8);
+        for (index, (key, value)) in self.mcp_env.iter().enumerate() {
+            let hidden = !self.mcp_env_revealed.contains(&index);
+            env = env.push(
+                row()
+                    .push(
+                        text_input("Variable", key)
+                            .on_input(move |value| SimpleSettingsMessage::McpServerEnvKeyChanged(index, value))
+                            .width(Length::FillPortion(1))
+                    )
+                    .push(widget::Space::with_width(8))
+                    .push(
+                        text_input::secure_input(
+                            "Value or ${env:NAME}",
+                            value,
+                            Some(SimpleSettingsMessage::ToggleMcpServerEnvVisible(index)),
+                            hidden,
+                        )
+                        .on_input(move |value| SimpleSettingsMessage::McpServerEnvValueChanged(index, value))
+                        .width(Length::FillPortion(2))
+                    )
+                    .push(widget::Space::with_width(8))
+                    .push(button::standard("Remove")
+                        .on_press(SimpleSettingsMessage::RemoveMcpServerEnv(index)))
+                    .align_y(Alignment::Center)
+            );
+        }
+
+        let discovered = (!self.mcp_discovered_tools.is_empty()).then(|| {
+            text(format!("Tools: {}", self.mcp_discovered_tools.join(", ")))
+                .size(12)
+                .class(cosmic::style::Text::Color(
+                    theme::active().cosmic().palette.neutral_6.into()
+                ))
+        });
+
+        container(
+            column()
+                .push(
+                    text(title)
+                        .size(16)
+                        .class(cosmic::style::Text::Color(
+                            theme::active().cosmic().palette.neutral_9.into()
+                        ))
+                )
+                .push(
+                    row()
+                        .push(
+                            text_input("Server Name", &self.mcp_name)
+                                .on_input(SimpleSettingsMessage::McpServerNameChanged)
+                                .width(Length::Fill)
+                        )
+                        .push(widget::Space::with_width(8))
+                        // stdio is the only transport the registry can start
+                        .push(text("Transport: stdio").size(12))
+                        .push(widget::Space::with_width(8))
+                        .push(widget::checkbox("Enabled", self.mcp_enabled)
+                            .on_toggle(SimpleSettingsMessage::McpServerEnabledToggled))
+                        .align_y(Alignment::Center)
+                )
+                .push(
+                    text_input("Command", &self.mcp_command)
+                        .on_input(SimpleSettingsMessage::McpServerCommandChanged)
+                )
+                .push(text("Arguments").size(12))
+                .push(args)
+                .push(button::standard("Add Argument")
+                    .on_press(SimpleSettingsMessage::AddMcpServerArg))
+                .push(text("Environment").size(12))
+                .push(env)
+                .push(button::standard("Add Variable")
+                    .on_press(SimpleSettingsMessage::AddMcpServerEnv))
+                .push_maybe(Self::status_text(&self.mcp_test_status))
+                .push_maybe(discovered)
+                .push_maybe(self.mcp_error.as_ref().map(|error| {
+                    text(format!("✗ {}", error))
+                        .size(12)
+                        .class(cosmic::style::Text::Color(
+                            theme::active().cosmic().destructive_color().into()
+                        ))
+                }))
+                .push(
+                    row()
+                        .push(widget::Space::with_width(Length::Fill))
+                        .push_maybe(self.mcp_editing.is_some().then(|| {
+                            button::standard("Cancel")
+                                .on_press(SimpleSettingsMessage::ResetMcpServer)
+                        }))
+                        .push(widget::Space::with_width(8))
+                        .push(button::standard("Test Connection")
+                            .on_press_maybe((self.mcp_test_status != ConnectionStatus::Checking)
+                                .then_some(SimpleSettingsMessage::TestMcpServer)))
+                        .push(widget::Space::with_width(8))
+                        .push(button::suggested("Save Server")
+                            .on_press(SimpleSettingsMessage::SaveMcpServer))
+                )
+                .spacing(12)
+        )
+        .padding(16)
+        .into()
+    }
+
+    fn preferences_section<'a>(&'a self, config: &'a AppConfig) -> Element<'a, SimpleSettingsMessage> {
+        let preferences = &config.preferences;
+        let themes: Vec<&'static str> = ThemePreference::ALL.iter().map(ThemePreference::label).collect();
+        let selected_theme = ThemePreference::ALL.iter().position(|theme| *theme == preferences.theme);
+
+        container(
+            column()
+                .push(
+                    text("Preferences")
+                        .size(16)
+                        .class(cosmic::style::Text::Color(
+                            theme::active().cosmic().palette.neutral_9.into()
+                        ))
+                )
+                .push(
+                    row()
+                        .push(text("Theme").width(Length::Fill))
+                        .push(widget::dropdown(themes, selected_theme, SimpleSettingsMessage::ThemeSelected))
+                        .align_y(Alignment::Center)
+                )
+                .push(
+                    widget::checkbox("Save conversations to history", preferences.save_history)
+                        .on_toggle(SimpleSettingsMessage::SaveHistoryToggled)
+                )
+                .push(
+                    widget::checkbox("Notify when a reply finishes in the background", preferences.notifications)
+                        .on_toggle(SimpleSettingsMessage::NotificationsToggled)
+                )
+                .push(
+                    widget::checkbox("Auto-scroll during streaming", preferences.auto_scroll)
+                        .on_toggle(SimpleSettingsMessage::AutoScrollToggled)
+                )
+                .spacing(12)
+        )
+        .padding(16)
+        .into()
+    }
+
+    /// Model picker: a dropdown once models are fetched, with free text as a fallback
+    fn model_row<'a>(&'a self) -> Element<'a, SimpleSettingsMessage> {
+        let mut model_row = row()
+            .push(
+                text_input("Model", &self.new_profile_model)
+                    .on_input(SimpleSettingsMessage::NewProfileModelChanged)
+                    .width(Length::Fill)
+            )
+            .push(widget::Space::with_width(8));
+
+        if !self.available_models.is_empty() {
+            model_row = model_row
+                .push(
+                    widget::dropdown(
+                        &self.available_models,
+                        self.available_models.iter().position(|m| m == self.new_profile_model.trim()),
+                        SimpleSettingsMessage::NewProfileModelSelected,
+                    )
+                )
+                .push(widget::Space::with_width(8));
+        }
+
+        model_row
+            .push(button::standard("Fetch Models")
+                .on_press_maybe((!self.connection_status.is_busy())
+                    .then_some(SimpleSettingsMessage::FetchModels)))
+            .align_y(Alignment::Center)
+            .into()
+    }
+
+    fn connection_status_text<'a>(&'a self) -> Option<Element<'a, SimpleSettingsMessage>> {
+        Self::status_text(&self.connection_status)
+    }
+
+    fn status_text<'a>(status: &ConnectionStatus) -> Option<Element<'a, SimpleSettingsMessage>> {
+        let (label, color): (String, cosmic::iced::Color) = match status {
+            ConnectionStatus::Idle => return None,
+            ConnectionStatus::Checking => (
+                "Checking…".to_string(),
+                theme::active().cosmic().palette.neutral_6.into(),
+            ),
+            ConnectionStatus::Pulling(progress) => (
+                format!("⬇ {}", progress),
+                theme::active().cosmic().palette.neutral_6.into(),
+            ),
+            ConnectionStatus::Ok(detail) => (
+                format!("✓ {}", detail),
+                theme::active().cosmic().success_color().into(),
+            ),
+            ConnectionStatus::Failed(error) => (
+                format!("✗ {}", error),
+                theme::active().cosmic().destructive_color().into(),
+            ),
+        };
+        Some(
+            text(label)
+                .size(12)
+                .class(cosmic::style::Text::Color(color))
+                .into()
+        )
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_draft_persona_validation() {
+        let mut page = SimpleSettingsPage::new();
+        page.persona_system_prompt = "  You review Rust code.  ".to_string();
+        page.persona_tools = "web__search, , filesystem ".to_string();
+        page.persona_temperature = "0.3".to_string();
+        page.persona_max_tokens = "2000".to_string();
+        let persona = page.draft_persona().unwrap();
+        assert_eq!(persona.system_prompt.as_deref(), Some("You review Rust code."));
+        assert_eq!(persona.tools, Some(vec!["web__search".to_string(), "filesystem".to_string()]));
+        assert_eq!(persona.temperature, Some(0.3));
+        assert_eq!(persona.max_tokens, Some(2000));
+
+        page.persona_tools.clear();
+        page.persona_temperature.clear();
+        page.persona_max_tokens.clear();
+        let persona = page.draft_persona().unwrap();
+        assert_eq!(persona.tools, None);
+        assert_eq!(persona.temperature, None);
+
+        page.persona_temperature = "3".to_string();
+        assert!(page.draft_persona().is_err());
+        page.persona_temperature = "warm".to_string();
+        assert!(page.draft_persona().is_err());
+        page.persona_temperature.clear();
+        page.persona_max_tokens = "0".to_string();
+        assert!(page.draft_persona().is_err());
+    }
 }